@@ -0,0 +1,72 @@
+//! A producer binds a fresh dataflow variable every round and links it to the
+//! previous one, a consumer only holds on to the newest. With a collection
+//! every few rounds the store stays the same size however long it runs.
+//!
+//! Run with `cargo bench --bench producer_consumer`.
+use single_assignment_store::{DataflowStore, Stats, Unify};
+use std::time::Instant;
+
+const ROUNDS: u64 = 1_000_000;
+const COLLECT_EVERY: u64 = 1_000;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Term {
+    Var(u64),
+    Int(u64),
+    /// A value and the variable holding the next one.
+    Cons(Vec<Term>),
+}
+
+impl Unify<u64> for Term {
+    fn key(&self) -> Option<&u64> {
+        match self {
+            Term::Var(k) => Some(k),
+            _ => None,
+        }
+    }
+    fn structure(&self) -> Option<(usize, &[Self])> {
+        match self {
+            Term::Cons(fields) => Some((0, fields)),
+            _ => None,
+        }
+    }
+}
+
+fn main() {
+    let mut store = DataflowStore::new();
+    // largest store seen right before and right after a collection
+    let mut before = Stats::default();
+    let mut after = Stats::default();
+    let start = Instant::now();
+    store.declare(0);
+    for round in 0..ROUNDS {
+        // producer: bind the current tail and open the next one
+        let next = round + 1;
+        store.declare(next);
+        store
+            .unify(&Term::Var(round), &Term::Cons(vec![Term::Int(round), Term::Var(next)]))
+            .unwrap();
+        // consumer: reads the value and moves on to the tail
+        assert!(store.get(&round).is_some());
+        if next % COLLECT_EVERY == 0 {
+            peak(&mut before, store.stats());
+            store.collect(&[next]);
+            store.compact();
+            peak(&mut after, store.stats());
+        }
+    }
+    let elapsed = start.elapsed();
+    println!("{} rounds in {:?} ({:?} per round)", ROUNDS, elapsed, elapsed / ROUNDS as u32);
+    println!("peak before collecting: {:?}", before);
+    println!("peak after collecting: {:?}", after);
+    assert!(before.keys <= COLLECT_EVERY as usize + 1);
+    assert_eq!(after.keys, 1);
+}
+
+fn peak(peak: &mut Stats, stats: Stats) {
+    peak.keys = peak.keys.max(stats.keys);
+    peak.values = peak.values.max(stats.values);
+    peak.key_capacity = peak.key_capacity.max(stats.key_capacity);
+    peak.value_capacity = peak.value_capacity.max(stats.value_capacity);
+    peak.next_index = peak.next_index.max(stats.next_index);
+}
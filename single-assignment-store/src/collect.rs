@@ -0,0 +1,181 @@
+use crate::{DataflowStore, Index, SingleAssignmentStore, Unify};
+
+use alloc::vec::Vec;
+use core::hash::Hash;
+use hashbrown::{HashMap, HashSet};
+
+/// What a [`collect`] removed.
+///
+/// [`collect`]: SingleAssignmentStore::collect
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Collected {
+    pub keys: usize,
+    pub values: usize,
+}
+
+/// Memory use of a store, see [`stats`].
+///
+/// [`stats`]: SingleAssignmentStore::stats
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub keys: usize,
+    pub values: usize,
+    /// Values someone waits on.
+    pub waited_values: usize,
+    pub key_capacity: usize,
+    pub value_capacity: usize,
+    /// The index the next value gets, [`compact`] brings it back to `values`.
+    ///
+    /// [`compact`]: SingleAssignmentStore::compact
+    pub next_index: u128,
+}
+
+impl<K, V> SingleAssignmentStore<K, V>
+where
+    K: Hash + Eq,
+{
+    ///Returns how many keys and values the map holds, and how much room it has for more.
+    pub fn stats(&self) -> Stats {
+        Stats {
+            keys: self.keys.len(),
+            values: self.data.len(),
+            waited_values: self.waiters.len(),
+            key_capacity: self.keys.capacity(),
+            value_capacity: self.data.capacity(),
+            next_index: self.max_index.0,
+        }
+    }
+    ///Renumbers the values densely from zero and releases unused capacity.
+    /// ```
+    /// use single_assignment_store::SingleAssignmentStore;
+    /// let mut map = SingleAssignmentStore::new();
+    /// for k in 0..100 {
+    ///     map.insert(k, k);
+    ///     map.remove(&k);
+    /// }
+    /// map.insert(100, 100);
+    /// map.compact();
+    /// assert_eq!(map.stats().next_index, 1);
+    /// assert_eq!(map.get(&100), Some(&100));
+    /// ```
+    pub fn compact(&mut self) {
+        let mut renumbered = HashMap::with_capacity(self.data.len());
+        let mut data = HashMap::with_capacity(self.data.len());
+        for (i, (idx, entry)) in self.data.drain().enumerate() {
+            renumbered.insert(idx, Index(i as u128));
+            data.insert(Index(i as u128), entry);
+        }
+        for idx in self.keys.values_mut() {
+            *idx = renumbered[&*idx];
+        }
+        self.waiters = self.waiters.drain().map(|(idx, tokens)| (renumbered[&idx], tokens)).collect();
+        self.max_index = Index(data.len() as u128);
+        self.data = data;
+        self.keys.shrink_to_fit();
+        self.waiters.shrink_to_fit();
+    }
+}
+
+impl<K, V> SingleAssignmentStore<K, Option<V>>
+where
+    K: Hash + Eq + Clone,
+    V: Unify<K>,
+{
+    ///Removes every key that cannot be reached from `roots`, following the variables bound
+    /// values refer to, and every value no key is left to. Whoever waits on a removed value
+    /// is forgotten.
+    /// ```
+    /// use single_assignment_store::{SingleAssignmentStore, Unify};
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// enum Term { Var(u32), Int(i64) }
+    /// impl Unify<u32> for Term {
+    ///     fn key(&self) -> Option<&u32> {
+    ///         match self { Term::Var(k) => Some(k), _ => None }
+    ///     }
+    /// }
+    /// let mut map = SingleAssignmentStore::new();
+    /// map.insert(1, Some(Term::Var(2)));
+    /// map.insert(2, Some(Term::Int(3)));
+    /// map.insert(3, None);
+    /// let collected = map.collect(&[1]);
+    /// assert_eq!((collected.keys, collected.values), (1, 1));
+    /// assert_eq!(map.get(&2), Some(&Some(Term::Int(3))));
+    /// assert!(!map.contains_key(&3));
+    /// ```
+    pub fn collect(&mut self, roots: &[K]) -> Collected {
+        let marked = self.mark(roots);
+        let keys = self.keys.len();
+        let values = self.data.len();
+        self.keys.retain(|k, _| marked.contains(k));
+        let mut counts: HashMap<Index, usize> = HashMap::new();
+        for idx in self.keys.values() {
+            *counts.entry(*idx).or_default() += 1;
+        }
+        self.data.retain(|idx, (count, _)| match counts.get(idx) {
+            Some(c) => {
+                *count = *c;
+                true
+            }
+            None => false,
+        });
+        let data = &self.data;
+        self.waiters.retain(|idx, _| data.contains_key(idx));
+        Collected {
+            keys: keys - self.keys.len(),
+            values: values - self.data.len(),
+        }
+    }
+
+    // keys reachable from `roots`
+    fn mark(&self, roots: &[K]) -> HashSet<K> {
+        let mut marked = HashSet::new();
+        let mut pending: Vec<K> = roots.to_vec();
+        while let Some(k) = pending.pop() {
+            if marked.contains(&k) {
+                continue;
+            }
+            if let Some(Some(v)) = self.get(&k) {
+                let mut values = vec![v];
+                while let Some(v) = values.pop() {
+                    if let Some(referred) = v.key() {
+                        pending.push(referred.clone());
+                    }
+                    if let Some((_, fields)) = v.structure() {
+                        values.extend(fields);
+                    }
+                }
+            }
+            marked.insert(k);
+        }
+        marked
+    }
+}
+
+impl<K, V> DataflowStore<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Unify<K>,
+{
+    ///Removes the variables that cannot be reached from `roots`, see
+    /// [SingleAssignmentStore]::[`collect`].
+    ///
+    /// [`collect`]: SingleAssignmentStore::collect
+    pub fn collect(&mut self, roots: &[K]) -> Collected {
+        self.vars.collect(roots)
+    }
+}
+
+impl<K, V> DataflowStore<K, V>
+where
+    K: Hash + Eq,
+{
+    pub fn stats(&self) -> Stats {
+        self.vars.stats()
+    }
+    ///Renumbers the variables densely, see [SingleAssignmentStore]::[`compact`].
+    ///
+    /// [`compact`]: SingleAssignmentStore::compact
+    pub fn compact(&mut self) {
+        self.vars.compact();
+    }
+}
@@ -0,0 +1,349 @@
+use crate::{BindError, Unify, UnifyError};
+
+use core::future::Future;
+use core::hash::Hash;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::collections::HashMap;
+use std::vec::Vec;
+use sync::{Arc, AtomicU64, AtomicU8, Condvar, Mutex, Ordering, RwLock, UnsafeCell};
+
+// loom checks every interleaving of these when built with `--cfg loom`
+mod sync {
+    #[cfg(loom)]
+    pub(crate) use loom::{
+        cell::UnsafeCell,
+        sync::atomic::{AtomicU64, AtomicU8, Ordering},
+        sync::{Arc, Condvar, Mutex, RwLock},
+    };
+    #[cfg(not(loom))]
+    pub(crate) use std::sync::{
+        atomic::{AtomicU64, AtomicU8, Ordering},
+        Arc, Condvar, Mutex, RwLock,
+    };
+
+    #[cfg(not(loom))]
+    pub(crate) struct UnsafeCell<T>(core::cell::UnsafeCell<T>);
+
+    #[cfg(not(loom))]
+    impl<T> UnsafeCell<T> {
+        pub(crate) fn new(t: T) -> Self {
+            Self(core::cell::UnsafeCell::new(t))
+        }
+        pub(crate) fn with<R>(&self, f: impl FnOnce(*const T) -> R) -> R {
+            f(self.0.get())
+        }
+        pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+            f(self.0.get())
+        }
+    }
+}
+
+const UNBOUND: u8 = 0;
+// the thread that won the race is writing the content
+const WRITING: u8 = 1;
+const BOUND: u8 = 2;
+const FORWARDED: u8 = 3;
+
+enum Content<V> {
+    Value(V),
+    /// Unified with another variable, which holds the value from now on.
+    Forward(Arc<Var<V>>),
+}
+
+// A once-cell: it leaves `UNBOUND` exactly once, for a value or a forward.
+struct Var<V> {
+    /// Forwards always point to a higher id or to a bound variable, so they never form a cycle.
+    id: u64,
+    state: AtomicU8,
+    content: UnsafeCell<Option<Content<V>>>,
+    wakers: Mutex<Vec<Waker>>,
+    settled: Condvar,
+}
+
+// the content is written once, before `state` is released, and only read after
+// `state` is acquired as settled
+unsafe impl<V: Send + Sync> Sync for Var<V> {}
+
+impl<V> Var<V> {
+    fn new(id: u64) -> Self {
+        Var {
+            id,
+            state: AtomicU8::new(UNBOUND),
+            content: UnsafeCell::new(None),
+            wakers: Mutex::new(Vec::new()),
+            settled: Condvar::new(),
+        }
+    }
+
+    fn settle(&self, content: Content<V>) -> Result<(), Content<V>> {
+        if self.state.compare_exchange(UNBOUND, WRITING, Ordering::AcqRel, Ordering::Acquire).is_err() {
+            return Err(content);
+        }
+        let state = match content {
+            Content::Value(_) => BOUND,
+            Content::Forward(_) => FORWARDED,
+        };
+        self.content.with_mut(|content_ptr| unsafe { *content_ptr = Some(content) });
+        self.state.store(state, Ordering::Release);
+        // waiters check the state with the lock held, so none of them misses this
+        let wakers = core::mem::take(&mut *self.wakers.lock().unwrap());
+        self.settled.notify_all();
+        for waker in wakers {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    fn content(&self) -> Option<&Content<V>> {
+        match self.state.load(Ordering::Acquire) {
+            BOUND | FORWARDED => self.content.with(|content_ptr| unsafe { (*content_ptr).as_ref() }),
+            _ => None,
+        }
+    }
+
+    fn value(&self) -> Option<&V> {
+        match self.content() {
+            Some(Content::Value(v)) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn is_settled(&self) -> bool {
+        self.state.load(Ordering::Acquire) >= BOUND
+    }
+
+    // follows the forwards from `var`, loom's `Arc` cannot be a receiver
+    fn root(var: &Arc<Self>) -> Arc<Self> {
+        let mut var = var.clone();
+        while let Some(Content::Forward(next)) = var.content() {
+            let next = next.clone();
+            var = next;
+        }
+        var
+    }
+
+    fn wait_settled(&self) {
+        let mut wakers = self.wakers.lock().unwrap();
+        while !self.is_settled() {
+            wakers = self.settled.wait(wakers).unwrap();
+        }
+    }
+}
+
+enum Walked<V> {
+    Unbound(Arc<Var<V>>),
+    Value(V),
+}
+
+/// A store of dataflow variables that threads share without locking to bind or read them.
+///
+/// Every variable is an atomic once-cell, bound to a value or forwarded to the variable it
+/// was unified with. Only declaring and aliasing keys lock the key table.
+///
+/// ```
+/// use single_assignment_store::ConcurrentSingleAssignmentStore;
+/// use std::sync::Arc;
+/// let store = Arc::new(ConcurrentSingleAssignmentStore::<u32, i64>::new());
+/// store.declare(1);
+/// let reader = {
+///     let store = store.clone();
+///     std::thread::spawn(move || store.wait(&1))
+/// };
+/// store.bind(&1, 5).unwrap();
+/// assert_eq!(reader.join().unwrap(), Some(5));
+/// ```
+pub struct ConcurrentSingleAssignmentStore<K, V> {
+    vars: RwLock<HashMap<K, Arc<Var<V>>>>,
+    next_id: AtomicU64,
+}
+
+impl<K, V> Default for ConcurrentSingleAssignmentStore<K, V> {
+    fn default() -> Self {
+        ConcurrentSingleAssignmentStore {
+            vars: RwLock::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+}
+
+impl<K, V> ConcurrentSingleAssignmentStore<K, V>
+where
+    K: Hash + Eq,
+{
+    ///Creates an empty [ConcurrentSingleAssignmentStore].
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn var(&self, k: &K) -> Option<Arc<Var<V>>> {
+        self.vars.read().unwrap().get(k).cloned()
+    }
+    ///Adds `k` as an unbound key. Returns false, changing nothing, if `k` is already declared.
+    pub fn declare(&self, k: K) -> bool {
+        let mut vars = self.vars.write().unwrap();
+        if vars.contains_key(&k) {
+            return false;
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        vars.insert(k, Arc::new(Var::new(id)));
+        true
+    }
+    ///Adds `alias` as another key of the variable at `k`. Returns `alias` if `k` is unknown
+    /// or `alias` is already declared.
+    pub fn alias(&self, k: &K, alias: K) -> Result<(), K> {
+        let mut vars = self.vars.write().unwrap();
+        if vars.contains_key(&alias) {
+            return Err(alias);
+        }
+        match vars.get(k).cloned() {
+            Some(var) => {
+                vars.insert(alias, var);
+                Ok(())
+            }
+            None => Err(alias),
+        }
+    }
+    pub fn contains_key(&self, k: &K) -> bool {
+        self.vars.read().unwrap().contains_key(k)
+    }
+    pub fn is_bound(&self, k: &K) -> bool {
+        self.var(k).is_some_and(|var| Var::root(&var).value().is_some())
+    }
+    ///Returns a future resolving to the value of `k` once it is bound, or [`None`] if `k`
+    /// is unknown.
+    pub fn wait_async(&self, k: &K) -> Option<Wait<V>> {
+        self.var(k).map(|var| Wait { var })
+    }
+}
+
+impl<K, V> ConcurrentSingleAssignmentStore<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    ///Returns the value bound to `k`, or [`None`] if `k` is unbound or unknown.
+    pub fn get(&self, k: &K) -> Option<V> {
+        Var::root(&self.var(k)?).value().cloned()
+    }
+    ///Blocks until `k` is bound and returns its value, or [`None`] if `k` is unknown.
+    pub fn wait(&self, k: &K) -> Option<V> {
+        let var = self.var(k)?;
+        loop {
+            let root = Var::root(&var);
+            if let Some(v) = root.value() {
+                return Some(v.clone());
+            }
+            root.wait_settled();
+        }
+    }
+    ///Binds the unbound key `k`, and every variable unified with it, to `value`. When
+    /// several threads race to bind one variable exactly one of them succeeds, the others
+    /// get the winning value back in [`BindError::AlreadyBound`].
+    pub fn bind(&self, k: &K, value: V) -> Result<(), BindError<K, V>> {
+        let var = self.var(k).ok_or_else(|| BindError::UnknownKey(k.clone()))?;
+        let mut value = value;
+        loop {
+            let root = Var::root(&var);
+            match root.settle(Content::Value(value)) {
+                Ok(()) => return Ok(()),
+                Err(content) => {
+                    value = match content {
+                        Content::Value(v) => v,
+                        Content::Forward(_) => unreachable!("settled with a value"),
+                    };
+                    root.wait_settled();
+                    if let Some(bound) = root.value() {
+                        return Err(BindError::AlreadyBound { key: k.clone(), bound: bound.clone(), value });
+                    }
+                    // forwarded in the meantime, bind where it now points
+                }
+            }
+        }
+    }
+}
+
+impl<K, V> ConcurrentSingleAssignmentStore<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Unify<K>,
+{
+    fn walk(&self, v: &V) -> Result<Walked<V>, UnifyError<K, V>> {
+        let mut v = v.clone();
+        loop {
+            let Some(k) = v.key() else {
+                return Ok(Walked::Value(v));
+            };
+            let var = Var::root(&self.var(k).ok_or_else(|| UnifyError::UnknownKey(k.clone()))?);
+            match var.value() {
+                Some(bound) => v = bound.clone(),
+                None => return Ok(Walked::Unbound(var)),
+            }
+        }
+    }
+    ///Makes `a` and `b` equal, like [SingleAssignmentStore]::[`unify`]. A step that loses a
+    /// race against another thread is retried against what that thread did.
+    ///
+    /// [SingleAssignmentStore]: crate::SingleAssignmentStore
+    ///
+    /// [`unify`]: crate::SingleAssignmentStore::unify
+    pub fn unify(&self, a: &V, b: &V) -> Result<(), UnifyError<K, V>> {
+        let mut pending = vec![(a.clone(), b.clone())];
+        while let Some((a, b)) = pending.pop() {
+            let settled = match (self.walk(&a)?, self.walk(&b)?) {
+                (Walked::Unbound(x), Walked::Unbound(y)) => {
+                    if Arc::ptr_eq(&x, &y) {
+                        continue;
+                    }
+                    let (from, to) = if x.id < y.id { (x, y) } else { (y, x) };
+                    from.settle(Content::Forward(to)).map_err(|_| from)
+                }
+                (Walked::Unbound(x), Walked::Value(v)) | (Walked::Value(v), Walked::Unbound(x)) => {
+                    x.settle(Content::Value(v)).map_err(|_| x)
+                }
+                (Walked::Value(x), Walked::Value(y)) => {
+                    match (x.structure(), y.structure()) {
+                        (Some((cx, fx)), Some((cy, fy))) if cx == cy && fx.len() == fy.len() => {
+                            pending.extend(fx.iter().cloned().zip(fy.iter().cloned()));
+                        }
+                        (None, None) if x == y => {}
+                        _ => return Err(UnifyError::Mismatch(x, y)),
+                    }
+                    Ok(())
+                }
+            };
+            if let Err(lost) = settled {
+                lost.wait_settled();
+                pending.push((a, b));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Resolves to the value of a variable once it is bound, see
+/// [ConcurrentSingleAssignmentStore]::[`wait_async`].
+///
+/// [`wait_async`]: ConcurrentSingleAssignmentStore::wait_async
+pub struct Wait<V> {
+    var: Arc<Var<V>>,
+}
+
+impl<V: Clone> Future for Wait<V> {
+    type Output = V;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<V> {
+        loop {
+            let root = Var::root(&self.var);
+            if let Some(v) = root.value() {
+                return Poll::Ready(v.clone());
+            }
+            let mut wakers = root.wakers.lock().unwrap();
+            if root.is_settled() {
+                continue;
+            }
+            wakers.push(cx.waker().clone());
+            return Poll::Pending;
+        }
+    }
+}
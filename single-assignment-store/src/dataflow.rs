@@ -0,0 +1,178 @@
+use crate::{SingleAssignmentStore, Token, Unify, UnifyError};
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::Hash;
+
+/// Why a value could not be bound.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BindError<K, V> {
+    /// A key that was never declared.
+    UnknownKey(K),
+    /// The key already holds `bound`, so `value` was not bound.
+    AlreadyBound { key: K, bound: V, value: V },
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Display for BindError<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindError::UnknownKey(k) => write!(f, "unknown variable {:?}", k),
+            BindError::AlreadyBound { key, bound, value } => {
+                write!(f, "variable {:?} is already bound to {:?}, cannot bind it to {:?}", key, bound, value)
+            }
+        }
+    }
+}
+
+/// A [SingleAssignmentStore] where every key is either unbound or bound, and a bound key
+/// keeps its value for good: there is no mutable access to values, binding a bound key
+/// fails and keys can only be aliased onto keys that are not declared yet.
+///
+/// ```
+/// use single_assignment_store::{BindError, DataflowStore};
+/// let mut store = DataflowStore::new();
+/// store.declare(1);
+/// store.alias(&1, 2).unwrap();
+/// store.bind(&2, "foo").unwrap();
+/// assert_eq!(store.get(&1), Some(&"foo"));
+/// assert_eq!(
+///     store.bind(&1, "bar"),
+///     Err(BindError::AlreadyBound { key: 1, bound: "foo", value: "bar" })
+/// );
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DataflowStore<K, V>
+where
+    K: Hash + Eq,
+{
+    pub(crate) vars: SingleAssignmentStore<K, Option<V>>,
+}
+
+impl<K, V> Default for DataflowStore<K, V>
+where
+    K: Hash + Eq,
+{
+    fn default() -> Self {
+        DataflowStore { vars: SingleAssignmentStore::new() }
+    }
+}
+
+impl<K, V> DataflowStore<K, V>
+where
+    K: Hash + Eq,
+{
+    ///Creates an empty [DataflowStore].
+    pub fn new() -> Self {
+        Default::default()
+    }
+    ///Adds `k` as an unbound key. Returns false, changing nothing, if `k` is already declared.
+    pub fn declare(&mut self, k: K) -> bool {
+        if self.vars.contains_key(&k) {
+            return false;
+        }
+        self.vars.insert(k, None);
+        true
+    }
+    pub fn contains_key(&self, k: &K) -> bool {
+        self.vars.contains_key(k)
+    }
+    pub fn is_bound(&self, k: &K) -> bool {
+        matches!(self.vars.get(k), Some(Some(_)))
+    }
+    ///Returns the value bound to `k`, or [`None`] if `k` is unbound or unknown.
+    pub fn get(&self, k: &K) -> Option<&V> {
+        self.vars.get(k).and_then(Option::as_ref)
+    }
+    ///Adds `alias` as another key of the variable at `k`, bound or not. Returns `alias` if
+    /// `k` is unknown or `alias` is already declared.
+    pub fn alias(&mut self, k: &K, alias: K) -> Result<(), K> {
+        if self.vars.contains_key(&alias) {
+            return Err(alias);
+        }
+        self.vars.alias(k, alias).map(|_| ())
+    }
+    ///An iterator visiting all keys and their values, [`None`] for unbound keys.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, Option<&V>)> {
+        self.vars.iter().map(|(k, v)| (k, v.as_ref()))
+    }
+    ///Registers `token` to be woken once `k` is bound, see [SingleAssignmentStore]::[`wait_on`].
+    /// Returns false, registering nothing, if `k` is unknown or already bound.
+    ///
+    /// [`wait_on`]: SingleAssignmentStore::wait_on
+    pub fn wait_on(&mut self, k: &K, token: Token) -> bool {
+        !self.is_bound(k) && self.vars.wait_on(k, token)
+    }
+}
+
+impl<K, V> DataflowStore<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    ///Binds the unbound key `k`, and every alias of it, to `value`. Returns the tokens that
+    /// waited on `k`, in ascending order.
+    pub fn bind(&mut self, k: &K, value: V) -> Result<Vec<Token>, BindError<K, V>> {
+        match self.vars.get(k) {
+            None => Err(BindError::UnknownKey(k.clone())),
+            Some(Some(bound)) => Err(BindError::AlreadyBound { key: k.clone(), bound: bound.clone(), value }),
+            Some(None) => {
+                let mut woken = self.vars.bind_key(k, value);
+                woken.sort_unstable();
+                Ok(woken)
+            }
+        }
+    }
+}
+
+impl<K, V> DataflowStore<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Unify<K>,
+{
+    ///Follows `v` through bound variables, see [SingleAssignmentStore]::[`walk`].
+    ///
+    /// [`walk`]: SingleAssignmentStore::walk
+    pub fn walk(&self, v: &V) -> Result<V, UnifyError<K, V>> {
+        self.vars.walk(v)
+    }
+    ///Makes `a` and `b` equal, see [SingleAssignmentStore]::[`unify`]. Unlike [`bind`],
+    /// a bound variable unifies with a value equal to its own.
+    ///
+    /// [`unify`]: SingleAssignmentStore::unify
+    ///
+    /// [`bind`]: DataflowStore::bind
+    pub fn unify(&mut self, a: &V, b: &V) -> Result<Vec<Token>, UnifyError<K, V>> {
+        self.vars.unify(a, b)
+    }
+    ///Whether `a` and `b` are the same variable.
+    pub fn same_class(&self, a: &K, b: &K) -> bool {
+        self.vars.same_class(a, b)
+    }
+}
+
+impl<K, V> SingleAssignmentStore<K, Option<V>>
+where
+    K: Hash + Eq,
+{
+    // binds the element of `k`, for every key sharing it, returns who waited on it
+    pub(crate) fn bind_key(&mut self, k: &K, v: V) -> Vec<Token> {
+        let Some(&idx) = self.keys.get(k) else {
+            return Vec::new();
+        };
+        if let Some((_, slot)) = self.data.get_mut(&idx) {
+            *slot = Some(v);
+        }
+        self.wake_index(idx)
+    }
+}
+
+impl<K, V> PartialEq for DataflowStore<K, V>
+where
+    K: Hash + Eq,
+    V: PartialEq,
+{
+    fn eq(&self, rhs: &Self) -> bool {
+        self.vars == rhs.vars
+    }
+}
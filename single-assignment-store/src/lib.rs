@@ -24,10 +24,18 @@
 #[macro_use]
 extern crate alloc;
 extern crate hashbrown;
+#[cfg(feature = "std")]
+extern crate std;
 
 use core::hash::Hash;
 pub use entry::{Entry, OccupiedEntry, VacantEntry};
 pub use iter::Iter;
+pub use collect::{Collected, Stats};
+#[cfg(feature = "std")]
+pub use concurrent::{ConcurrentSingleAssignmentStore, Wait};
+pub use dataflow::{BindError, DataflowStore};
+pub use snapshot::{Delta, Snapshot};
+pub use unify::{Unify, UnifyError};
 use core::borrow::Borrow;
 use core::fmt::Debug;
 use hashbrown::HashMap;
@@ -44,6 +52,42 @@ pub mod entry;
 ///
 /// [`iter`]: SingleAssignmentStore::iter
 pub mod iter;
+/// Provides [`Stats`] and [`Collected`], see [`stats`] and [`collect`] for more info.
+///
+/// [`Stats`]: collect::Stats
+///
+/// [`Collected`]: collect::Collected
+///
+/// [`stats`]: SingleAssignmentStore::stats
+///
+/// [`collect`]: SingleAssignmentStore::collect
+pub mod collect;
+/// Provides [`ConcurrentSingleAssignmentStore`], a store of dataflow variables shared between
+/// threads, which can block or `.await` until a variable is bound.
+///
+/// [`ConcurrentSingleAssignmentStore`]: concurrent::ConcurrentSingleAssignmentStore
+#[cfg(feature = "std")]
+pub mod concurrent;
+/// Provides [`DataflowStore`], a store whose keys are bound at most once.
+///
+/// [`DataflowStore`]: dataflow::DataflowStore
+pub mod dataflow;
+/// Provides [`Snapshot`] and [`Delta`], see [`snapshot`] and [`delta`] for more info.
+///
+/// [`Snapshot`]: snapshot::Snapshot
+///
+/// [`Delta`]: snapshot::Delta
+///
+/// [`snapshot`]: SingleAssignmentStore::snapshot
+///
+/// [`delta`]: SingleAssignmentStore::delta
+pub mod snapshot;
+/// Provides [`Unify`], the values a store of dataflow variables can hold. see [`unify`] for more info.
+///
+/// [`Unify`]: unify::Unify
+///
+/// [`unify`]: SingleAssignmentStore::unify
+pub mod unify;
 #[cfg(test)]
 mod tests;
 
@@ -53,6 +97,11 @@ mod tests;
 #[derive(Hash, PartialEq, Eq, Clone, Copy)]
 struct Index(u128);
 
+/// Identifies whoever waits on a key, see [`wait_on`].
+///
+/// [`wait_on`]: SingleAssignmentStore::wait_on
+pub type Token = u64;
+
 #[derive(Clone)]
 /// A wrapper over [HashMap] that allows for multiple keys to point to a single element,
 /// providing some additional utilities to make working with multiple keys easier.
@@ -65,6 +114,7 @@ where
     keys: HashMap<K, Index>,
     data: HashMap<Index, (usize, V)>,
     max_index: Index,
+    waiters: HashMap<Index, Vec<Token>>,
 }
 
 impl<K, V> Default for SingleAssignmentStore<K, V>
@@ -76,6 +126,7 @@ where
             keys: HashMap::new(),
             data: HashMap::new(),
             max_index: Index(0),
+            waiters: HashMap::new(),
         }
     }
 }
@@ -107,10 +158,10 @@ where
     /// there are no other keys to that value. otherwise returns [`None`].
     pub fn insert(&mut self, k: K, v: V) -> Option<V> {
         if self.contains_key(&k) {
-            let idx = self.keys.get(&k).unwrap();
-            let (count, _) = self.data.get_mut(idx).unwrap();
+            let idx = *self.keys.get(&k).unwrap();
+            let (count, _) = self.data.get_mut(&idx).unwrap();
             if *count <= 1 {
-                self.data.insert(*idx, (1, v)).map(|(_, v)| v)
+                self.data.insert(idx, (1, v)).map(|(_, v)| v)
             } else {
                 *count -= 1;
                 let new_idx = self.max_index;
@@ -147,10 +198,10 @@ where
     {
         if self.contains_key(k) {
             let idx = *self.keys.get(k).unwrap();
-            let (count, v) = self.data.get_mut(&idx).unwrap();
+            let (count, _) = self.data.get_mut(&idx).unwrap();
             *count += 1;
             self.keys.insert(alias, idx);
-            Ok(v)
+            Ok(self.data.get_mut(&idx).map(|(_, v)| v).unwrap())
         } else {
             Err(alias)
         }
@@ -174,12 +225,12 @@ where
     {
         if self.contains_key(k) {
             let idx = *self.keys.get(k).unwrap();
-            let (count, v) = self.data.get_mut(&idx).unwrap();
+            let (count, _) = self.data.get_mut(&idx).unwrap();
             for alias in aliases {
                 *count += 1;
                 self.keys.insert(alias, idx);
             }
-            Ok(v)
+            Ok(self.data.get_mut(&idx).map(|(_, v)| v).unwrap())
         } else {
             Err(aliases)
         }
@@ -254,7 +305,8 @@ where
             .map(|(_, v)| v)
     }
     ///inserts a new value, pairs it to a list of keys, and returns the values that existed
-    /// at each key if there are no other keys to that value.
+    /// at each key if there are no other keys to that value. Whoever waited on a returned
+    /// value waits on the new one.
     /// ```
     /// use single_assignment_store::SingleAssignmentStore;
     /// let mut map = SingleAssignmentStore::new();
@@ -269,17 +321,20 @@ where
         let mut new_count = 0;
         for k in ks {
             if self.contains_key(&k) {
-                let idx = self.keys.get(&k).unwrap();
-                let (count, _) = self.data.get_mut(idx).unwrap();
+                let idx = *self.keys.get(&k).unwrap();
+                let (count, _) = self.data.get_mut(&idx).unwrap();
                 if *count <= 1 {
-                    if let Some((_, v)) = self.data.remove(idx) {
+                    if let Some((_, v)) = self.data.remove(&idx) {
                         bumped.push(v);
                     }
+                    if let Some(tokens) = self.waiters.remove(&idx) {
+                        self.add_waiters(new_idx, tokens);
+                    }
                 } else {
                     *count -= 1;
-                    new_count += 1;
-                    self.keys.insert(k, new_idx);
                 }
+                new_count += 1;
+                self.keys.insert(k, new_idx);
             } else {
                 new_count += 1;
                 self.keys.insert(k, new_idx);
@@ -289,17 +344,18 @@ where
         bumped
     }
     ///Removes a key from the map, returning the value at that key if it existed in the map
-    /// and no other keys share that value.
+    /// and no other keys share that value. Whoever waited on that value is forgotten.
     pub fn remove<Q: ?Sized>(&mut self, k: &Q) -> Option<V>
     where
         K: Borrow<Q>,
         Q: Hash + Eq,
     {
         if self.contains_key(k) {
-            let idx = self.keys.get(k).unwrap();
-            let (count, _) = self.data.get_mut(idx).unwrap();
+            let idx = *self.keys.get(k).unwrap();
+            let (count, _) = self.data.get_mut(&idx).unwrap();
             if *count == 1 {
-                let result = self.data.remove(idx).map(|(_, v)| v);
+                let result = self.data.remove(&idx).map(|(_, v)| v);
+                self.waiters.remove(&idx);
                 self.keys.remove(k);
                 result
             } else {
@@ -326,6 +382,56 @@ where
         }
         bumped
     }
+    ///Registers `token` to be woken once the value at `k` is bound through [`unify`], which
+    /// returns the tokens it woke. A token waits on the value rather than on `k`, so binding
+    /// any key sharing it wakes the token, and waits at most once on each value. Returns
+    /// false, registering nothing, if `k` is not in the map.
+    ///
+    /// Inserting, aliasing and writing through mutable references wake nobody.
+    /// ```
+    /// use single_assignment_store::{SingleAssignmentStore, Unify};
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// enum Term { Var(u32), Int(i64) }
+    /// impl Unify<u32> for Term {
+    ///     fn key(&self) -> Option<&u32> {
+    ///         match self { Term::Var(k) => Some(k), _ => None }
+    ///     }
+    /// }
+    /// let mut map = SingleAssignmentStore::new();
+    /// map.insert(1, None);
+    /// map.alias(&1, 2).unwrap();
+    /// assert!(map.wait_on(&2, 20));
+    /// assert!(!map.wait_on(&3, 30));
+    /// assert_eq!(map.unify(&Term::Var(1), &Term::Int(4)), Ok(vec![20]));
+    /// ```
+    /// [`unify`]: SingleAssignmentStore::unify
+    pub fn wait_on<Q: ?Sized>(&mut self, k: &Q, token: Token) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        match self.keys.get(k) {
+            Some(&idx) => {
+                self.add_waiters(idx, [token]);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // makes `tokens` wait on the value at `idx`, each once
+    pub(crate) fn add_waiters(&mut self, idx: Index, tokens: impl IntoIterator<Item = Token>) {
+        let waiters = self.waiters.entry(idx).or_default();
+        for token in tokens {
+            if !waiters.contains(&token) {
+                waiters.push(token);
+            }
+        }
+    }
+    // the tokens waiting on the value at `idx`, which no longer wait
+    pub(crate) fn wake_index(&mut self, idx: Index) -> Vec<Token> {
+        self.waiters.remove(&idx).unwrap_or_default()
+    }
     ///Equivalent to [HashMap]::[`entry`].
     ///
     /// [`entry`]: HashMap::entry
@@ -0,0 +1,154 @@
+use crate::{Index, SingleAssignmentStore};
+
+use alloc::vec::Vec;
+use core::hash::Hash;
+use hashbrown::HashMap;
+
+/// The keys and values of a store at one point, each value with every key sharing it.
+/// Turn it back into a store with [`From`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot<K, V> {
+    pub groups: Vec<(Vec<K>, V)>,
+}
+
+/// The changes made to a store since a [`Snapshot`], see [`delta`].
+///
+/// [`delta`]: SingleAssignmentStore::delta
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Delta<K, V> {
+    /// Keys that are gone.
+    pub removed: Vec<K>,
+    /// Values that are new or changed, or whose keys changed.
+    pub groups: Vec<(Vec<K>, V)>,
+}
+
+impl<K, V> Delta<K, V> {
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.groups.is_empty()
+    }
+}
+
+impl<K, V> SingleAssignmentStore<K, V>
+where
+    K: Hash + Eq,
+{
+    // every value with the keys sharing it
+    fn groups(&self) -> Vec<(Vec<&K>, &V)> {
+        let mut groups: HashMap<Index, Vec<&K>> = HashMap::with_capacity(self.data.len());
+        for (k, idx) in self.keys.iter() {
+            groups.entry(*idx).or_default().push(k);
+        }
+        groups
+            .into_iter()
+            .filter_map(|(idx, keys)| self.data.get(&idx).map(|(_, v)| (keys, v)))
+            .collect()
+    }
+    ///Takes a [`Snapshot`] of the map, keeping which keys share a value. Whoever waits on a
+    /// key is not part of it.
+    /// ```
+    /// use single_assignment_store::SingleAssignmentStore;
+    /// let mut map = SingleAssignmentStore::from([(vec![1, 2], "foo")]);
+    /// let snapshot = map.snapshot();
+    /// let mut restored = SingleAssignmentStore::from(snapshot);
+    /// assert_eq!(restored, map);
+    /// *restored.get_mut(&1).unwrap() = "bar";
+    /// assert_eq!(restored.get(&2), Some(&"bar"));
+    /// ```
+    pub fn snapshot(&self) -> Snapshot<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let groups = self
+            .groups()
+            .into_iter()
+            .map(|(keys, v)| (keys.into_iter().cloned().collect(), v.clone()))
+            .collect();
+        Snapshot { groups }
+    }
+    ///Returns what changed since `since` was taken: the keys removed, and every value that
+    /// was added, changed or gained or lost keys, along with all of its keys.
+    /// ```
+    /// use single_assignment_store::SingleAssignmentStore;
+    /// let mut map = SingleAssignmentStore::from([(vec![1, 2], "foo"), (vec![3], "bar")]);
+    /// let checkpoint = map.snapshot();
+    /// map.insert(4, "baz");
+    /// map.remove(&3);
+    /// let delta = map.delta(&checkpoint);
+    /// assert_eq!(delta.removed, vec![3]);
+    /// assert_eq!(delta.groups, vec![(vec![4], "baz")]);
+    ///
+    /// let mut restored = SingleAssignmentStore::from(checkpoint);
+    /// restored.apply(delta);
+    /// assert_eq!(restored, map);
+    /// ```
+    pub fn delta(&self, since: &Snapshot<K, V>) -> Delta<K, V>
+    where
+        K: Clone,
+        V: Clone + PartialEq,
+    {
+        let mut group_of: HashMap<&K, usize> = HashMap::new();
+        for (i, (keys, _)) in since.groups.iter().enumerate() {
+            for k in keys {
+                group_of.insert(k, i);
+            }
+        }
+        let removed = group_of.keys().filter(|k| !self.contains_key(**k)).map(|k| (*k).clone()).collect();
+        let groups = self
+            .groups()
+            .into_iter()
+            .filter(|(keys, v)| {
+                let Some(&i) = group_of.get(keys[0]) else {
+                    return true;
+                };
+                let (old_keys, old) = &since.groups[i];
+                old_keys.len() != keys.len() || *old != **v || keys.iter().any(|k| group_of.get(*k) != Some(&i))
+            })
+            .map(|(keys, v)| (keys.into_iter().cloned().collect(), v.clone()))
+            .collect();
+        Delta { removed, groups }
+    }
+    ///Applies the changes of a [`Delta`] taken from a map that was equal to this one.
+    pub fn apply(&mut self, delta: Delta<K, V>) {
+        for k in delta.removed {
+            self.remove(&k);
+        }
+        for (keys, v) in delta.groups {
+            self.insert_many(keys, v);
+        }
+    }
+}
+
+impl<K, V> From<Snapshot<K, V>> for SingleAssignmentStore<K, V>
+where
+    K: Hash + Eq,
+{
+    fn from(snapshot: Snapshot<K, V>) -> Self {
+        Self::from_iter(snapshot.groups)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K, V> serde::Serialize for SingleAssignmentStore<K, V>
+where
+    K: Hash + Eq + serde::Serialize,
+    V: serde::Serialize,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&self.groups(), serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, V> serde::Deserialize<'de> for SingleAssignmentStore<K, V>
+where
+    K: Hash + Eq + serde::Deserialize<'de>,
+    V: serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let groups: Vec<(Vec<K>, V)> = serde::Deserialize::deserialize(deserializer)?;
+        Ok(Self::from_iter(groups))
+    }
+}
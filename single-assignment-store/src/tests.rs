@@ -1,13 +1,8 @@
 #![cfg_attr(feature = "no_std", no_std)]
-use crate::SingleAssignmentStore;
+use crate::{BindError, Collected, DataflowStore, SingleAssignmentStore, Snapshot, Stats, Unify, UnifyError};
+use alloc::vec::Vec;
 
-#[cfg(not(feature = "std"))]
-//use alloc::vec::Vec;
-
-#[cfg(not(feature = "std"))]
 use alloc::string::String;
-
-#[cfg(not(feature = "std"))]
 use alloc::string::ToString;
 
 #[test]
@@ -127,3 +122,469 @@ fn entry_default_test() {
     map.entry(7).or_default();
     assert_eq!(Some(&String::new()), map.get(&7));
 }
+
+#[derive(Clone, Debug, PartialEq)]
+enum Term {
+    Var(u32),
+    Int(i64),
+    Pair(Vec<Term>),
+}
+
+impl Unify<u32> for Term {
+    fn key(&self) -> Option<&u32> {
+        match self {
+            Term::Var(k) => Some(k),
+            _ => None,
+        }
+    }
+    fn structure(&self) -> Option<(usize, &[Self])> {
+        match self {
+            Term::Pair(fields) => Some((0, fields)),
+            _ => None,
+        }
+    }
+}
+
+fn variables(keys: &[u32]) -> SingleAssignmentStore<u32, Option<Term>> {
+    let mut map = SingleAssignmentStore::new();
+    for k in keys {
+        map.insert(*k, None);
+    }
+    map
+}
+
+#[test]
+fn unify_unbound_test() {
+    let mut map = variables(&[1, 2, 3]);
+    map.unify(&Term::Var(1), &Term::Var(2)).unwrap();
+    map.unify(&Term::Var(3), &Term::Var(2)).unwrap();
+    assert!(map.same_class(&1, &3));
+    assert_eq!(Some(&None), map.get(&1));
+    map.unify(&Term::Int(5), &Term::Var(3)).unwrap();
+    for k in [1, 2, 3] {
+        assert_eq!(Some(&Some(Term::Int(5))), map.get(&k));
+    }
+    assert_eq!(1, map.values().count());
+}
+
+#[test]
+fn unify_bound_test() {
+    let mut map = variables(&[1, 2]);
+    map.unify(&Term::Var(1), &Term::Int(5)).unwrap();
+    map.unify(&Term::Var(2), &Term::Int(5)).unwrap();
+    assert_eq!(Ok(vec![]), map.unify(&Term::Var(1), &Term::Var(2)));
+    assert_eq!(
+        Err(UnifyError::Mismatch(Term::Int(5), Term::Int(6))),
+        map.unify(&Term::Var(1), &Term::Int(6))
+    );
+    assert_eq!(Err(UnifyError::UnknownKey(9)), map.unify(&Term::Var(9), &Term::Int(6)));
+}
+
+#[test]
+fn unify_structure_test() {
+    let mut map = variables(&[1, 2, 3]);
+    let left = Term::Pair(vec![Term::Var(1), Term::Int(2)]);
+    let right = Term::Pair(vec![Term::Int(1), Term::Var(2)]);
+    map.unify(&left, &right).unwrap();
+    assert_eq!(Some(&Some(Term::Int(1))), map.get(&1));
+    assert_eq!(Some(&Some(Term::Int(2))), map.get(&2));
+
+    // bound through a variable, and a shape that does not match
+    map.unify(&Term::Var(3), &left).unwrap();
+    assert_eq!(Ok(Term::Pair(vec![Term::Var(1), Term::Int(2)])), map.walk(&Term::Var(3)));
+    let short = Term::Pair(vec![Term::Int(1)]);
+    assert_eq!(Err(UnifyError::Mismatch(left.clone(), short.clone())), map.unify(&Term::Var(3), &short));
+}
+
+#[test]
+fn wait_on_known_keys_test() {
+    let mut map = variables(&[1]);
+    assert!(!map.wait_on(&2, 20));
+    assert!(map.wait_on(&1, 10));
+    assert!(map.wait_on(&1, 10));
+    // inserting wakes nobody, binding does
+    map.insert(1, None);
+    assert_eq!(Ok(vec![10]), map.unify(&Term::Var(1), &Term::Int(3)));
+    assert_eq!(Ok(vec![]), map.unify(&Term::Var(1), &Term::Int(3)));
+}
+
+#[test]
+fn wake_every_alias_once_test() {
+    let mut map = variables(&[1, 4]);
+    map.alias(&1, 2).unwrap();
+    map.alias(&1, 3).unwrap();
+    // the same token on two aliases is woken once
+    map.wait_on(&1, 10);
+    map.wait_on(&2, 20);
+    map.wait_on(&3, 20);
+    map.wait_on(&4, 40);
+    assert_eq!(Ok(vec![]), map.unify(&Term::Var(4), &Term::Var(2)));
+    assert_eq!(Ok(vec![10, 20, 40]), map.unify(&Term::Var(3), &Term::Int(7)));
+    assert_eq!(Ok(vec![]), map.unify(&Term::Var(1), &Term::Int(7)));
+
+    // one unify binding two values wakes the waiters of both
+    let mut map = variables(&[1, 2]);
+    map.wait_on(&1, 20);
+    map.wait_on(&2, 10);
+    map.wait_on(&2, 20);
+    let pair = Term::Pair(vec![Term::Var(1), Term::Var(2)]);
+    assert_eq!(Ok(vec![10, 20]), map.unify(&pair, &Term::Pair(vec![Term::Int(1), Term::Int(2)])));
+}
+
+#[test]
+fn waiters_follow_replaced_values_test() {
+    let mut map = variables(&[1, 3]);
+    map.alias(&3, 4).unwrap();
+    map.wait_on(&1, 10);
+    map.wait_on(&3, 30);
+    // the value of 1 is dropped and its waiter moves to the new one, 3 keeps its value
+    map.insert_many(vec![1, 2, 4], None);
+    assert_eq!(Ok(vec![10]), map.unify(&Term::Var(2), &Term::Int(1)));
+    assert_eq!(Ok(vec![30]), map.unify(&Term::Var(3), &Term::Int(3)));
+    // removing the last key of a value forgets its waiters
+    map.insert(5, None);
+    map.wait_on(&5, 50);
+    map.remove(&5);
+    map.insert(5, None);
+    assert_eq!(Ok(vec![]), map.unify(&Term::Var(5), &Term::Int(5)));
+    assert_eq!(0, map.stats().waited_values);
+}
+
+#[test]
+fn insert_many_replaces_test() {
+    let mut map = SingleAssignmentStore::new();
+    map.insert(1, 1);
+    assert_eq!(vec![1], map.insert_many(vec![1, 2], 3));
+    assert_eq!(Some(&3), map.get(&1));
+    assert_eq!(Some(&3), map.get(&2));
+    // the replaced key counts towards the new value
+    assert_eq!(None, map.remove(&1));
+    assert_eq!(Some(3), map.remove(&2));
+
+    // a key leaving a shared value counts towards the new one too
+    map.insert_many(vec![1, 2], 1);
+    assert!(map.insert_many(vec![2, 3], 2).is_empty());
+    assert_eq!(None, map.remove(&3));
+    assert_eq!(Some(2), map.remove(&2));
+    assert_eq!(Some(1), map.remove(&1));
+}
+
+#[test]
+fn dataflow_bind_once_test() {
+    let mut store = DataflowStore::new();
+    assert!(store.declare(1));
+    assert!(!store.declare(1));
+    assert!(!store.is_bound(&1));
+    assert!(store.wait_on(&1, 10));
+    assert_eq!(Ok(vec![10]), store.bind(&1, 5));
+    // nothing to wait for on a bound or unknown key
+    assert!(!store.wait_on(&1, 11));
+    assert!(!store.wait_on(&2, 20));
+    assert_eq!(Some(&5), store.get(&1));
+    assert_eq!(Err(BindError::AlreadyBound { key: 1, bound: 5, value: 5 }), store.bind(&1, 5));
+    assert_eq!(Err(BindError::AlreadyBound { key: 1, bound: 5, value: 6 }), store.bind(&1, 6));
+    assert_eq!(Err(BindError::UnknownKey(2)), store.bind(&2, 6));
+    assert_eq!(Some(&5), store.get(&1));
+}
+
+#[test]
+fn dataflow_alias_test() {
+    let mut store: DataflowStore<u32, Term> = DataflowStore::new();
+    store.declare(1);
+    store.declare(2);
+    // aliasing onto a declared key would rebind it
+    assert_eq!(Err(2), store.alias(&1, 2));
+    assert_eq!(Err(4), store.alias(&3, 4));
+    assert_eq!(Ok(()), store.alias(&1, 3));
+    store.unify(&Term::Var(2), &Term::Var(3)).unwrap();
+    store.bind(&2, Term::Int(1)).unwrap();
+    assert_eq!(Some(&Term::Int(1)), store.get(&1));
+    assert_eq!(Ok(vec![]), store.unify(&Term::Var(1), &Term::Int(1)));
+    assert!(matches!(store.bind(&3, Term::Int(1)), Err(BindError::AlreadyBound { key: 3, .. })));
+    assert_eq!(3, store.iter().filter(|(_, v)| v.is_some()).count());
+}
+
+#[test]
+fn collect_test() {
+    let mut map = variables(&[1, 2, 3, 4, 5]);
+    map.alias(&2, 6).unwrap();
+    map.unify(&Term::Var(1), &Term::Pair(vec![Term::Var(2), Term::Int(0)])).unwrap();
+    map.unify(&Term::Var(3), &Term::Var(4)).unwrap();
+    map.wait_on(&3, 30);
+    map.wait_on(&5, 50);
+    let collected = map.collect(&[1, 3]);
+    assert_eq!(Collected { keys: 3, values: 1 }, collected);
+    // 6 is an alias of 2 nothing refers to, 4 shares the value of 3
+    assert!(map.contains_key(&2) && map.contains_key(&3));
+    assert!(!map.contains_key(&4) && !map.contains_key(&5) && !map.contains_key(&6));
+    assert_eq!(1, map.stats().waited_values);
+    assert_eq!(Ok(vec![]), map.unify(&Term::Var(2), &Term::Int(1)));
+    // compacting renumbers the values someone waits on too
+    map.compact();
+    assert_eq!(Ok(vec![30]), map.unify(&Term::Var(3), &Term::Int(2)));
+    assert_eq!(3, map.stats().values);
+}
+
+#[test]
+fn bounded_memory_test() {
+    // a producer binds a fresh variable every round, the consumer only keeps the last one
+    let mut store: DataflowStore<u32, Term> = DataflowStore::new();
+    let mut peak = Stats::default();
+    for round in 0..10_000u32 {
+        store.declare(round);
+        store.bind(&round, Term::Int(round as i64)).unwrap();
+        if round % 100 == 99 {
+            store.collect(&[round]);
+            store.compact();
+            let stats = store.stats();
+            peak.keys = peak.keys.max(stats.keys);
+            peak.key_capacity = peak.key_capacity.max(stats.key_capacity);
+            peak.next_index = peak.next_index.max(stats.next_index);
+        }
+    }
+    assert_eq!(1, peak.keys);
+    assert!(peak.key_capacity <= 4);
+    assert_eq!(1, peak.next_index);
+}
+
+#[test]
+fn delta_test() {
+    let mut map = SingleAssignmentStore::from([(vec![1, 2, 3], 10), (vec![4], 40), (vec![5], 50)]);
+    let checkpoint: Snapshot<u32, i32> = map.snapshot();
+    assert!(map.delta(&checkpoint).is_empty());
+
+    map.insert(3, 30);
+    map.alias(&4, 6).unwrap();
+    map.remove(&5);
+    let delta = map.delta(&checkpoint);
+    assert_eq!(vec![5], delta.removed);
+    // 1 and 2 lost a key, 3 got its own value and 4 gained an alias
+    assert_eq!(3, delta.groups.len());
+
+    let mut restored = SingleAssignmentStore::from(checkpoint);
+    restored.apply(delta);
+    assert_eq!(map, restored);
+    *restored.get_mut(&6).unwrap() = 41;
+    assert_eq!(Some(&41), restored.get(&4));
+    assert_eq!(Some(&10), restored.get(&2));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_keeps_sharing_test() {
+    let map = SingleAssignmentStore::from([(vec![1, 2], 10), (vec![3], 30)]);
+    let bytes = postcard::to_allocvec(&map).unwrap();
+    let mut restored: SingleAssignmentStore<u32, i32> = postcard::from_bytes(&bytes).unwrap();
+    assert_eq!(map, restored);
+    *restored.get_mut(&1).unwrap() = 11;
+    assert_eq!(Some(&11), restored.get(&2));
+
+    let mut store: DataflowStore<u32, i32> = DataflowStore::new();
+    store.declare(1);
+    store.alias(&1, 2).unwrap();
+    let bytes = postcard::to_allocvec(&store).unwrap();
+    let mut restored: DataflowStore<u32, i32> = postcard::from_bytes(&bytes).unwrap();
+    restored.bind(&1, 5).unwrap();
+    assert_eq!(Some(&5), restored.get(&2));
+
+    let checkpoint = map.snapshot();
+    let bytes = postcard::to_allocvec(&checkpoint).unwrap();
+    assert_eq!(checkpoint, postcard::from_bytes::<Snapshot<u32, i32>>(&bytes).unwrap());
+}
+
+#[cfg(all(feature = "std", not(loom)))]
+#[test]
+fn concurrent_bind_race_test() {
+    use crate::ConcurrentSingleAssignmentStore;
+    use std::sync::Arc;
+    use std::thread;
+
+    for _ in 0..100 {
+        let store = Arc::new(ConcurrentSingleAssignmentStore::<u32, i64>::new());
+        store.declare(1);
+        store.alias(&1, 2).unwrap();
+        let binders: Vec<_> = (0..4)
+            .map(|i| {
+                let store = store.clone();
+                thread::spawn(move || store.bind(&(1 + i % 2), i as i64))
+            })
+            .collect();
+        let reader = {
+            let store = store.clone();
+            thread::spawn(move || store.wait(&2))
+        };
+        let results: Vec<_> = binders.into_iter().map(|t| t.join().unwrap()).collect();
+        let bound = store.get(&1).unwrap();
+        assert_eq!(1, results.iter().filter(|r| r.is_ok()).count());
+        for result in results {
+            if let Err(BindError::AlreadyBound { bound: b, .. }) = result {
+                assert_eq!(bound, b);
+            }
+        }
+        assert_eq!(Some(bound), reader.join().unwrap());
+    }
+}
+
+#[cfg(all(feature = "std", not(loom)))]
+#[test]
+fn concurrent_unify_test() {
+    use crate::ConcurrentSingleAssignmentStore;
+    use std::sync::Arc;
+    use std::thread;
+
+    for _ in 0..100 {
+        let store = Arc::new(ConcurrentSingleAssignmentStore::<u32, Term>::new());
+        for k in 1..=4 {
+            store.declare(k);
+        }
+        let pairs = [(1, 2), (2, 1), (3, 2), (4, 3)];
+        let unifiers: Vec<_> = pairs
+            .into_iter()
+            .map(|(a, b)| {
+                let store = store.clone();
+                thread::spawn(move || store.unify(&Term::Var(a), &Term::Var(b)))
+            })
+            .collect();
+        for unifier in unifiers {
+            unifier.join().unwrap().unwrap();
+        }
+        store.bind(&4, Term::Int(7)).unwrap();
+        for k in 1..=4 {
+            assert_eq!(Some(Term::Int(7)), store.get(&k));
+        }
+    }
+    let store = ConcurrentSingleAssignmentStore::<u32, Term>::new();
+    store.declare(1);
+    store.declare(2);
+    let (a, b) = (Term::Pair(vec![Term::Var(1), Term::Int(2)]), Term::Pair(vec![Term::Int(1), Term::Var(2)]));
+    store.unify(&a, &b).unwrap();
+    assert_eq!((Some(Term::Int(1)), Some(Term::Int(2))), (store.get(&1), store.get(&2)));
+    assert_eq!(
+        Err(UnifyError::Mismatch(Term::Int(1), Term::Int(3))),
+        store.unify(&Term::Var(1), &Term::Int(3))
+    );
+    assert_eq!(Err(UnifyError::UnknownKey(9)), store.unify(&Term::Var(9), &Term::Int(3)));
+}
+
+#[cfg(all(feature = "std", not(loom)))]
+#[test]
+fn concurrent_wait_async_test() {
+    use crate::ConcurrentSingleAssignmentStore;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::sync::atomic::{AtomicBool, Ordering};
+    use core::task::{Context, Poll};
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    struct Flag(AtomicBool);
+    impl Wake for Flag {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let store = ConcurrentSingleAssignmentStore::<u32, Term>::new();
+    store.declare(1);
+    store.declare(2);
+    let flag = Arc::new(Flag(AtomicBool::new(false)));
+    let waker = flag.clone().into();
+    let mut cx = Context::from_waker(&waker);
+    let mut wait = pin!(store.wait_async(&1).unwrap());
+    assert_eq!(Poll::Pending, wait.as_mut().poll(&mut cx));
+    // unifying wakes the waiter, which then waits on the variable that gets the value
+    store.unify(&Term::Var(2), &Term::Var(1)).unwrap();
+    assert!(flag.0.swap(false, Ordering::SeqCst));
+    assert_eq!(Poll::Pending, wait.as_mut().poll(&mut cx));
+    store.bind(&2, Term::Int(5)).unwrap();
+    assert!(flag.0.load(Ordering::SeqCst));
+    assert_eq!(Poll::Ready(Term::Int(5)), wait.as_mut().poll(&mut cx));
+    assert!(store.wait_async(&3).is_none());
+}
+
+#[cfg(all(feature = "std", loom))]
+#[test]
+fn loom_bind_race_test() {
+    use crate::ConcurrentSingleAssignmentStore;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    loom::model(|| {
+        let store = Arc::new(ConcurrentSingleAssignmentStore::<u32, i64>::new());
+        store.declare(1);
+        let binder = {
+            let store = store.clone();
+            thread::spawn(move || store.bind(&1, 1))
+        };
+        let result = store.bind(&1, 0);
+        let other = binder.join().unwrap();
+        let bound = store.get(&1).unwrap();
+        assert_eq!(result.is_ok(), bound == 0);
+        assert_eq!(other.is_ok(), bound == 1);
+        let lost = if bound == 0 { other } else { result };
+        assert_eq!(Err(BindError::AlreadyBound { key: 1, bound, value: 1 - bound }), lost);
+    });
+}
+
+#[cfg(all(feature = "std", loom))]
+#[test]
+fn loom_wait_test() {
+    use crate::ConcurrentSingleAssignmentStore;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    loom::model(|| {
+        let store = Arc::new(ConcurrentSingleAssignmentStore::<u32, i64>::new());
+        store.declare(1);
+        let reader = {
+            let store = store.clone();
+            thread::spawn(move || store.wait(&1))
+        };
+        store.bind(&1, 5).unwrap();
+        assert_eq!(Some(5), reader.join().unwrap());
+    });
+}
+
+#[cfg(all(feature = "std", loom))]
+#[test]
+fn loom_unify_bind_race_test() {
+    use crate::ConcurrentSingleAssignmentStore;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    loom::model(|| {
+        let store = Arc::new(ConcurrentSingleAssignmentStore::<u32, Term>::new());
+        store.declare(1);
+        store.declare(2);
+        let unifier = {
+            let store = store.clone();
+            thread::spawn(move || store.unify(&Term::Var(2), &Term::Var(1)))
+        };
+        store.bind(&1, Term::Int(3)).unwrap();
+        unifier.join().unwrap().unwrap();
+        assert_eq!(Some(Term::Int(3)), store.get(&2));
+    });
+}
+
+#[cfg(all(feature = "std", loom))]
+#[test]
+fn loom_unify_race_test() {
+    use crate::ConcurrentSingleAssignmentStore;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    loom::model(|| {
+        let store = Arc::new(ConcurrentSingleAssignmentStore::<u32, Term>::new());
+        store.declare(1);
+        store.declare(2);
+        let unifier = {
+            let store = store.clone();
+            thread::spawn(move || store.unify(&Term::Var(2), &Term::Var(1)))
+        };
+        store.unify(&Term::Var(1), &Term::Var(2)).unwrap();
+        unifier.join().unwrap().unwrap();
+        // forwards only point to later variables, so the two never wait on each other
+        store.bind(&2, Term::Int(3)).unwrap();
+        assert_eq!(Some(Term::Int(3)), store.get(&1));
+    });
+}
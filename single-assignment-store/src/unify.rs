@@ -0,0 +1,143 @@
+use crate::{SingleAssignmentStore, Token};
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::Hash;
+
+/// Values that [`unify`] can look into.
+///
+/// A store of `Option<V>` holds dataflow variables: a key maps to `None`
+/// while its variable is unbound, and keys sharing one element form an
+/// equivalence class of variables that were unified with each other.
+///
+/// [`unify`]: SingleAssignmentStore::unify
+pub trait Unify<K>: Clone + PartialEq {
+    /// The variable this value stands for, if it is one.
+    fn key(&self) -> Option<&K>;
+    /// Constructor and fields of a structured value. Two structured values
+    /// unify when their constructors are equal and their fields unify
+    /// pairwise, other values unify when they are equal.
+    fn structure(&self) -> Option<(usize, &[Self])> {
+        None
+    }
+}
+
+/// Why two values could not be unified.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UnifyError<K, V> {
+    /// A variable that is not in the store.
+    UnknownKey(K),
+    /// Two values, or fields of them, that can never be equal.
+    Mismatch(V, V),
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Display for UnifyError<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnifyError::UnknownKey(k) => write!(f, "unknown variable {:?}", k),
+            UnifyError::Mismatch(a, b) => write!(f, "cannot unify {:?} with {:?}", a, b),
+        }
+    }
+}
+
+impl<K, V> SingleAssignmentStore<K, Option<V>>
+where
+    K: Hash + Eq + Clone,
+    V: Unify<K>,
+{
+    ///Follows `v` through bound variables, returning either a value that is not a variable
+    /// or an unbound variable.
+    pub fn walk(&self, v: &V) -> Result<V, UnifyError<K, V>> {
+        let mut v = v.clone();
+        while let Some(k) = v.key() {
+            match self.get(k) {
+                None => return Err(UnifyError::UnknownKey(k.clone())),
+                Some(None) => break,
+                Some(Some(bound)) => v = bound.clone(),
+            }
+        }
+        Ok(v)
+    }
+    ///Makes `a` and `b` equal. Two unbound variables are merged into one equivalence class,
+    /// an unbound variable is bound to the other side, and structured values are unified
+    /// field by field. Returns the tokens waiting on the values it bound, see [`wait_on`],
+    /// in ascending order.
+    ///
+    /// Bindings made before a mismatch is found are kept, but wake nobody.
+    /// ```
+    /// use single_assignment_store::{SingleAssignmentStore, Unify};
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// enum Term { Var(u32), Int(i64) }
+    /// impl Unify<u32> for Term {
+    ///     fn key(&self) -> Option<&u32> {
+    ///         match self { Term::Var(k) => Some(k), _ => None }
+    ///     }
+    /// }
+    /// let mut map = SingleAssignmentStore::new();
+    /// map.insert(1, None);
+    /// map.insert(2, None);
+    /// map.wait_on(&1, 10);
+    /// assert_eq!(map.unify(&Term::Var(1), &Term::Var(2)), Ok(vec![]));
+    /// assert_eq!(map.unify(&Term::Var(2), &Term::Int(3)), Ok(vec![10]));
+    /// assert_eq!(map.get(&1), Some(&Some(Term::Int(3))));
+    /// assert!(map.unify(&Term::Var(1), &Term::Int(4)).is_err());
+    /// ```
+    /// [`wait_on`]: SingleAssignmentStore::wait_on
+    pub fn unify(&mut self, a: &V, b: &V) -> Result<Vec<Token>, UnifyError<K, V>> {
+        let mut pending = vec![(a.clone(), b.clone())];
+        let mut woken = Vec::new();
+        while let Some((a, b)) = pending.pop() {
+            let a = self.walk(&a)?;
+            let b = self.walk(&b)?;
+            match (a.key(), b.key()) {
+                (Some(ka), Some(kb)) => self.merge(ka, kb),
+                (Some(k), None) => woken.extend(self.bind_key(k, b.clone())),
+                (None, Some(k)) => woken.extend(self.bind_key(k, a.clone())),
+                (None, None) => match (a.structure(), b.structure()) {
+                    (Some((ca, fa)), Some((cb, fb))) if ca == cb && fa.len() == fb.len() => {
+                        pending.extend(fa.iter().cloned().zip(fb.iter().cloned()));
+                    }
+                    (None, None) if a == b => {}
+                    _ => return Err(UnifyError::Mismatch(a, b)),
+                },
+            }
+        }
+        woken.sort_unstable();
+        woken.dedup();
+        Ok(woken)
+    }
+    ///Whether `a` and `b` share one element.
+    pub fn same_class(&self, a: &K, b: &K) -> bool {
+        match (self.keys.get(a), self.keys.get(b)) {
+            (Some(ia), Some(ib)) => ia == ib,
+            _ => false,
+        }
+    }
+
+    // points every key of `a`'s class to the element of `b`, whoever waited on
+    // `a` now waits on `b`
+    fn merge(&mut self, a: &K, b: &K) {
+        let (Some(&from), Some(&into)) = (self.keys.get(a), self.keys.get(b)) else {
+            return;
+        };
+        if from == into {
+            return;
+        }
+        let moved: Vec<K> = self
+            .keys
+            .iter()
+            .filter(|(_, idx)| **idx == from)
+            .map(|(k, _)| k.clone())
+            .collect();
+        self.data.remove(&from);
+        if let Some(tokens) = self.waiters.remove(&from) {
+            self.add_waiters(into, tokens);
+        }
+        if let Some((count, _)) = self.data.get_mut(&into) {
+            *count += moved.len();
+        }
+        for k in moved {
+            self.keys.insert(k, into);
+        }
+    }
+}
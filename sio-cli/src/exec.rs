@@ -1,7 +1,5 @@
-use sio::environ::brigadier::{BrigadierLiteral, brigadier_literal_mapper as literal_mapper, brigadier_literal_to_value as literal_to_value};
-use sio::allocator::BrigadierAllocator as Alloc;
-use sio::value::brigadier::BrigadierValue as Value;
-use sio::BrigadierState;
+use sio::{literal_mapper, literal_to_value, sequence_constructor, Literal, State, Value};
+use sio::Allocator as Alloc;
 
 use hashbrown::HashSet;
 use werbolg_compile::{code_dump, compile, Environment, InstructionAddress};
@@ -56,16 +54,16 @@ pub fn report_print(source: &Source, report: Report) -> Result<(), Box<dyn Error
 
 pub fn run_compile<A>(
     params: &SioParams,
-    env: &mut Environment<NIF<A, BrigadierLiteral, BrigadierState, Value>, Value>,
+    env: &mut Environment<NIF<A, Literal, State, Value>, Value>,
     source: Source,
     module: Module,
-) -> Result<werbolg_compile::CompilationUnit<BrigadierLiteral>, Box<dyn Error>> {
+) -> Result<werbolg_compile::CompilationUnit<Literal>, Box<dyn Error>> {
     let module_ns = Namespace::root().append(Ident::from("main"));
     let modules = vec![(module_ns.clone(), module)];
 
     let compilation_params = werbolg_compile::CompilationParams {
         literal_mapper: literal_mapper,
-        sequence_constructor: None,
+        sequence_constructor: Some(sequence_constructor()),
     };
 
     let exec_module = match compile(&compilation_params, modules, env) {
@@ -91,8 +89,8 @@ pub fn run_compile<A>(
 
 pub fn run_exec(
     params: &SioParams,
-    ee: ExecutionEnviron<Alloc, BrigadierLiteral, BrigadierState, Value>,
-    exec_module: werbolg_compile::CompilationUnit<BrigadierLiteral>,
+    ee: ExecutionEnviron<Alloc, Literal, State, Value>,
+    exec_module: werbolg_compile::CompilationUnit<Literal>,
 ) -> Result<(), Box<dyn Error>> {
     let module_ns = Namespace::root().append(Ident::from("main"));
 
@@ -104,7 +102,7 @@ pub fn run_exec(
     let execution_params = ExecutionParams {
         literal_to_value: literal_to_value,
     };
-    let mut state = BrigadierState {};
+    let mut state = State::default();
     let mut allocator = Alloc {};
 
     let mut em = ExecutionMachine::new(
@@ -5,11 +5,13 @@ mod exec;
 mod params;
 
 use sio::{
-    create_brigadier_env,
-    create_major_env,
-    create_corporal_env,
+    create_env,
+    identity::SEED_LEN,
+    tier,
+    Identity,
+    SignedModule,
 };
-use sio_garrison::{Garrison, Brigadier, Major, Corporal};
+use sio_garrison::{Garrison, Brigadier, Major, Corporal, ExitReason, RunState};
 use exec::*;
 use params::{Frontend, SioParams};
 
@@ -22,8 +24,13 @@ enum Flag {
     ExecStepTrace,
     StepAddress(u64),
     Frontend(Frontend),
+    KeyFile(String),
 }
 
+/// Environment variable holding the garrison seed, used when no key file is
+/// given.
+const SEED_VAR: &str = "SIO_SEED";
+
 fn version() {
     println!("v0.1.0")
 }
@@ -41,6 +48,8 @@ Options:
   --exec-step-trace   Trace every step of execution
   --step-address <a>  Address to print a debug trace
   --frontend <value>  Set the frontend to use a specific frontend
+  --key-file <file>   Read the garrison seed, 64 hex digits, from <file>
+                      instead of the SIO_SEED environment variable
     "#
     );
 }
@@ -87,6 +96,10 @@ fn main() -> Result<(), Box<dyn Error>> {
                     }
                 })),
             ),
+            (
+                "key-file",
+                args::FlagDescr::Arg(Box::new(|s| Ok(Flag::KeyFile(s)))),
+            ),
         ],
     };
     let (flags, args) = args::args(options)?;
@@ -121,6 +134,13 @@ fn main() -> Result<(), Box<dyn Error>> {
         })
         .last()
         .unwrap_or(Frontend::General);
+    let key_file = flags
+        .iter()
+        .filter_map(|f| match f {
+            Flag::KeyFile(path) => Some(path.as_str()),
+            _ => None,
+        })
+        .last();
 
     let params = SioParams {
         dump_ir,
@@ -129,8 +149,41 @@ fn main() -> Result<(), Box<dyn Error>> {
         step_address,
         frontend,
     };
-    let src = "
+    let identity = Identity::from_seed(load_seed(key_file)?);
+    let mut garrison = garrison(&identity)?;
+
+    let state = garrison.run_until_idle();
+    for report in garrison.take_exit_reports() {
+        println!("process {} exited: {}", report.pid, report.reason);
+        if let ExitReason::Crashed(e) = &report.reason {
+            println!("{}", e.debug_state);
+        }
+    }
+    for (pid, key) in garrison.take_install_requests() {
+        println!("process {} asked to install a brigadier for {}", pid, key);
+    }
+    match state {
+        RunState::Terminated => {}
+        RunState::Idle => println!("garrison idle: every remaining process is blocked"),
+        RunState::Runnable => unreachable!("run_until_idle returns once nothing is ready"),
+    }
+
+    Ok(())
+}
+
+/// The program the garrison runs, one module per tier.
+const PROGRAM: &str = "
     url corp: this::is::a::corp;
+    brigadier corp::Brigadier {
+        pub main :: () {
+            true;
+        }
+    }
+    major corp::Major {
+        pub main :: () {
+            true;
+        }
+    }
     corporal corp::Corporal {
         pub main :: () {
             let x;
@@ -143,26 +196,75 @@ fn main() -> Result<(), Box<dyn Error>> {
             } else {
                 assign_the_value(y, false);
             }
+            y;
         }
-        assign_the_value :: (y , value) {
+        assign_the_value :: (y: bool, value: bool) {
             y = value;
         }
     }
     	";
-    let mut brigadier_env = create_brigadier_env();
-    let mut garrison = Garrison::new(src.to_string(), "brigadier".to_string(), brigadier_env);
 
-    let mut major_env = create_major_env();
-    let major = Major::new(src.to_string(),"major".to_string(), major_env).expect("reason");
+/// A garrison of `identity` running [`PROGRAM`], signed by `identity`, with a
+/// process of every tier.
+fn garrison(identity: &Identity) -> Result<Garrison, Box<dyn Error>> {
+    // root `this` at our own key so the locally signed code verifies
+    let src = format!("url this: {};{}", identity.public_key(), PROGRAM);
+    let code = SignedModule::sign(identity, src);
+
+    let brigadier_env = create_env::<tier::Brigadier>();
+    let mut garrison = Garrison::new(code.clone(), identity.public_key(), "brigadier".to_string(), brigadier_env, identity.clone())
+        .map_err(|e| format!("cannot start the brigadier: {}", e))?;
+
+    // the garrison derives the key of every process it runs from its own
+    let major_env = create_env::<tier::Major>();
+    let major = Major::new(code.clone(), identity.public_key(), "major".to_string(), major_env)
+        .map_err(|e| format!("cannot compile the major: {}", e))?;
     garrison.add_major(major);
 
-    let mut corporal_env = create_corporal_env();
-    let corporal= Corporal::new(src.to_string(),"corporal".to_string(), corporal_env).expect("reason");
+    let corporal_env = create_env::<tier::Corporal>();
+    let corporal = Corporal::new(code, identity.public_key(), "corporal".to_string(), corporal_env)
+        .map_err(|e| format!("cannot compile the corporal: {}", e))?;
     garrison.add_corporal(corporal);
+    Ok(garrison)
+}
 
-    loop {
-        garrison.march();
+/// The garrison seed, from the key file if one is given or else from
+/// [`SEED_VAR`]. Either holds the seed as hex digits.
+fn load_seed(key_file: Option<&str>) -> Result<[u8; SEED_LEN], Box<dyn Error>> {
+    let (source, text) = match key_file {
+        Some(path) => (path, std::fs::read_to_string(path)?),
+        None => match std::env::var(SEED_VAR) {
+            Ok(text) => (SEED_VAR, text),
+            Err(_) => return Err(format!("no garrison seed: set {} or pass --key-file", SEED_VAR).into()),
+        },
+    };
+    parse_seed(text.trim())
+        .ok_or_else(|| format!("{} does not hold a seed of {} hex digits", source, 2 * SEED_LEN).into())
+}
+
+fn parse_seed(hex: &str) -> Option<[u8; SEED_LEN]> {
+    if hex.len() != 2 * SEED_LEN {
+        return None;
+    }
+    let mut seed = [0; SEED_LEN];
+    for (i, byte) in seed.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(2 * i..2 * i + 2)?, 16).ok()?;
     }
+    Some(seed)
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sio::{ProcessId, Value};
+
+    #[test]
+    fn program_runs() {
+        let identity = Identity::from_seed([1; SEED_LEN]);
+        let mut garrison = garrison(&identity).unwrap();
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        assert!(garrison.take_exit_reports().is_empty());
+        // the brigadier, the major, then the main thread of the corporal
+        assert_eq!(garrison.result(ProcessId(2)), Some(Value::Bool(true)));
+    }
 }
@@ -37,7 +37,9 @@ pub enum LogicalOperator {
 pub enum Expr {
     Binary(Box<WithSpan<Expr>>, WithSpan<BinaryOperator>, Box<WithSpan<Expr>>),
     Grouping(Box<WithSpan<Expr>>),
-    Number(f64),
+    /// The text of an integer literal, with its radix prefix and type suffix.
+    Number(String),
+    Decimal(f64),
     Boolean(bool),
     Nil,
     String(String),
@@ -66,6 +68,7 @@ pub enum Stmt {
     LetMultiple(Vec<WithSpan<Identifier>>),
     Thread(Vec<WithSpan<Stmt>>),
     Function(Function),
+    Ffi(Ffi),
     Use(WithSpan<String>, Option<Vec<WithSpan<String>>>),
     Module(Module),
     Return(Box<WithSpan<Expr>>),
@@ -102,6 +105,25 @@ pub struct Function {
     pub body: Vec<WithSpan<Stmt>>,
 }
 
+/// A host function bound to `name`, declared as
+/// `name :: url :: (params) -> ret;`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Ffi {
+    pub visibility: Visibility,
+    pub name: WithSpan<Identifier>,
+    pub url: HierarchicalName,
+    pub params: Vec<Param>,
+    pub return_type: Option<WithSpan<Identifier>>,
+}
+
+impl Ffi {
+    /// The url the host registers the function under, its parts joined by
+    /// `::`.
+    pub fn url_key(&self) -> String {
+        let parts: Vec<&str> = self.url.parts.iter().map(|part| part.value.as_str()).collect();
+        parts.join("::")
+    }
+}
      
 #[derive(Debug, Clone, PartialEq)]
 pub struct HierarchicalName {
@@ -0,0 +1,85 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use hashbrown::HashMap;
+use sio::key::{self, PublicKey};
+use crate::{
+    ast::{HierarchicalName, Module, Stmt},
+    position::{Diagnostic, WithSpan},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthorError {
+    Parse(Vec<Diagnostic>),
+    /// The source declares no corporal, major, brigadier or general.
+    NoModule,
+    /// The module path does not resolve to a public key through the url declarations.
+    Unresolved(String),
+    /// Two modules in the same source resolve to different keys.
+    Conflicting(PublicKey, PublicKey),
+}
+
+impl fmt::Display for AuthorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthorError::Parse(ds) => {
+                let messages = ds.iter().map(|d| d.message.as_str()).collect::<Vec<_>>();
+                write!(f, "parse error: {}", messages.join(", "))
+            }
+            AuthorError::NoModule => write!(f, "no module declared"),
+            AuthorError::Unresolved(name) => write!(f, "module path {} is not rooted at a public key", name),
+            AuthorError::Conflicting(a, b) => write!(f, "modules rooted at both {} and {}", a, b),
+        }
+    }
+}
+
+/// Returns the public key the declared modules are rooted at, following the
+/// top level `url` aliases, e.g. `app::Corporal` with
+/// `url app : public_key::type::name;` and `url public_key : spub1...;`.
+pub fn module_author(code: &str) -> Result<PublicKey, AuthorError> {
+    let ast = crate::parse(code).map_err(AuthorError::Parse)?;
+    author(&ast)
+}
+
+pub(crate) fn author(ast: &[WithSpan<Stmt>]) -> Result<PublicKey, AuthorError> {
+    let mut urls: HashMap<&str, &HierarchicalName> = HashMap::new();
+    for stmt in ast {
+        if let Stmt::Url(name, path) = &stmt.value {
+            urls.insert(name.value.as_str(), path);
+        }
+    }
+
+    let mut found: Option<PublicKey> = None;
+    for stmt in ast {
+        let name = match &stmt.value {
+            Stmt::Module(Module::Corporal { name, .. })
+            | Stmt::Module(Module::Major { name, .. })
+            | Stmt::Module(Module::Brigadier { name, .. })
+            | Stmt::Module(Module::General { name, .. }) => name,
+            _ => continue,
+        };
+        let key = resolve_root(&urls, name)?;
+        match found {
+            Some(previous) if previous != key => return Err(AuthorError::Conflicting(previous, key)),
+            _ => found = Some(key),
+        }
+    }
+    found.ok_or(AuthorError::NoModule)
+}
+
+fn resolve_root(urls: &HashMap<&str, &HierarchicalName>, name: &HierarchicalName) -> Result<PublicKey, AuthorError> {
+    let unresolved = || AuthorError::Unresolved(name.to_string());
+    let mut root = name.parts.first().ok_or_else(unresolved)?.value.as_str();
+    // every alias is followed at most once, anything longer is a cycle
+    for _ in 0..=urls.len() {
+        if key::looks_like_key(root) {
+            return key::decode(root)
+                .map(|lit| lit.key)
+                .map_err(|e| AuthorError::Unresolved(format!("{} ({})", name.to_string(), e)));
+        }
+        let alias = urls.get(root).ok_or_else(unresolved)?;
+        root = alias.parts.first().ok_or_else(unresolved)?.value.as_str();
+    }
+    Err(unresolved())
+}
@@ -19,6 +19,23 @@ pub fn expect_identifier(p: &mut Parser) -> Result<WithSpan<Identifier>, ()> {
     }
 }
 
+/// Expects one segment of a url: an identifier or a public key literal.
+pub fn expect_url_part(p: &mut Parser) -> Result<WithSpan<String>, ()> {
+    let token = p.advance();
+    match &token.value {
+        Token::Identifier(ident) => Ok(WithSpan::new(ident.clone(), token.span)),
+        Token::PublicKey(key) => Ok(WithSpan::new(key.to_string(), token.span)),
+        Token::InvalidPublicKey(e) => {
+            p.error(&format!("Invalid public key: {}", e), token.span);
+            Err(())
+        },
+        _ => {
+            p.error(&format!("Expected {} got {}", TokenKind::Identifier, token.value), token.span);
+            Err(())
+        },
+    }
+}
+
 pub fn expect_string(p: &mut Parser) -> Result<WithSpan<String>, ()> {
     let token = p.advance();
     match &token.value {
@@ -125,6 +125,7 @@ fn parse_url_call(it: &mut Parser, left: WithSpan<Expr>) -> Result<WithSpan<Expr
 fn parse_prefix(it: &mut Parser) -> Result<WithSpan<Expr>, ()> {
     match it.peek() {
         TokenKind::Number
+        | TokenKind::Decimal
         | TokenKind::Nil
         | TokenKind::True
         | TokenKind::False
@@ -304,7 +305,8 @@ fn parse_primary(it: &mut Parser) -> Result<WithSpan<Expr>, ()> {
     let tc = it.advance();
     match &tc.value {
         &Token::Nil => Ok(WithSpan::new(Expr::Nil, tc.span)),
-        &Token::Number(n) => Ok(WithSpan::new(Expr::Number(n), tc.span)),
+        Token::Number(n) => Ok(WithSpan::new(Expr::Number(n.clone()), tc.span)),
+        &Token::Decimal(n) => Ok(WithSpan::new(Expr::Decimal(n), tc.span)),
         &Token::True => Ok(WithSpan::new(Expr::Boolean(true), tc.span)),
         &Token::False => Ok(WithSpan::new(Expr::Boolean(false), tc.span)),
         &Token::String(ref s) => Ok(WithSpan::new(Expr::String(s.clone()), tc.span)),
@@ -364,7 +366,7 @@ mod tests {
 
         /// Make Expr::Number
         pub fn n(value: f64) -> Expr {
-            Expr::Number(value)
+            Expr::Number(format!("{}", value))
         }
 
         /// Make WithSpan<Expr::Number>
@@ -473,7 +475,7 @@ mod tests {
         use make::*;
         use help::assert;
         assert("nil", ws(Expr::Nil, 0..3));
-        assert("1.0", ws(n(1.0), 0..3));
+        assert("1.0", ws(Expr::Decimal(1.0), 0..3));
         assert("1", ws(n(1.0), 0..1));
         assert("true", ws(Expr::Boolean(true), 0..4));
         assert("false", ws(Expr::Boolean(false), 0..5));
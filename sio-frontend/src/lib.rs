@@ -10,17 +10,21 @@ pub mod ast;
 
 #[macro_use]
 mod parser;
+mod author;
 mod common;
+mod lower;
 mod token;
 mod tokenizer;
 mod stmt_parser;
 mod expr_parser;
 
-use werbolg_ir_write::module;
 use werbolg_lang_common::{FileUnit, ParseError};
+use sio::{ffi::Signature, KeyTier};
 use ast::Ast;
 use position::Diagnostic;
 
+pub use author::{module_author, AuthorError};
+
 fn parse(code: &str) -> Result<Ast, Vec<Diagnostic>> {
     use stmt_parser::parse;
     use tokenizer::tokenize_with_context;
@@ -33,14 +37,14 @@ fn parse(code: &str) -> Result<Ast, Vec<Diagnostic>> {
     }
 }
 
-#[allow(dead_code)]
-pub fn module(file_unit: &FileUnit) -> Result<werbolg_core::Module, Vec<Diagnostic>> {
+/// Lowers the module of `tier` declared in `file_unit`, with the prelude
+/// functions it does not define itself. Its FFI declarations may bind the
+/// host functions of `hosts`.
+pub fn module(file_unit: &FileUnit, tier: KeyTier, hosts: &[Signature]) -> Result<werbolg_core::Module, Vec<Diagnostic>> {
     let ast = parse(&file_unit.content)?;
-    Ok(module! {
-        fn main() {
-            1
-        }
-    })
+    let mut module = lower::lower(&ast, tier, hosts)?;
+    module.statements.extend(lower::prelude(&ast, tier));
+    Ok(module)
 }
 
 
@@ -72,6 +76,71 @@ mod tests {
                 }
             }
         }";
+    #[test]
+    fn invalid_public_key() {
+        let diagnostics = crate::parse(
+            "url pk : spub108ms3sj6y0knvassltxpgq66m3a6fvdl4yjjaa2udsj0rwds827s0n578q;"
+        ).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "Invalid public key: invalid checksum");
+    }
+
+    #[test]
+    fn author_follows_urls() {
+        let src = "
+            url public_key : spub108ms3sj6y0knvassltxpgq66m3a6fvdl4yjjaa2udsj0rwds827s0n578r;
+            url type : src;
+            url name : app_name;
+            url app : public_key::type::name;
+            corporal app::Corporal {
+                pub main :: () {
+                    1;
+                }
+            }";
+        let author = crate::module_author(src).unwrap();
+        assert_eq!(author.to_string(), "spub108ms3sj6y0knvassltxpgq66m3a6fvdl4yjjaa2udsj0rwds827s0n578r");
+    }
+
+    #[test]
+    fn author_must_be_a_key() {
+        let unrooted = "
+            url app : corp::src;
+            corporal app::Corporal {
+                pub main :: () {
+                    1;
+                }
+            }";
+        assert_eq!(
+            crate::module_author(unrooted),
+            Err(crate::AuthorError::Unresolved("app::Corporal".to_string()))
+        );
+        let cyclic = "
+            url a : b;
+            url b : a;
+            corporal a::Corporal {
+                pub main :: () {
+                    1;
+                }
+            }";
+        assert_eq!(
+            crate::module_author(cyclic),
+            Err(crate::AuthorError::Unresolved("a::Corporal".to_string()))
+        );
+        assert_eq!(
+            crate::module_author("url a : b;"),
+            Err(crate::AuthorError::NoModule)
+        );
+    }
+
+    #[test]
+    fn every_tier_parses() {
+        for tier in ["brigadier", "major", "corporal"] {
+            let src = alloc::format!("{} app::App {{ pub main :: () {{ 1; }} }}", tier);
+            let ast = crate::parse(&src).unwrap();
+            assert_eq!(ast.len(), 1);
+        }
+    }
+
     #[test]
     fn test() {
         assert_eq!(tokenize(&SRC ), vec![
@@ -93,13 +162,13 @@ mod tests {
                 Token::LeftBrace,
                 Token::Identifier("x".to_string()),
                 Token::Equal,
-                Token::Number(0.0),
+                Token::Number("0".to_string()),
                 Token::Semicolon,
                 Token::RightBrace,
                 Token::If,
                 Token::Identifier("x".to_string()),
                 Token::EqualEqual,
-                Token::Number(0.0),
+                Token::Number("0".to_string()),
                 Token::LeftBrace,
                 Token::True,
                 Token::RightBrace,
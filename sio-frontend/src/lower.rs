@@ -0,0 +1,1033 @@
+//! Lowers the parsed modules to werbolg IR.
+//!
+//! Each tier of a source is lowered on its own, into the module the
+//! garrison runs for that tier.
+//!
+//! Statement sequences become nested `let`s. A `thread { .. }` statement is
+//! lifted into a private function of the module taking the variables it
+//! captures, and replaced by a call to the `spawn_thread` NIF with the list
+//! of those variables, which starts it as a new process in the current
+//! garrison. Unlike `spawn`, every tier may call it.
+//!
+//! Variables hold dataflow variables until they are bound, so operands,
+//! conditions and the arguments of NIF calls go through the `read` NIF, which
+//! waits for the binding. Functions of the module get their arguments as they
+//! are, and so do `bind` and `read`, which take variables.
+//!
+//! Lists are immutable: `l[i]` calls the `get` NIF and `l[i] = v` calls `set`,
+//! which evaluates to a copy of `l` with `v` at `i`. The list functions taking
+//! a function, `map`, `fold` and `filter`, are written in sio in [`PRELUDE`]
+//! and added by [`prelude`] to every module that does not define them itself.
+//!
+//! A call to a NIF the tier of the module lacks, see [`sio::tier`], is an
+//! error.
+//!
+//! An FFI declaration `name :: url :: (params) -> ret;` becomes a function
+//! `name` calling the host function the embedder registered under the url,
+//! see `sio::ffi`. Its arguments are read first, host functions only take
+//! bound values. The declaration must match the signature of the host
+//! function, and the last part of the url must not name a NIF the tier of
+//! the module lacks.
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use werbolg_core::{
+    Binder, Expr as IrExpr, FunDef, FunImpl, Ident, Literal, Module as IrModule, Path, Privacy, Spanned,
+    Statement, Variable,
+};
+use sio::ffi::{self, Signature};
+use sio::{tier, KeyTier};
+use crate::{
+    ast::*,
+    position::{Diagnostic, Span, WithSpan},
+};
+
+fn ir_span(span: Span) -> werbolg_core::Span {
+    span.start.0 as usize..span.end.0 as usize
+}
+
+fn path(span: Span, name: &str) -> IrExpr {
+    IrExpr::Path(ir_span(span), Path::relative(Ident::from(name)))
+}
+
+fn call(span: Span, name: &str, args: Vec<IrExpr>) -> IrExpr {
+    let mut exprs = vec![path(span, name)];
+    exprs.extend(args);
+    IrExpr::Call(ir_span(span), exprs)
+}
+
+fn unit(span: Span) -> IrExpr {
+    call(span, "unit", vec![])
+}
+
+fn boolean(span: Span, b: bool) -> IrExpr {
+    IrExpr::Literal(ir_span(span), Literal::Bool(String::from(if b { "true" } else { "false" }).into()))
+}
+
+fn unary_nif(op: UnaryOperator) -> &'static str {
+    match op {
+        UnaryOperator::Minus => "neg",
+        UnaryOperator::Bang => "not",
+    }
+}
+
+fn binary_nif(op: BinaryOperator) -> &'static str {
+    match op {
+        BinaryOperator::Slash => "/",
+        BinaryOperator::Star => "*",
+        BinaryOperator::Plus => "+",
+        BinaryOperator::Minus => "-",
+        BinaryOperator::Greater => ">",
+        BinaryOperator::GreaterEqual => ">=",
+        BinaryOperator::Less => "<",
+        BinaryOperator::LessEqual => "<=",
+        BinaryOperator::BangEqual => "!=",
+        BinaryOperator::EqualEqual => "==",
+    }
+}
+
+/// The NIFs taking dataflow variables, their arguments are not read.
+const TAKE_VARIABLES: &[&str] = &["bind", "read"];
+
+const PRELUDE: &str = "corporal sio::Prelude {
+    map :: (f: fun, xs: list) -> list {
+        let ys = read(xs);
+        if is_empty(ys) {
+            ys;
+        } else {
+            cons(f(head(ys)), map(f, tail(ys)));
+        }
+    }
+
+    fold :: (f: fun, acc: any, xs: list) -> any {
+        let ys = read(xs);
+        if is_empty(ys) {
+            acc;
+        } else {
+            fold(f, f(acc, head(ys)), tail(ys));
+        }
+    }
+
+    filter :: (f: fun, xs: list) -> list {
+        let ys = read(xs);
+        if is_empty(ys) {
+            ys;
+        } else {
+            let rest = filter(f, tail(ys));
+            if f(head(ys)) {
+                cons(head(ys), rest);
+            } else {
+                rest;
+            }
+        }
+    }
+}";
+
+#[derive(Default)]
+struct Lowerer {
+    diagnostics: Vec<Diagnostic>,
+    /// Functions lifted out of thread statements.
+    lifted: Vec<Statement>,
+    /// Variables in scope, innermost last.
+    scope: Vec<Identifier>,
+    /// Tier of the module being lowered, calls to NIFs it lacks are errors.
+    tier: Option<KeyTier>,
+    /// Functions the modules define, they shadow NIFs of the same name.
+    defined: Vec<Identifier>,
+    /// The host functions FFI declarations may bind.
+    hosts: Vec<Signature>,
+}
+
+impl Lowerer {
+    fn error(&mut self, message: &str, span: Span) {
+        self.diagnostics.push(Diagnostic {
+            message: message.to_string(),
+            span,
+        });
+    }
+
+    fn function(&mut self, function: &Function, span: Span) -> Result<Statement, ()> {
+        let name = function_name(function);
+        let privacy = match function.visibility {
+            Visibility::Public => Privacy::Public,
+            Visibility::Private => Privacy::Private,
+        };
+        self.scope = function.params.iter().map(|p| p.name.value.clone()).collect();
+        let vars = function
+            .params
+            .iter()
+            .map(|p| Variable(Spanned::new(ir_span(p.name.span), Ident::from(p.name.value.as_str()))))
+            .collect();
+        let body = self.block(&function.body, span)?;
+        Ok(Statement::Function(
+            ir_span(span),
+            FunDef { privacy, name: Some(Ident::from(name)) },
+            FunImpl { vars, body },
+        ))
+    }
+
+    fn block(&mut self, stmts: &[WithSpan<Stmt>], span: Span) -> Result<IrExpr, ()> {
+        let depth = self.scope.len();
+        let expr = self.sequence(stmts, span);
+        self.scope.truncate(depth);
+        expr
+    }
+
+    /// Lowers `stmts` in order, the value of the last statement is the value
+    /// of the sequence.
+    fn sequence(&mut self, stmts: &[WithSpan<Stmt>], span: Span) -> Result<IrExpr, ()> {
+        let Some((first, rest)) = stmts.split_first() else {
+            return Ok(unit(span));
+        };
+        match &first.value {
+            Stmt::Let(name, init) => {
+                let value = match init {
+                    Some(expr) => self.expr(expr)?,
+                    None => call(first.span, "unbound", vec![]),
+                };
+                self.scope.push(name.value.clone());
+                let then = self.sequence(rest, span)?;
+                Ok(IrExpr::Let(Binder::Ident(Ident::from(name.value.as_str())), Box::new(value), Box::new(then)))
+            }
+            Stmt::LetMultiple(names) => {
+                self.scope.extend(names.iter().map(|name| name.value.clone()));
+                let mut then = self.sequence(rest, span)?;
+                for name in names.iter().rev() {
+                    then = IrExpr::Let(
+                        Binder::Ident(Ident::from(name.value.as_str())),
+                        Box::new(call(name.span, "unbound", vec![])),
+                        Box::new(then),
+                    );
+                }
+                Ok(then)
+            }
+            Stmt::Return(expr) if rest.is_empty() => self.expr(expr),
+            Stmt::Return(_) => {
+                self.error("Unreachable code after return", rest[0].span);
+                Err(())
+            }
+            _ => {
+                let value = self.statement(first)?;
+                if rest.is_empty() {
+                    Ok(value)
+                } else {
+                    let then = self.sequence(rest, span)?;
+                    Ok(IrExpr::Let(Binder::Ignore, Box::new(value), Box::new(then)))
+                }
+            }
+        }
+    }
+
+    fn statement(&mut self, stmt: &WithSpan<Stmt>) -> Result<IrExpr, ()> {
+        match &stmt.value {
+            Stmt::Expression(expr) => self.expr(expr),
+            Stmt::Block(stmts) => self.block(stmts, stmt.span),
+            Stmt::If(cond, then_branch, else_branch) => {
+                let cond = Spanned::new(ir_span(cond.span), self.operand(cond)?);
+                let then_expr = Spanned::new(
+                    ir_span(then_branch.span),
+                    self.block(core::slice::from_ref(then_branch), then_branch.span)?,
+                );
+                let else_expr = match else_branch {
+                    Some(branch) => Spanned::new(
+                        ir_span(branch.span),
+                        self.block(core::slice::from_ref(branch), branch.span)?,
+                    ),
+                    None => Spanned::new(ir_span(stmt.span), unit(stmt.span)),
+                };
+                Ok(IrExpr::If {
+                    span: ir_span(stmt.span),
+                    cond: Box::new(cond),
+                    then_expr: Box::new(then_expr),
+                    else_expr: Box::new(else_expr),
+                })
+            }
+            Stmt::Thread(body) => self.thread(body, stmt.span),
+            Stmt::Let(..) | Stmt::LetMultiple(..) | Stmt::Return(..) => {
+                self.block(core::slice::from_ref(stmt), stmt.span)
+            }
+            _ => {
+                self.error("Unsupported statement", stmt.span);
+                Err(())
+            }
+        }
+    }
+
+    /// Lifts the thread body into a function taking the variables it
+    /// captures and spawns it in the current garrison.
+    fn thread(&mut self, body: &[WithSpan<Stmt>], span: Span) -> Result<IrExpr, ()> {
+        let mut captured = Vec::new();
+        for stmt in body {
+            stmt_captures(stmt, &self.scope, &mut captured);
+        }
+        let outer = core::mem::replace(&mut self.scope, captured.clone());
+        let lifted_body = self.block(body, span);
+        self.scope = outer;
+        // named after lowering the body, which lifts nested threads first
+        let name = format!("thread#{}", self.lifted.len());
+        self.lifted.push(Statement::Function(
+            ir_span(span),
+            FunDef { privacy: Privacy::Private, name: Some(Ident::from(name.as_str())) },
+            FunImpl {
+                vars: captured
+                    .iter()
+                    .map(|var| Variable(Spanned::new(ir_span(span), Ident::from(var.as_str()))))
+                    .collect(),
+                body: lifted_body?,
+            },
+        ));
+
+        let args = IrExpr::Sequence(ir_span(span), captured.iter().map(|var| path(span, var)).collect());
+        Ok(call(span, "spawn_thread", vec![path(span, &name), args, call(span, "garrison", vec![])]))
+    }
+
+    /// A function passing its arguments to the host function registered under
+    /// the url of `ffi`, once the declaration matches its signature.
+    fn ffi(&mut self, ffi: &Ffi, span: Span) -> Result<Statement, ()> {
+        let url = ffi.url_key();
+        let name = url.rsplit("::").next().unwrap_or_default();
+        if let Some(tier) = self.tier {
+            if !tier::allows(tier, name) {
+                self.error(&format!("{} is not available to {:?} modules", url, tier), span);
+                return Err(());
+            }
+        }
+        let Some(host) = self.hosts.iter().find(|host| host.url == url).copied() else {
+            self.error(&format!("{} is not a registered host function", url), span);
+            return Err(());
+        };
+        if host.params.len() != ffi.params.len() {
+            self.error(
+                &format!("{} takes {} arguments, {} declares {}", url, host.params.len(), ffi.name.value, ffi.params.len()),
+                span,
+            );
+            return Err(());
+        }
+        for (param, expected) in ffi.params.iter().zip(host.params) {
+            if !ffi::type_matches(expected, &param.param_type.value) {
+                self.error(
+                    &format!("{} of {} is a {}, {} takes a {}", param.name.value, ffi.name.value, param.param_type.value, url, expected),
+                    param.param_type.span,
+                );
+                return Err(());
+            }
+        }
+        let (ret, ret_span) = match &ffi.return_type {
+            Some(ret) => (ret.value.as_str(), ret.span),
+            None => ("unit", span),
+        };
+        if !ffi::type_matches(ret, host.ret) {
+            self.error(&format!("{} returns a {}, {} returns a {}", ffi.name.value, ret, url, host.ret), ret_span);
+            return Err(());
+        }
+
+        let privacy = match ffi.visibility {
+            Visibility::Public => Privacy::Public,
+            Visibility::Private => Privacy::Private,
+        };
+        let vars = ffi
+            .params
+            .iter()
+            .map(|p| Variable(Spanned::new(ir_span(p.name.span), Ident::from(p.name.value.as_str()))))
+            .collect();
+        let args = ffi
+            .params
+            .iter()
+            .map(|p| call(p.name.span, "read", vec![path(p.name.span, &p.name.value)]))
+            .collect();
+        Ok(Statement::Function(
+            ir_span(span),
+            FunDef { privacy, name: Some(Ident::from(ffi.name.value.as_str())) },
+            FunImpl { vars, body: call(span, &url, args) },
+        ))
+    }
+
+    /// Lowers an expression whose value is needed right away.
+    fn operand(&mut self, expr: &WithSpan<Expr>) -> Result<IrExpr, ()> {
+        let value = self.expr(expr)?;
+        Ok(call(expr.span, "read", vec![value]))
+    }
+
+    fn expr(&mut self, expr: &WithSpan<Expr>) -> Result<IrExpr, ()> {
+        let span = expr.span;
+        match &expr.value {
+            // the literal mapper checks the number fits its type
+            Expr::Number(n) => Ok(IrExpr::Literal(ir_span(span), Literal::Number(n.clone().into()))),
+            // `Debug` keeps the fractional part, so `1.0` does not read back as an integer
+            Expr::Decimal(n) => Ok(IrExpr::Literal(ir_span(span), Literal::Decimal(format!("{:?}", n).into()))),
+            Expr::String(s) => Ok(IrExpr::Literal(ir_span(span), Literal::String(s.clone().into()))),
+            Expr::Boolean(b) => Ok(boolean(span, *b)),
+            Expr::Nil => Ok(unit(span)),
+            Expr::Grouping(inner) => self.expr(inner),
+            Expr::Variable(name) => Ok(path(name.span, &name.value)),
+            Expr::Unary(op, operand) => {
+                // negated with its sign, so the minimum of each type is a literal
+                if let (UnaryOperator::Minus, Expr::Number(n)) = (op.value, &operand.value) {
+                    return Ok(IrExpr::Literal(ir_span(span), Literal::Number(format!("-{}", n).into())));
+                }
+                let operand = self.operand(operand)?;
+                Ok(call(span, unary_nif(op.value), vec![operand]))
+            }
+            Expr::Binary(left, op, right) => {
+                let left = self.operand(left)?;
+                let right = self.operand(right)?;
+                Ok(IrExpr::Call(ir_span(span), vec![path(op.span, binary_nif(op.value)), left, right]))
+            }
+            // `right` is only evaluated when `left` does not decide the result
+            Expr::Logical(left, op, right) => {
+                let cond = Spanned::new(ir_span(left.span), self.operand(left)?);
+                let right = Spanned::new(ir_span(right.span), self.operand(right)?);
+                let decided = Spanned::new(ir_span(op.span), boolean(op.span, op.value == LogicalOperator::Or));
+                let (then_expr, else_expr) = match op.value {
+                    LogicalOperator::And => (right, decided),
+                    LogicalOperator::Or => (decided, right),
+                };
+                Ok(IrExpr::If {
+                    span: ir_span(span),
+                    cond: Box::new(cond),
+                    then_expr: Box::new(then_expr),
+                    else_expr: Box::new(else_expr),
+                })
+            }
+            Expr::Call(callee, args) => {
+                let nif = match &callee.value {
+                    Expr::Variable(name) if !self.scope.contains(&name.value) && !self.defined.contains(&name.value) => {
+                        Some(name)
+                    }
+                    _ => None,
+                };
+                if let (Some(name), Some(tier)) = (nif, self.tier) {
+                    if !tier::allows(tier, &name.value) {
+                        self.error(&format!("{} is not available to {:?} modules", name.value, tier), name.span);
+                        return Err(());
+                    }
+                }
+                let read_args = nif.is_some_and(|name| !TAKE_VARIABLES.contains(&name.value.as_str()));
+                let mut exprs = vec![self.expr(callee)?];
+                for arg in args {
+                    exprs.push(if read_args { self.operand(arg)? } else { self.expr(arg)? });
+                }
+                Ok(IrExpr::Call(ir_span(span), exprs))
+            }
+            Expr::Assign(name, value) => {
+                let value = self.expr(value)?;
+                Ok(call(span, "bind", vec![path(name.span, &name.value), value]))
+            }
+            Expr::List(items) => {
+                let items = items.iter().map(|item| self.expr(item)).collect::<Result<_, _>>()?;
+                Ok(IrExpr::Sequence(ir_span(span), items))
+            }
+            Expr::ListGet(list, index) => {
+                let list = self.operand(list)?;
+                let index = self.operand(index)?;
+                Ok(call(span, "get", vec![list, index]))
+            }
+            Expr::ListSet(list, index, value) => {
+                let list = self.operand(list)?;
+                let index = self.operand(index)?;
+                let value = self.expr(value)?;
+                Ok(call(span, "set", vec![list, index, value]))
+            }
+            _ => {
+                self.error("Unsupported expression", span);
+                Err(())
+            }
+        }
+    }
+}
+
+/// Collects the variables of `scope` that `stmt` refers to, in order of
+/// first use.
+fn stmt_captures(stmt: &WithSpan<Stmt>, scope: &[Identifier], captured: &mut Vec<Identifier>) {
+    match &stmt.value {
+        Stmt::Expression(expr) | Stmt::Print(expr) | Stmt::Return(expr) => expr_captures(expr, scope, captured),
+        Stmt::Let(_, Some(expr)) => expr_captures(expr, scope, captured),
+        Stmt::If(cond, then_branch, else_branch) => {
+            expr_captures(cond, scope, captured);
+            stmt_captures(then_branch, scope, captured);
+            if let Some(branch) = else_branch {
+                stmt_captures(branch, scope, captured);
+            }
+        }
+        Stmt::Block(stmts) | Stmt::Thread(stmts) => {
+            for stmt in stmts {
+                stmt_captures(stmt, scope, captured);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn capture(name: &Identifier, scope: &[Identifier], captured: &mut Vec<Identifier>) {
+    if scope.contains(name) && !captured.contains(name) {
+        captured.push(name.clone());
+    }
+}
+
+fn expr_captures(expr: &WithSpan<Expr>, scope: &[Identifier], captured: &mut Vec<Identifier>) {
+    match &expr.value {
+        Expr::Variable(name) => capture(&name.value, scope, captured),
+        Expr::Assign(name, value) => {
+            capture(&name.value, scope, captured);
+            expr_captures(value, scope, captured);
+        }
+        Expr::Grouping(inner) | Expr::Unary(_, inner) | Expr::Get(inner, _) => expr_captures(inner, scope, captured),
+        Expr::Binary(left, _, right) | Expr::Logical(left, _, right) | Expr::ListGet(left, right) => {
+            expr_captures(left, scope, captured);
+            expr_captures(right, scope, captured);
+        }
+        Expr::Set(target, _, value) => {
+            expr_captures(target, scope, captured);
+            expr_captures(value, scope, captured);
+        }
+        Expr::ListSet(list, index, value) => {
+            expr_captures(list, scope, captured);
+            expr_captures(index, scope, captured);
+            expr_captures(value, scope, captured);
+        }
+        Expr::Call(callee, args) => {
+            expr_captures(callee, scope, captured);
+            for arg in args {
+                expr_captures(arg, scope, captured);
+            }
+        }
+        Expr::UrlCall(_, args) | Expr::List(args) => {
+            for arg in args {
+                expr_captures(arg, scope, captured);
+            }
+        }
+        Expr::Number(_) | Expr::Decimal(_) | Expr::Boolean(_) | Expr::Nil | Expr::String(_) => {}
+    }
+}
+
+/// The declarations of the modules of `tier` declared in `ast`.
+fn declarations(ast: &Ast, tier: KeyTier) -> impl Iterator<Item = &WithSpan<Stmt>> {
+    ast.iter()
+        .filter_map(|stmt| match &stmt.value {
+            Stmt::Module(Module::Corporal { stmts, .. }) => Some((KeyTier::Corporal, stmts)),
+            Stmt::Module(Module::Major { stmts, .. }) => Some((KeyTier::Major, stmts)),
+            Stmt::Module(Module::Brigadier { stmts, .. }) => Some((KeyTier::Brigadier, stmts)),
+            Stmt::Module(Module::General { stmts, .. }) => Some((KeyTier::General, stmts)),
+            _ => None,
+        })
+        .filter(move |(module_tier, _)| *module_tier == tier)
+        .flat_map(|(_, stmts)| stmts.iter())
+}
+
+/// The functions of the modules of `tier` declared in `ast`, with their
+/// spans.
+fn functions(ast: &Ast, tier: KeyTier) -> impl Iterator<Item = (&Function, Span)> {
+    declarations(ast, tier).filter_map(|stmt| match &stmt.value {
+        Stmt::Function(function) => Some((function, stmt.span)),
+        _ => None,
+    })
+}
+
+/// The FFI declarations of the modules of `tier` declared in `ast`, with
+/// their spans.
+fn ffis(ast: &Ast, tier: KeyTier) -> impl Iterator<Item = (&Ffi, Span)> {
+    declarations(ast, tier).filter_map(|stmt| match &stmt.value {
+        Stmt::Ffi(ffi) => Some((ffi, stmt.span)),
+        _ => None,
+    })
+}
+
+/// The names the modules of `tier` in `ast` bind, functions and FFI
+/// declarations.
+fn defined(ast: &Ast, tier: KeyTier) -> impl Iterator<Item = &str> {
+    functions(ast, tier)
+        .map(|(function, _)| function_name(function))
+        .chain(ffis(ast, tier).map(|(ffi, _)| ffi.name.value.as_str()))
+}
+
+fn function_name(function: &Function) -> &str {
+    &function.name.as_ref().expect("declared functions are named").value
+}
+
+/// Lowers the functions of the modules of `tier` declared in `ast` into one
+/// IR module, the code a process of that tier runs. Its FFI declarations
+/// bind the host functions of `hosts`.
+pub fn lower(ast: &Ast, tier: KeyTier, hosts: &[Signature]) -> Result<IrModule, Vec<Diagnostic>> {
+    if !declares(ast, tier) {
+        return Err(vec![Diagnostic {
+            message: format!("No {:?} module declared", tier),
+            span: Span::empty(),
+        }]);
+    }
+    let mut lowerer = Lowerer {
+        defined: defined(ast, tier).map(|name| name.to_string()).collect(),
+        tier: Some(tier),
+        hosts: hosts.to_vec(),
+        ..Default::default()
+    };
+    let mut statements = Vec::new();
+    for (function, span) in functions(ast, tier) {
+        if let Ok(statement) = lowerer.function(function, span) {
+            statements.push(statement);
+        }
+    }
+    for (ffi, span) in ffis(ast, tier) {
+        if let Ok(statement) = lowerer.ffi(ffi, span) {
+            statements.push(statement);
+        }
+    }
+    if !lowerer.diagnostics.is_empty() {
+        return Err(lowerer.diagnostics);
+    }
+    statements.append(&mut lowerer.lifted);
+    Ok(IrModule { statements })
+}
+
+/// Whether `ast` declares a module of `tier`, even an empty one.
+fn declares(ast: &Ast, tier: KeyTier) -> bool {
+    ast.iter().any(|stmt| {
+        matches!(
+            (&stmt.value, tier),
+            (Stmt::Module(Module::Corporal { .. }), KeyTier::Corporal)
+                | (Stmt::Module(Module::Major { .. }), KeyTier::Major)
+                | (Stmt::Module(Module::Brigadier { .. }), KeyTier::Brigadier)
+                | (Stmt::Module(Module::General { .. }), KeyTier::General)
+        )
+    })
+}
+
+/// The [`PRELUDE`] functions that the modules of `tier` in `ast` do not
+/// define themselves.
+pub fn prelude(ast: &Ast, tier: KeyTier) -> Vec<Statement> {
+    let prelude = crate::parse(PRELUDE).expect("the prelude parses");
+    let mut lowerer = Lowerer {
+        defined: defined(&prelude, KeyTier::Corporal).map(|name| name.to_string()).collect(),
+        ..Default::default()
+    };
+    functions(&prelude, KeyTier::Corporal)
+        .filter(|(function, _)| defined(ast, tier).all(|name| name != function_name(function)))
+        .map(|(function, span)| lowerer.function(function, span).expect("the prelude lowers"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lower_str(code: &str) -> Result<IrModule, Vec<Diagnostic>> {
+        lower_tier(code, KeyTier::Corporal)
+    }
+
+    fn lower_tier(code: &str, tier: KeyTier) -> Result<IrModule, Vec<Diagnostic>> {
+        lower(&crate::parse(code)?, tier, &[])
+    }
+
+    fn function_names(module: &IrModule) -> Vec<Ident> {
+        module
+            .statements
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::Function(_, FunDef { name, .. }, _) => name.clone(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn threads_are_lifted() {
+        let module = lower_str(
+            "corporal corp::Corporal {
+                pub main :: () {
+                    let x;
+                    thread {
+                        x = 0;
+                    }
+                    x;
+                }
+            }",
+        )
+        .unwrap();
+        assert_eq!(function_names(&module), vec![Ident::from("main"), Ident::from("thread#0")]);
+        let Statement::Function(_, _, FunImpl { vars, .. }) = &module.statements[1] else {
+            panic!("expected the lifted thread");
+        };
+        assert_eq!(vars.len(), 1);
+    }
+
+    #[test]
+    fn operands_are_read() {
+        let module = lower_str(
+            "corporal corp::Corporal {
+                pub main :: () {
+                    let x;
+                    x == 0;
+                }
+            }",
+        )
+        .unwrap();
+        let Statement::Function(_, _, FunImpl { body: IrExpr::Let(_, _, then), .. }) = &module.statements[0] else {
+            panic!("expected main to start with a let");
+        };
+        let IrExpr::Call(_, exprs) = then.as_ref() else {
+            panic!("expected the comparison");
+        };
+        // `x` is wrapped in a call to `read` rather than passed as a path
+        assert!(matches!(&exprs[1], IrExpr::Call(_, read) if read.len() == 2));
+    }
+
+    #[test]
+    fn negative_numbers_are_literals() {
+        let module = lower_str(
+            "corporal corp::Corporal {
+                pub main :: () {
+                    -128i8;
+                }
+            }",
+        )
+        .unwrap();
+        let Statement::Function(_, _, FunImpl { body, .. }) = &module.statements[0] else {
+            panic!("expected main");
+        };
+        // the sign is part of the literal rather than a call to `neg`
+        assert!(matches!(body, IrExpr::Literal(_, Literal::Number(n)) if n.as_str() == "-128i8"));
+    }
+
+    #[test]
+    fn nif_arguments_are_read() {
+        let module = lower_str(
+            "corporal corp::Corporal {
+                pub main :: () {
+                    let x;
+                    len(x);
+                    twice(x);
+                }
+                twice :: (y: int) {
+                    y * 2;
+                }
+            }",
+        )
+        .unwrap();
+        let Statement::Function(_, _, FunImpl { body: IrExpr::Let(_, _, then), .. }) = &module.statements[0] else {
+            panic!("expected main to start with a let");
+        };
+        let IrExpr::Let(_, len, twice) = then.as_ref() else {
+            panic!("expected the two calls");
+        };
+        let (IrExpr::Call(_, len), IrExpr::Call(_, twice)) = (len.as_ref(), twice.as_ref()) else {
+            panic!("expected the two calls");
+        };
+        // the NIF gets `read(x)`, the function of the module `x` itself
+        assert!(matches!(&len[1], IrExpr::Call(_, read) if read.len() == 2));
+        assert!(matches!(&twice[1], IrExpr::Path(..)));
+    }
+
+    /// The expression statement `main` consists of.
+    fn main_expr(body: &str) -> Expr {
+        let ast = crate::parse(&format!("corporal corp::Corporal {{ pub main :: () {{ {} }} }}", body)).unwrap();
+        let (main, _) = functions(&ast, KeyTier::Corporal).next().unwrap();
+        let Stmt::Expression(expr) = &main.body[0].value else {
+            panic!("expected an expression statement");
+        };
+        expr.value.clone()
+    }
+
+    #[test]
+    fn operators_name_nifs() {
+        // every NIF named here is exercised by the garrison tests
+        for (op, nif) in [
+            ("/", "/"),
+            ("*", "*"),
+            ("+", "+"),
+            ("-", "-"),
+            (">", ">"),
+            (">=", ">="),
+            ("<", "<"),
+            ("<=", "<="),
+            ("!=", "!="),
+            ("==", "=="),
+        ] {
+            let Expr::Binary(_, parsed, _) = main_expr(&format!("1 {} 2;", op)) else {
+                panic!("expected {} to parse as a binary operator", op);
+            };
+            assert_eq!(binary_nif(parsed.value), nif);
+        }
+        for (op, nif) in [("-", "neg"), ("!", "not")] {
+            let Expr::Unary(parsed, _) = main_expr(&format!("{}true;", op)) else {
+                panic!("expected {} to parse as a unary operator", op);
+            };
+            assert_eq!(unary_nif(parsed.value), nif);
+        }
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        for op in ["and", "or"] {
+            let module = lower_str(&format!(
+                "corporal corp::Corporal {{
+                    pub main :: () {{
+                        true {} false;
+                    }}
+                }}",
+                op
+            ))
+            .unwrap();
+            let Statement::Function(_, _, FunImpl { body, .. }) = &module.statements[0] else {
+                panic!("expected main");
+            };
+            // `and` evaluates its right side when the left is true, `or` when it is false
+            let IrExpr::If { then_expr, else_expr, .. } = body else {
+                panic!("expected {} to lower to a conditional", op);
+            };
+            let (right, decided) = if op == "and" { (then_expr, else_expr) } else { (else_expr, then_expr) };
+            assert!(matches!(&right.inner, IrExpr::Call(..)));
+            assert!(matches!(&decided.inner, IrExpr::Literal(_, Literal::Bool(b)) if (&**b == "true") == (op == "or")));
+        }
+    }
+
+    #[test]
+    fn decimals_stay_decimal() {
+        let module = lower_str(
+            "corporal corp::Corporal {
+                pub main :: () {
+                    1.0;
+                }
+            }",
+        )
+        .unwrap();
+        let Statement::Function(_, _, FunImpl { body, .. }) = &module.statements[0] else {
+            panic!("expected main");
+        };
+        assert!(matches!(body, IrExpr::Literal(_, Literal::Decimal(d)) if &**d == "1.0"));
+    }
+
+    #[test]
+    fn strings_are_literals() {
+        let module = lower_str(
+            "corporal corp::Corporal {
+                pub main :: () {
+                    \"corporal1\";
+                }
+            }",
+        )
+        .unwrap();
+        let Statement::Function(_, _, FunImpl { body, .. }) = &module.statements[0] else {
+            panic!("expected main");
+        };
+        assert!(matches!(body, IrExpr::Literal(_, Literal::String(s)) if &**s == "corporal1"));
+    }
+
+    #[test]
+    fn lists_are_sequences() {
+        let module = lower_str(
+            "corporal corp::Corporal {
+                pub main :: () {
+                    let xs = [1, 2];
+                    xs[0] = xs[1];
+                }
+            }",
+        )
+        .unwrap();
+        let Statement::Function(_, _, FunImpl { body: IrExpr::Let(_, value, then), .. }) = &module.statements[0] else {
+            panic!("expected main to start with a let");
+        };
+        assert!(matches!(value.as_ref(), IrExpr::Sequence(_, items) if items.len() == 2));
+        // `set` takes the list, the index and the value `get` returns
+        let IrExpr::Call(_, set) = then.as_ref() else {
+            panic!("expected the update");
+        };
+        assert_eq!(set.len(), 4);
+        assert!(matches!(&set[3], IrExpr::Call(_, get) if get.len() == 3));
+    }
+
+    #[test]
+    fn prelude_is_not_redefined() {
+        let ast = crate::parse(
+            "corporal corp::Corporal {
+                map :: (f: fun, xs: list) -> list {
+                    xs;
+                }
+            }",
+        )
+        .unwrap();
+        let module = IrModule { statements: prelude(&ast, KeyTier::Corporal) };
+        assert_eq!(function_names(&module), vec![Ident::from("fold"), Ident::from("filter")]);
+    }
+
+    #[test]
+    fn threads_capture_every_variable_they_use() {
+        let module = lower_str(
+            "corporal corp::Corporal {
+                pub main :: () {
+                    let x, y, z;
+                    thread {
+                        z = x + y;
+                    }
+                    z;
+                }
+            }",
+        )
+        .unwrap();
+        let Statement::Function(_, _, FunImpl { vars, .. }) = &module.statements[1] else {
+            panic!("expected the lifted thread");
+        };
+        let params = vars.iter().map(|Variable(name)| name.inner.clone()).collect::<Vec<_>>();
+        assert_eq!(params, vec![Ident::from("z"), Ident::from("x"), Ident::from("y")]);
+    }
+
+    #[test]
+    fn each_tier_lowers_its_own_module() {
+        let code = "corporal corp::Corporal {
+                pub main :: () {
+                    1;
+                }
+            }
+            major corp::Major {
+                pub main :: () {
+                    2;
+                }
+                report :: () {
+                    3;
+                }
+            }";
+        assert_eq!(function_names(&lower_tier(code, KeyTier::Corporal).unwrap()), vec![Ident::from("main")]);
+        assert_eq!(
+            function_names(&lower_tier(code, KeyTier::Major).unwrap()),
+            vec![Ident::from("main"), Ident::from("report")]
+        );
+        let diagnostics = lower_tier(code, KeyTier::General).unwrap_err();
+        assert_eq!(diagnostics[0].message, "No General module declared");
+    }
+
+    #[test]
+    fn nifs_follow_the_tier() {
+        let diagnostics = lower_str(
+            "corporal corp::Corporal {
+                pub main :: () {
+                    monitor(self());
+                }
+            }",
+        )
+        .unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "monitor is not available to Corporal modules");
+
+        assert!(lower_tier(
+            "major corp::Major {
+                pub main :: () {
+                    monitor(self());
+                }
+            }",
+            KeyTier::Major,
+        )
+        .is_ok());
+        // a function of the module shadows the NIF
+        assert!(lower_str(
+            "corporal corp::Corporal {
+                pub main :: () {
+                    monitor(1);
+                }
+                monitor :: (x: int) {
+                    x;
+                }
+            }"
+        )
+        .is_ok());
+    }
+
+    /// The one diagnostic lowering `main` of a `tier` module running `body`.
+    fn refusal(tier: KeyTier, body: &str) -> String {
+        let keyword = format!("{:?}", tier).to_lowercase();
+        let code = format!("{} corp::App {{ pub main :: () {{ {} }} }}", keyword, body);
+        let diagnostics = lower_tier(&code, tier).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        diagnostics[0].message.clone()
+    }
+
+    #[test]
+    fn generals_only_install_brigadiers() {
+        assert!(lower_tier("general corp::App { pub main :: () { install_brigadier(garrison()); } }", KeyTier::General).is_ok());
+        assert_eq!(refusal(KeyTier::General, "spawn(main, [], garrison());"), "spawn is not available to General modules");
+    }
+
+    #[test]
+    fn brigadiers_do_not_install_brigadiers() {
+        assert!(lower_tier("brigadier corp::App { pub main :: () { supervise(\"series\", [spawn(main, [], garrison())]); } }", KeyTier::Brigadier).is_ok());
+        assert_eq!(
+            refusal(KeyTier::Brigadier, "install_brigadier(garrison());"),
+            "install_brigadier is not available to Brigadier modules"
+        );
+    }
+
+    #[test]
+    fn majors_do_not_install_brigadiers() {
+        assert!(lower_tier("major corp::App { pub main :: () { supervise(\"series\", [spawn(main, [], garrison())]); } }", KeyTier::Major).is_ok());
+        assert_eq!(
+            refusal(KeyTier::Major, "install_brigadier(garrison());"),
+            "install_brigadier is not available to Major modules"
+        );
+    }
+
+    #[test]
+    fn corporals_only_compute_and_message() {
+        assert!(lower_str("corporal corp::App { pub main :: () { let x; thread { x = 1; } x; } }").is_ok());
+        assert_eq!(refusal(KeyTier::Corporal, "spawn(main, [], garrison());"), "spawn is not available to Corporal modules");
+        assert_eq!(
+            refusal(KeyTier::Corporal, "supervise(\"series\", [self()]);"),
+            "supervise is not available to Corporal modules"
+        );
+    }
+
+    const TRAP_EXIT: Signature = Signature { url: "sio_vm::sio::process::trap_exit", params: &["bool"], ret: "bool" };
+
+    #[test]
+    fn ffi_declarations_call_the_host() {
+        let ast = crate::parse(
+            "major corp::Major {
+                ffi_trap_exit :: sio_vm::/sio/process/trap_exit :: (flag: bool) -> bool;
+                pub main :: () {
+                    ffi_trap_exit(true);
+                }
+            }",
+        )
+        .unwrap();
+        let (ffi, _) = ffis(&ast, KeyTier::Major).next().unwrap();
+        assert_eq!(ffi.url_key(), "sio_vm::sio::process::trap_exit");
+        assert_eq!(ffi.params.len(), 1);
+
+        let module = lower(&ast, KeyTier::Major, &[TRAP_EXIT]).unwrap();
+        assert_eq!(function_names(&module), vec![Ident::from("main"), Ident::from("ffi_trap_exit")]);
+        let Statement::Function(_, _, FunImpl { vars, body: IrExpr::Call(_, exprs) }) = &module.statements[1] else {
+            panic!("expected the call to the host");
+        };
+        assert_eq!(vars.len(), 1);
+        assert_eq!(exprs.len(), 2);
+    }
+
+    #[test]
+    fn ffi_declarations_match_the_host() {
+        let refusal = |tier: KeyTier, declaration: &str| {
+            let keyword = format!("{:?}", tier).to_lowercase();
+            let code = format!("{} corp::App {{ {} pub main :: () {{ 1; }} }}", keyword, declaration);
+            let diagnostics = lower(&crate::parse(&code).unwrap(), tier, &[TRAP_EXIT]).unwrap_err();
+            assert_eq!(diagnostics.len(), 1);
+            diagnostics[0].message.clone()
+        };
+        assert_eq!(
+            refusal(KeyTier::Major, "f :: host::/missing :: (flag: bool) -> bool;"),
+            "host::missing is not a registered host function"
+        );
+        assert_eq!(
+            refusal(KeyTier::Major, "f :: sio_vm::/sio/process/trap_exit :: (flag: bool, other: bool) -> bool;"),
+            "sio_vm::sio::process::trap_exit takes 1 arguments, f declares 2"
+        );
+        assert_eq!(
+            refusal(KeyTier::Major, "f :: sio_vm::/sio/process/trap_exit :: (flag: int) -> bool;"),
+            "flag of f is a int, sio_vm::sio::process::trap_exit takes a bool"
+        );
+        assert_eq!(
+            refusal(KeyTier::Major, "f :: sio_vm::/sio/process/trap_exit :: (flag: bool) -> str;"),
+            "f returns a str, sio_vm::sio::process::trap_exit returns a bool"
+        );
+        assert_eq!(
+            refusal(KeyTier::Corporal, "f :: sio_vm::/sio/process/trap_exit :: (flag: bool) -> bool;"),
+            "sio_vm::sio::process::trap_exit is not available to Corporal modules"
+        );
+    }
+}
@@ -51,11 +51,11 @@ fn parse_hierarchical_names(p: &mut Parser) -> Result<HierarchicalName, ()> {
     let mut parts = Vec::new();
 
     // Expect the first part of the name
-    parts.push(expect_identifier(p)?);
+    parts.push(expect_url_part(p)?);
 
     while p.peek() == TokenKind::ColonColon {
         p.expect(TokenKind::ColonColon)?;
-        parts.push(expect_identifier(p)?);
+        parts.push(expect_url_part(p)?);
     }
 
     Ok(HierarchicalName { parts })
@@ -98,7 +98,7 @@ fn parse_corporal_declaration(p: &mut Parser) -> Result<WithSpan<Stmt>, ()> {
     let name = parse_hierarchical_names(p)?;
     p.expect(TokenKind::LeftBrace)?;
 
-    let stmts = parse_corporal_declarations(p)?;
+    let stmts = parse_function_declarations(p)?;
 
     let end_span = p.expect(TokenKind::RightBrace)?;
 
@@ -108,7 +108,7 @@ fn parse_corporal_declaration(p: &mut Parser) -> Result<WithSpan<Stmt>, ()> {
     ))
 }
 
-fn parse_corporal_declarations(p: &mut Parser) -> Result<Vec<WithSpan<Stmt>>, ()> {
+fn parse_function_declarations(p: &mut Parser) -> Result<Vec<WithSpan<Stmt>>, ()> {
     let mut functions: Vec<WithSpan<Stmt>> = Vec::new();
 
     while p.check(TokenKind::Pub) || p.check(TokenKind::Identifier) {
@@ -124,12 +124,34 @@ fn parse_corporal_declarations(p: &mut Parser) -> Result<Vec<WithSpan<Stmt>>, ()
     Ok(functions)
 }
 
-fn parse_major_declaration(it: &mut Parser) -> Result<WithSpan<Stmt>, ()> {
-    todo!();
+fn parse_major_declaration(p: &mut Parser) -> Result<WithSpan<Stmt>, ()> {
+    let begin_span = p.expect(TokenKind::Major)?;
+    let name = parse_hierarchical_names(p)?;
+    p.expect(TokenKind::LeftBrace)?;
+
+    let stmts = parse_function_declarations(p)?;
+
+    let end_span = p.expect(TokenKind::RightBrace)?;
+
+    Ok(WithSpan::new(
+        Stmt::Module(Module::Major{ name, stmts }),
+        Span::union(&begin_span, &end_span),
+    ))
 }
 
-fn parse_brigadier_declaration(it: &mut Parser) -> Result<WithSpan<Stmt>, ()> {
-    todo!();
+fn parse_brigadier_declaration(p: &mut Parser) -> Result<WithSpan<Stmt>, ()> {
+    let begin_span = p.expect(TokenKind::Brigadier)?;
+    let name = parse_hierarchical_names(p)?;
+    p.expect(TokenKind::LeftBrace)?;
+
+    let stmts = parse_function_declarations(p)?;
+
+    let end_span = p.expect(TokenKind::RightBrace)?;
+
+    Ok(WithSpan::new(
+        Stmt::Module(Module::Brigadier{ name, stmts }),
+        Span::union(&begin_span, &end_span),
+    ))
 }
 
 fn parse_declaration(it: &mut Parser) -> Result<WithSpan<Stmt>, ()> {
@@ -168,6 +190,9 @@ fn parse_function_declaration(it: &mut Parser) -> Result<WithSpan<Stmt>, ()> {
     let name = expect_identifier(it)?;
 
     it.expect(TokenKind::ColonColon)?;
+    if !it.check(TokenKind::LeftParen) {
+        return parse_ffi_declaration(it, visibility, name);
+    }
     it.expect(TokenKind::LeftParen)?;
     let params = parse_params(it)?;
     it.expect(TokenKind::RightParen)?;
@@ -200,6 +225,50 @@ fn parse_function_declaration(it: &mut Parser) -> Result<WithSpan<Stmt>, ()> {
     Ok(WithSpan::new(stmt, span))
 }
 
+/// Parses the rest of `name :: url :: (params) -> ret;`, from the url on.
+/// Url parts are separated by `::` or `/`, so `sio_vm::/sio/process/trap_exit`
+/// has the parts `sio_vm`, `sio`, `process` and `trap_exit`.
+fn parse_ffi_declaration(
+    it: &mut Parser,
+    visibility: Visibility,
+    name: WithSpan<Identifier>,
+) -> Result<WithSpan<Stmt>, ()> {
+    let mut parts = Vec::new();
+    loop {
+        if it.check(TokenKind::Slash) {
+            it.expect(TokenKind::Slash)?;
+        }
+        parts.push(expect_url_part(it)?);
+        if it.check(TokenKind::Slash) {
+            continue;
+        }
+        it.expect(TokenKind::ColonColon)?;
+        if it.check(TokenKind::LeftParen) {
+            break;
+        }
+    }
+    it.expect(TokenKind::LeftParen)?;
+    let params = parse_params(it)?;
+    it.expect(TokenKind::RightParen)?;
+    let return_type = if it.check(TokenKind::Arrow) {
+        it.expect(TokenKind::Arrow)?;
+        Some(expect_identifier(it)?)
+    } else {
+        None
+    };
+    let end_span = it.expect(TokenKind::Semicolon)?;
+
+    let ffi = Ffi {
+        visibility,
+        name: name.clone(),
+        url: HierarchicalName { parts },
+        params,
+        return_type,
+    };
+    let span = Span::union(&name, end_span);
+    Ok(WithSpan::new(Stmt::Ffi(ffi), span))
+}
+
 fn parse_params(it: &mut Parser) -> Result<Vec<Param>, ()> {
     let mut params: Vec<Param> = Vec::new();
 
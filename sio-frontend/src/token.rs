@@ -1,5 +1,6 @@
 use alloc::fmt::Display;
 use alloc::string::String;
+use sio::key::{KeyError, KeyLiteral};
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Token {
@@ -34,8 +35,11 @@ pub enum Token {
     // Literals.
     Identifier(String),
     String(String),
-    PublicKey(String),
-    Number(f64),
+    PublicKey(KeyLiteral),
+    /// A number without a fractional part, as written.
+    Number(String),
+    /// A number written with a fractional part, `1.0` included.
+    Decimal(f64),
     True,
     False,
 
@@ -62,6 +66,7 @@ pub enum Token {
     // Other.
     Eof,
     UnterminatedString,
+    InvalidPublicKey(KeyError),
     Unknown(char),
 }
 
@@ -100,6 +105,7 @@ pub enum TokenKind {
     String,
     PublicKey,
     Number,
+    Decimal,
     True,
     False,
 
@@ -126,6 +132,7 @@ pub enum TokenKind {
     // Other.
     Eof,
     UnterminatedString,
+    InvalidPublicKey,
     Unknown,
 }
 
@@ -171,6 +178,7 @@ impl From<&Token> for TokenKind {
             Token::String(_) => TokenKind::String,
             Token::PublicKey(_) => TokenKind::PublicKey,
             Token::Number(_) => TokenKind::Number,
+            Token::Decimal(_) => TokenKind::Decimal,
             Token::True => TokenKind::True,
             Token::False => TokenKind::False,
             Token::Url => TokenKind::Url,
@@ -195,6 +203,7 @@ impl From<&Token> for TokenKind {
             Token::Fun => TokenKind::Fun,
             Token::Eof => TokenKind::Eof,
             Token::UnterminatedString => TokenKind::UnterminatedString,
+            Token::InvalidPublicKey(_) => TokenKind::InvalidPublicKey,
             Token::Unknown(_) => TokenKind::Unknown,
         }
     }
@@ -235,6 +244,7 @@ impl Display for TokenKind {
             TokenKind::String => "string",
             TokenKind::PublicKey => "public_key",
             TokenKind::Number => "number",
+            TokenKind::Decimal => "decimal",
             TokenKind::True => "true",
             TokenKind::False => "false",
             TokenKind::Url => "'url'",
@@ -254,6 +264,7 @@ impl Display for TokenKind {
             TokenKind::Fun => "'fn'",
             TokenKind::Eof => "<EOF>",
             TokenKind::UnterminatedString => "<Unterminated String>",
+            TokenKind::InvalidPublicKey => "<Invalid Public Key>",
             TokenKind::Unknown => "<Unknown>",
         })
     }
@@ -5,6 +5,7 @@ use alloc::str;
 use alloc::string::String;
 use alloc::str::Chars;
 use alloc::vec::Vec;
+use sio::key;
 
 struct Scanner<'a> {
     current_position: BytePos,
@@ -103,6 +104,12 @@ impl<'a> Lexer<'a> {
         match ch {
             ':' => Some(self.either(':', Token::ColonColon, Token::Colon)),
             '=' => Some(self.either('=', Token::EqualEqual, Token::Equal)),
+            '!' => Some(self.either('=', Token::BangEqual, Token::Bang)),
+            '<' => Some(self.either('=', Token::LessEqual, Token::Less)),
+            '>' => Some(self.either('=', Token::GreaterEqual, Token::Greater)),
+            '+' => Some(Token::Plus),
+            '*' => Some(Token::Star),
+            '.' => Some(Token::Dot),
             ' ' => None,
             '\n' => None,
             '/' => self.comment_or_slash(),
@@ -148,9 +155,21 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// A number keeps its text, the compiler checks it fits its type. An
+    /// integer may have a radix prefix, `0x`, `0o` or `0b`, and a type suffix
+    /// such as `u8`, as in `0xffu8`.
     fn number(&mut self, x: char) -> Option<Token> {
         let mut number = String::new();
         number.push(x);
+        if x == '0' && self.it.peek().is_some_and(|ch| matches!(ch, 'x' | 'o' | 'b')) {
+            let rest: String = self
+                .it
+                .consume_while(|a| a.is_ascii_alphanumeric())
+                .into_iter()
+                .collect();
+            number.push_str(rest.as_str());
+            return Some(Self::number_or_key(number));
+        }
         let num: String = self
             .it
             .consume_while(|a| a.is_numeric())
@@ -165,8 +184,25 @@ impl<'a> Lexer<'a> {
                 .collect();
             number.push('.');
             number.push_str(num2.as_str());
+            return Some(Token::Decimal(number.parse::<f64>().unwrap()));
+        }
+        let suffix: String = self
+            .it
+            .consume_while(|a| a.is_ascii_alphanumeric())
+            .into_iter()
+            .collect();
+        number.push_str(suffix.as_str());
+        Some(Self::number_or_key(number))
+    }
+
+    /// Bare hex keys start with a digit as often as not, reject them here too.
+    fn number_or_key(number: String) -> Token {
+        if key::looks_like_key(&number) {
+            if let Err(e) = key::decode(&number) {
+                return Token::InvalidPublicKey(e);
+            }
         }
-        Some(Token::Number(number.parse::<f64>().unwrap()))
+        Token::Number(number)
     }
 
     //TODO Static the keywords
@@ -189,6 +225,9 @@ impl<'a> Lexer<'a> {
         keywords.insert("true", Token::True);
         keywords.insert("false", Token::False);
         keywords.insert("print", Token::Print);
+        keywords.insert("and", Token::And);
+        keywords.insert("or", Token::Or);
+        keywords.insert("nil", Token::Nil);
         match keywords.get(identifier) {
             None => None,
             Some(token) => Some(token.clone()),
@@ -204,12 +243,11 @@ impl<'a> Lexer<'a> {
             .into_iter()
             .collect();
         identifier.push_str(rest.as_str());
-        if identifier.starts_with("spub1") {
-            if identifier.len() == 69 {
-                if !identifier.contains('_') {
-                    return Some(Token::PublicKey(identifier));
-                }
-            }
+        if key::looks_like_key(&identifier) {
+            return match key::decode(&identifier) {
+                Ok(key) => Some(Token::PublicKey(key)),
+                Err(e) => Some(Token::InvalidPublicKey(e)),
+            };
         }
         match self.keyword(&identifier) {
             None => Some(Token::Identifier(identifier)),
@@ -246,6 +284,7 @@ pub fn tokenize_with_context(buf: &str) -> Vec<WithSpan<Token>> {
 #[cfg(test)]
 mod tests {
     use super::Token;
+    use sio::key::KeyError;
     use alloc::vec::Vec;
     use alloc::vec;
     use crate::alloc::string::ToString;
@@ -257,9 +296,27 @@ mod tests {
             .collect()
     }
 
+    fn public_key() -> Token {
+        Token::PublicKey("spub108ms3sj6y0knvassltxpgq66m3a6fvdl4yjjaa2udsj0rwds827s0n578r".parse().unwrap())
+    }
+
     #[test]
     fn test_errors() {
         assert_eq!(tokenize("\"test"), vec![Token::UnterminatedString]);
+        assert_eq!(tokenize("spub108ms3sj6y0knvassltxpgq66m3a6fvdl4yjjaa2udsj0rwds827s0n578q"),
+            vec![Token::InvalidPublicKey(KeyError::InvalidChecksum)]);
+        assert_eq!(tokenize("spub108ms3sj6y0knvassltxpgq66m3a6fvdl4yjjaa2udsj0rwds827s0n578rq"),
+            vec![Token::InvalidPublicKey(KeyError::InvalidLength(33))]);
+        assert_eq!(tokenize("spub108ms3sj6y0knvassltxpgq66m3a6fvdl4yjjaa2udsj0rwds827s0n578"),
+            vec![Token::InvalidPublicKey(KeyError::InvalidLength(31))]);
+        assert_eq!(tokenize("spub1 spubg1x"),
+            vec![Token::Identifier("spub1".to_string()), Token::InvalidPublicKey(KeyError::InvalidLength(0))]);
+        assert_eq!(tokenize("sio79f708c25a23ed367610facc14035adc7ba4b1bfa9252ef55c6c24f1b9b03abd"),
+            vec![Token::InvalidPublicKey(KeyError::LegacyHex)]);
+        assert_eq!(tokenize("79f708c25a23ed367610facc14035adc7ba4b1bfa9252ef55c6c24f1b9b03abd"),
+            vec![Token::InvalidPublicKey(KeyError::LegacyHex)]);
+        assert_eq!(tokenize("f79708c25a23ed367610facc14035adc7ba4b1bfa9252ef55c6c24f1b9b03abd"),
+            vec![Token::InvalidPublicKey(KeyError::LegacyHex)]);
         assert_eq!(tokenize("spub1_9f708c25a23ed367610facc14035adc7ba4b1bfa9252ef55c6c24f1b9b03aba"),
             vec![Token::Identifier("spub1_9f708c25a23ed367610facc14035adc7ba4b1bfa9252ef55c6c24f1b9b03aba".to_string())]);
     }
@@ -281,16 +338,21 @@ mod tests {
             tokenize("\"test\""),
             vec![Token::String("test".to_string())]
         );
+        assert_eq!(tokenize("1 1.0 1.5"), vec![Token::Number("1".to_string()), Token::Decimal(1.0), Token::Decimal(1.5)]);
+        assert_eq!(
+            tokenize("0xffu8 0b101 7i32"),
+            vec![Token::Number("0xffu8".to_string()), Token::Number("0b101".to_string()), Token::Number("7i32".to_string())]
+        );
         assert_eq!(tokenize("["), vec![Token::LeftBracket]);
         assert_eq!(tokenize("]"), vec![Token::RightBracket]);
-        assert_eq!(tokenize("spub179f708c25a23ed367610facc14035adc7ba4b1bfa9252ef55c6c24f1b9b03aba"),
-            vec![Token::PublicKey("spub179f708c25a23ed367610facc14035adc7ba4b1bfa9252ef55c6c24f1b9b03aba".to_string())]);
-        assert_eq!(tokenize("url pk0 : spub179f708c25a23ed367610facc14035adc7ba4b1bfa9252ef55c6c24f1b9b03aba;"),
+        assert_eq!(tokenize("spub108ms3sj6y0knvassltxpgq66m3a6fvdl4yjjaa2udsj0rwds827s0n578r"),
+            vec![public_key()]);
+        assert_eq!(tokenize("url pk0 : spub108ms3sj6y0knvassltxpgq66m3a6fvdl4yjjaa2udsj0rwds827s0n578r;"),
             vec![
                 Token::Url,
                 Token::Identifier("pk0".to_string()),
                 Token::Colon,
-                Token::PublicKey("spub179f708c25a23ed367610facc14035adc7ba4b1bfa9252ef55c6c24f1b9b03aba".to_string()),
+                public_key(),
                 Token::Semicolon
             ]
         );
@@ -322,7 +384,7 @@ mod tests {
             "brigadier brig::Brigadier {
                 majors {
                     app1::Major1,
-                    spub179f708c25a23ed367610facc14035adc7ba4b1bfa9252ef55c6c24f1b9b03aba::\"app3\"::Major3,
+                    spub108ms3sj6y0knvassltxpgq66m3a6fvdl4yjjaa2udsj0rwds827s0n578r::\"app3\"::Major3,
                     //app1::Commented,
                     app2,
                 }
@@ -339,7 +401,7 @@ mod tests {
                 Token::ColonColon,
                 Token::Identifier("Major1".to_string()),
                 Token::Comma,
-                Token::PublicKey("spub179f708c25a23ed367610facc14035adc7ba4b1bfa9252ef55c6c24f1b9b03aba".to_string()),
+                public_key(),
                 Token::ColonColon,
                 Token::String("app3".to_string()),
                 Token::ColonColon,
@@ -355,7 +417,7 @@ mod tests {
             "major maj::Major {
                 corporals {
                     app1::Corporal1,
-                    spub179f708c25a23ed367610facc14035adc7ba4b1bfa9252ef55c6c24f1b9b03aba::\"app3\"::Corporal2,
+                    spub108ms3sj6y0knvassltxpgq66m3a6fvdl4yjjaa2udsj0rwds827s0n578r::\"app3\"::Corporal2,
                     //app1::Commented,
                     app3,
                 }
@@ -372,7 +434,7 @@ mod tests {
                 Token::ColonColon,
                 Token::Identifier("Corporal1".to_string()),
                 Token::Comma,
-                Token::PublicKey("spub179f708c25a23ed367610facc14035adc7ba4b1bfa9252ef55c6c24f1b9b03aba".to_string()),
+                public_key(),
                 Token::ColonColon,
                 Token::String("app3".to_string()),
                 Token::ColonColon,
@@ -418,13 +480,13 @@ mod tests {
                 Token::LeftBrace,
                 Token::Identifier("x".to_string()),
                 Token::Equal,
-                Token::Number(0.0),
+                Token::Number("0".to_string()),
                 Token::Semicolon,
                 Token::RightBrace,
                 Token::If,
                 Token::Identifier("x".to_string()),
                 Token::EqualEqual,
-                Token::Number(0.0),
+                Token::Number("0".to_string()),
                 Token::LeftBrace,
                 Token::True,
                 Token::RightBrace,
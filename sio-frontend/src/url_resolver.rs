@@ -85,7 +85,7 @@ fn main() {
     let mut url_resolver = UrlResolver::new();
     
     // Add URLs with their unique keys
-    url_resolver.resolve_url("public_key", "sio79f708c25a23ed367610facc14035adc7ba4b1bfa9252ef55c6c24f1b9b03abd");
+    url_resolver.resolve_url("public_key", "spub108ms3sj6y0knvassltxpgq66m3a6fvdl4yjjaa2udsj0rwds827s0n578r");
     url_resolver.resolve_url("type", "src");
     url_resolver.resolve_url("name", "app_name");
     url_resolver.resolve_url("app", "public_key::type::name");
@@ -1,100 +1,134 @@
 use sio::{
-    CorporalExecutionMachine, CorporalEnvironment, CorporalAllocator, CorporalLiteral, CorporalState, CorporalValue, corporal_literal_mapper, corporal_literal_to_value
+    Dataflow, ProcessContext, ProcessId, PublicKey, Spawn, SpawnError, KeyTier, Suspend, SignedModule, Environment, ExecutionEnviron, ExecutionMachine, Literal, State, Value
 };
-use werbolg_core::{AbsPath, Ident, Namespace, ir::Module};
-use werbolg_exec::{ ExecutionMachine, ExecutionEnviron, ExecutionParams, WerRefCount};
-use werbolg_compile::{compile};
-use werbolg_lang_common::{Report, ReportKind, Source};
+use werbolg_core::FunId;
+use sio::ffi::Signature;
+use werbolg_exec::{ExecutionError, WerRefCount};
+use werbolg_compile::CompilationUnit;
 use alloc::{ 
-    format, vec, vec::Vec, boxed::Box, string::String};
+    vec, vec::Vec, boxed::Box, rc::Rc, string::String};
+use core::cell::RefCell;
 use core::error::Error;
 use crate::{
     //SioParams, 
-    report_print, run_frontend};
-
-
-fn compile_corporal(
-    //params: SioParams,
-    env: &mut CorporalEnvironment,
-    source: Source,
-    module: Module,
-) -> Result<werbolg_compile::CompilationUnit<CorporalLiteral>, Box<dyn Error>> {
-    //let (source, module) = run_frontend(src, path).unwrap();
-    let module_ns = Namespace::root().append(Ident::from("main"));
-    let modules = vec![(module_ns.clone(), module)];
-    let compilation_params = werbolg_compile::CompilationParams {
-        literal_mapper: corporal_literal_mapper,
-        sequence_constructor: None,
-    };
-    let cu = match compile(&compilation_params, modules, env) {
-        Err(e) => {
-            let report = Report::new(ReportKind::Error, format!("Compilation Error: {:?}", e))
-                .lines_before(1)
-                .lines_after(1)
-                .highlight(e.span().unwrap(), format!("compilation error here"));
-            report_print(&source, report)?;
-            return Err(format!("compilation error {:?}", e).into());
-        }
-        Ok(m) => m,
-    };
-    //if params.dump_instr {
-    //    let mut out = String::new();
-    //    code_dump(&mut out, &cu.code, &cu.funs).expect("writing to string work");
-        //println!("{}", out);
-    //}
-    Ok(cu)
-}
-
-pub fn build_corporal_machine (
-    ee: ExecutionEnviron<CorporalAllocator, CorporalLiteral, CorporalState, CorporalValue>,
-    cu: werbolg_compile::CompilationUnit<CorporalLiteral>,
-) -> Result<CorporalExecutionMachine, Box<dyn Error>> {
-    let module_ns = Namespace::root().append(Ident::from("main"));
-    let entry_point = cu
-        .funs_tbl
-        .get(&AbsPath::new(&module_ns, &Ident::from("main")))
-        .expect("existing function as entry point");
-    let execution_params = ExecutionParams {
-        literal_to_value: corporal_literal_to_value,
-    };
-    let state = CorporalState {};
-    let allocator = CorporalAllocator {};
-    let mut em = ExecutionMachine::new(
-        WerRefCount::new(cu),
-        WerRefCount::new(ee),
-        execution_params, allocator, state);
-    werbolg_exec::initialize(&mut em, entry_point, &[]).unwrap();
-    Ok(em)
-}
+    run_frontend, verify_module, scheduler::Step, error::ProcessError};
+use crate::machine::{bound_value, build_spawned, build_thread, compile_module, entry_point, spawn_request};
 
 pub struct Corporal {
-    threads: Vec<CorporalExecutionMachine>,
+    threads: Vec<ExecutionMachine>,
+    /// Function and arguments each thread started with.
+    starts: Vec<(FunId, Vec<Value>)>,
+    /// What the function of each thread returned, once it did.
+    results: Vec<Option<Value>>,
+    /// Dataflow variables, shared by all the threads.
+    store: Rc<RefCell<Dataflow<Value>>>,
+    cu: WerRefCount<CompilationUnit<Literal>>,
+    ee: WerRefCount<ExecutionEnviron>,
 }
 
 impl Corporal {
+    /// Compiles `code`, which must be signed by `author`.
     pub fn new(
-        src: String,
+        code: SignedModule,
+        author: PublicKey,
         path: String,
         //params: SioParams,
-        mut env: CorporalEnvironment,
+        env: Environment,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::with_host_functions(code, author, path, env, &[])
+    }
+    /// Compiles `code` like [`Corporal::new`], its FFI declarations binding the
+    /// host functions of `hosts`, which must be registered in `env`.
+    pub fn with_host_functions(
+        code: SignedModule,
+        author: PublicKey,
+        path: String,
+        mut env: Environment,
+        hosts: &[Signature],
     ) -> Result<Self, Box<dyn Error>> {
-        let (source, module) = run_frontend(src, path)?;
-        let cu = compile_corporal(/*params, */&mut env, source, module)?;
-        let ee = werbolg_exec::ExecutionEnviron::from_compile_environment(env.finalize());
-        let em = build_corporal_machine(ee, cu)?;
-        Ok(Self { threads: vec![em] })
-    }
-    pub fn march(&mut self) -> Result<Option<CorporalValue>, Box<dyn Error>> {
-        for thread in &mut self.threads {
-            match werbolg_exec::step(thread).unwrap() {
-                None => {},
-                Some(v) => {
-                    println!("thread: {:?}", v);
-                    break;
-                },
+        verify_module(&code, &author)?;
+        let (source, module) = run_frontend(code.source, path, KeyTier::Corporal, hosts)?;
+        let cu = compile_module(/*params, */&mut env, source, module)?;
+        let ee = WerRefCount::new(werbolg_exec::ExecutionEnviron::from_compile_environment(env.finalize()));
+        let cu = WerRefCount::new(cu);
+        let main = entry_point(&cu);
+        let store = Rc::new(RefCell::new(Dataflow::new()));
+        let state = State { store: store.clone(), ..Default::default() };
+        let em = build_thread(ee.clone(), cu.clone(), main, &[], state)?;
+        Ok(Self { threads: vec![em], starts: vec![(main, vec![])], results: vec![None], store, cu, ee })
+    }
+    /// A fresh thread state sharing the corporal's variables.
+    fn state(&self) -> State {
+        State { store: self.store.clone(), ..Default::default() }
+    }
+    pub fn threads(&self) -> usize {
+        self.threads.len()
+    }
+    pub fn context(&self, thread: usize) -> &ProcessContext {
+        &self.threads[thread].userdata.context
+    }
+    pub fn context_mut(&mut self, thread: usize) -> &mut ProcessContext {
+        &mut self.threads[thread].userdata.context
+    }
+    /// Replaces `thread` with a fresh execution machine, started where the
+    /// thread first started.
+    pub fn restart(&mut self, thread: usize) -> Result<(), Box<dyn Error>> {
+        let (fun, args) = &self.starts[thread];
+        self.threads[thread] = build_thread(self.ee.clone(), self.cu.clone(), *fun, args, self.state())?;
+        self.results[thread] = None;
+        Ok(())
+    }
+    /// The value the function of `thread` returned, with its variables
+    /// resolved. `None` while it runs or when it returned a variable nobody
+    /// bound.
+    pub fn result(&self, thread: usize) -> Option<Value> {
+        bound_value(&self.store.borrow(), self.results[thread].as_ref()?)
+    }
+    /// Serves the `spawn` call `thread` is blocked on by starting the
+    /// requested function as a new thread, if it targets `garrison`. Returns
+    /// the new thread, whose pid goes back through [`Corporal::spawned`].
+    pub fn spawn(&mut self, thread: usize, garrison: PublicKey) -> Result<usize, SpawnError> {
+        let (fun, args) = spawn_request(&mut self.threads[thread], garrison)?;
+        let em = build_spawned(self.ee.clone(), self.cu.clone(), fun, &args, self.state())?;
+        self.threads.push(em);
+        self.starts.push((fun, args));
+        self.results.push(None);
+        Ok(self.threads.len() - 1)
+    }
+    /// Threads waiting on a variable bound since the last call.
+    pub fn take_woken(&mut self, thread: usize) -> Vec<ProcessId> {
+        core::mem::take(&mut self.threads[thread].userdata.woken)
+    }
+    /// Hands the result of a `spawn` call back to `thread`.
+    pub fn spawned(&mut self, thread: usize, pid: Result<ProcessId, SpawnError>) {
+        self.threads[thread].userdata.spawn = Some(Spawn::Done(pid));
+    }
+    /// Executes one instruction of `thread`.
+    pub fn step(&mut self, thread: usize) -> Result<Step, ProcessError> {
+        match werbolg_exec::step(&mut self.threads[thread]) {
+            Ok(None) => Ok(Step::Running),
+            Ok(Some(v)) => {
+                self.results[thread] = Some(v);
+                Ok(Step::Done)
             }
+            Err(e) => match self.threads[thread].userdata.take_suspension() {
+                Some(why) => Ok(Step::Blocked(why)),
+                None => Err(self.crash(thread, e)),
+            },
+        }
+    }
+    fn crash(&self, thread: usize, error: ExecutionError) -> ProcessError {
+        let em = &self.threads[thread];
+        let mut debug_state = String::new();
+        em.debug_state(&mut debug_state).expect("writing to string work");
+        ProcessError {
+            tier: KeyTier::Corporal,
+            who: self.context(thread).who(),
+            thread: Some(thread),
+            ip: em.ip,
+            error,
+            debug_state,
         }
-        Ok(None)
     }
 }
 
@@ -104,11 +138,11 @@ mod corporal_tests {
     //use alloc::vec::Vec;
     //use alloc::vec;
     use alloc::string::ToString;
-    use sio::create_corporal_env;
+    use sio::{create_env, tier, Identity, SignedModule, Suspension};
     use super::*;
     static src: &str =
         "
-        url public_key : sio79f708c25a23ed367610facc14035adc7ba4b1bfa9252ef55c6c24f1b9b03abd;
+        url public_key : spub132yw8ht5p8cetl2jmvknewjawt9xwzdlrk2pyxlnwjyqrdq0dawqxrffgs;
         url type : src;
         url name : app_name;
         url app : public_key::type::name;
@@ -124,6 +158,7 @@ mod corporal_tests {
                 } else {
                     assign_the_value(y, false);
                 }
+                y;
             }
             assign_the_value :: (y: int , value: int) {
                 y = value;
@@ -146,11 +181,54 @@ mod corporal_tests {
                 y = value;
             }
         }";
+    /// Steps the threads of `corporal` in turn, serving their spawns the way
+    /// a garrison would, until every thread returned.
+    fn run(corporal: &mut Corporal, garrison: PublicKey) {
+        corporal.context_mut(0).garrison = Some(garrison);
+        let mut done = vec![];
+        for _ in 0..1000 {
+            done.resize(corporal.threads(), false);
+            if !done.contains(&false) {
+                return;
+            }
+            for thread in 0..done.len() {
+                if done[thread] {
+                    continue;
+                }
+                match corporal.step(thread).expect("no thread fails") {
+                    Step::Done => done[thread] = true,
+                    Step::Blocked(Suspension::Spawn) => {
+                        let spawned = corporal.spawn(thread, garrison).map(|new| ProcessId(new as u64));
+                        corporal.spawned(thread, spawned);
+                    }
+                    Step::Running | Step::Blocked(_) => {}
+                }
+            }
+        }
+        panic!("threads still running after 1000 rounds");
+    }
+
     #[test]
     fn basic_dataflow() {
-        let env = create_corporal_env();
-        let mut corporal = Corporal::new(src.to_string(), "/".to_string(), env).expect("Corporal failure reason:");
-        corporal.march();
-        assert_eq!(4, 4);
+        let env = create_env::<tier::Corporal>();
+        // src is rooted at the public key of this identity
+        let identity = Identity::from_seed([1; 32]);
+        let code = SignedModule::sign(&identity, src.to_string());
+        let mut corporal = Corporal::new(code, identity.public_key(), "/".to_string(), env).expect("Corporal failure reason:");
+        run(&mut corporal, identity.public_key());
+        // main waited for the thread to bind x before taking the branch
+        assert_eq!(corporal.threads(), 2);
+        assert_eq!(corporal.result(0), Some(Value::Bool(true)));
+    }
+
+    #[test]
+    fn rejects_tampered_code() {
+        let identity = Identity::from_seed([1; 32]);
+        let mut code = SignedModule::sign(&identity, src.to_string());
+        code.source = code.source.replace("x = 0;", "x = 1;");
+        assert!(Corporal::new(code, identity.public_key(), "/".to_string(), create_env::<tier::Corporal>()).is_err());
+
+        let unsigned = SignedModule::decode(src.as_bytes());
+        assert!(unsigned.is_err());
     }
 }
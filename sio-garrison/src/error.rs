@@ -0,0 +1,80 @@
+use sio::{ExitSignal, KeyTier, ProcessId, PublicKey};
+use werbolg_compile::{CompilationError, InstructionAddress};
+use werbolg_exec::ExecutionError;
+use crate::supervisor::{GroupId, SupervisionError};
+use alloc::string::String;
+use core::{error::Error, fmt};
+
+/// An execution error raised by a process, with enough context to tell
+/// which process failed and where.
+#[derive(Debug)]
+pub struct ProcessError {
+    pub tier: KeyTier,
+    /// Public key of the process identity, if it runs in a garrison.
+    pub who: Option<PublicKey>,
+    /// Thread index, for corporals.
+    pub thread: Option<usize>,
+    pub ip: InstructionAddress,
+    pub error: ExecutionError,
+    /// `debug_state` dump of the execution machine when it failed.
+    pub debug_state: String,
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.tier)?;
+        if let Some(who) = self.who {
+            write!(f, " {}", who)?;
+        }
+        if let Some(thread) = self.thread {
+            write!(f, " thread {}", thread)?;
+        }
+        write!(f, " failed at {}: {:?}", self.ip, self.error)
+    }
+}
+
+impl Error for ProcessError {}
+
+/// A module that parsed but did not compile, for instance because one of
+/// its literals does not fit its type.
+#[derive(Debug)]
+pub struct CompileError(pub CompilationError);
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "compilation error {:?}", self.0)
+    }
+}
+
+impl Error for CompileError {}
+
+#[derive(Debug)]
+pub enum ExitReason {
+    Crashed(ProcessError),
+    /// A supervision group of this process restarted its children too often.
+    Escalated(GroupId),
+    /// A linked process exited, or the link could not be made, while this
+    /// process did not trap exits.
+    Linked { from: ProcessId, signal: ExitSignal },
+    /// The process asked to supervise a group the garrison refused.
+    Supervision(SupervisionError),
+}
+
+impl fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExitReason::Crashed(e) => write!(f, "{}", e),
+            ExitReason::Escalated(group) => write!(f, "supervision group {} exceeded its restart intensity", group.0),
+            ExitReason::Linked { from, signal } => write!(f, "linked process {} exited: {:?}", from, signal),
+            ExitReason::Supervision(e) => write!(f, "supervision refused: {}", e),
+        }
+    }
+}
+
+/// Sent to the supervisor of a process that crashed.
+#[derive(Debug)]
+pub struct ExitReport {
+    pub pid: ProcessId,
+    pub supervisor: Option<ProcessId>,
+    pub reason: ExitReason,
+}
@@ -3,21 +3,32 @@
 
 extern crate alloc;
 use sio::{
-    BrigadierEnvironment
+    Environment, Identity, PublicKey, SignedModule, ModuleError, ProcessId, Suspension,
+    ExitSignal, KeyTier, ProcessContext, Request, Signal, Value,
 };
+use sio::ffi::Signature;
 use werbolg_core::{ir::Module, Span};
 use werbolg_lang_common::{Report, ReportKind, Source};
-use alloc::{format, vec, vec::Vec, boxed::Box, string::String};
+use alloc::{format, vec, vec::Vec, boxed::Box, string::String, collections::VecDeque};
 use core::error::Error;
 
-mod general;
-mod brigadier;
-mod major;
+mod officer;
 mod corporal;
-pub use general::General;
-pub use brigadier::Brigadier;
-pub use major::Major;
+mod error;
+mod links;
+mod machine;
+pub mod scheduler;
+pub mod supervisor;
+#[cfg(test)]
+mod testing;
+pub use officer::{General, Brigadier, Major, Officer};
 pub use corporal::Corporal;
+pub use error::{CompileError, ProcessError, ExitReason, ExitReport};
+pub use scheduler::{ProcessRef, RunState, Status, Step};
+pub use supervisor::{GroupId, Intensity, Strategy, SupervisionError};
+use scheduler::{Scheduler, DEFAULT_REDUCTIONS};
+use supervisor::{Action, Supervisors};
+use links::Links;
 
 pub struct SioParams {
     pub dump_ir: bool,
@@ -33,9 +44,11 @@ pub fn report_print(source: &Source, report: Report) -> Result<(), Box<dyn Error
     Ok(())
 }
 
-fn run_frontend(src: String, path: String) -> Result<(Source, Module), Box<dyn Error>> {
+/// Parses `src` and lowers its module of `tier`, whose FFI declarations may
+/// bind `hosts`.
+fn run_frontend(src: String, path: String, tier: KeyTier, hosts: &[Signature]) -> Result<(Source, Module), Box<dyn Error>> {
     let source = Source::from_string(path, src);
-    let parsing_res = sio_frontend::module(&source.file_unit);
+    let parsing_res = sio_frontend::module(&source.file_unit, tier, hosts);
     let module = match parsing_res {
         Err(es) => {
             for e in es.into_iter() {
@@ -53,38 +66,424 @@ fn run_frontend(src: String, path: String) -> Result<(Source, Module), Box<dyn E
     Ok((source, module))
 }
 
+/// Rejects code unless it carries a valid signature by `author`, the key the
+/// caller expects the code from, and its module path is rooted at that key.
+pub fn verify_module(code: &SignedModule, author: &PublicKey) -> Result<(), Box<dyn Error>> {
+    code.verify_by(author).map_err(|e| format!("module rejected: {}", e))?;
+    let path = sio_frontend::module_author(&code.source).map_err(|e| format!("module rejected: {}", e))?;
+    if path != code.author {
+        let e = ModuleError::AuthorMismatch { signed_by: code.author, path };
+        return Err(format!("module rejected: {}", e).into());
+    }
+    Ok(())
+}
+
 pub struct Garrison {
-    brigadier: Brigadier,
+    identity: Identity,
+    brigadiers: Vec<Brigadier>,
     majors: Vec<Major>,
     corporals: Vec<Corporal>,
+    scheduler: Scheduler,
+    brigadier_pid: ProcessId,
+    supervisors: Supervisors,
+    links: Links,
+    exit_reports: VecDeque<ExitReport>,
+    /// Brigadiers to install, with the process that asked for each.
+    install_requests: VecDeque<(ProcessId, PublicKey)>,
+    /// Instructions executed so far, the clock for restart intensities.
+    ticks: u64,
 }
 
 impl Garrison {
+    /// A garrison whose brigadier runs `code`, which must be signed by
+    /// `author`.
     pub fn new(
-        src: String,
+        code: SignedModule,
+        author: PublicKey,
         path: String,
         //params: SioParams,
-        env: BrigadierEnvironment,
-    ) -> Self {
-        Self {
-            brigadier: Brigadier::new(src, path, env).expect("Reason"),
+        env: Environment,
+        identity: Identity,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut scheduler = Scheduler::new(DEFAULT_REDUCTIONS);
+        let brigadier_pid = scheduler.spawn(ProcessRef::Brigadier(0), None);
+        let mut garrison = Self {
+            identity,
+            brigadiers: vec![Brigadier::new(code, author, path, env)?],
             majors: vec![],
             corporals: vec![],
-        }
+            scheduler,
+            brigadier_pid,
+            supervisors: Supervisors::new(),
+            links: Links::new(),
+            exit_reports: VecDeque::new(),
+            install_requests: VecDeque::new(),
+            ticks: 0,
+        };
+        garrison.attach(brigadier_pid);
+        Ok(garrison)
+    }
+    /// Sets how many instructions a process runs before it is preempted.
+    pub fn set_reductions(&mut self, reductions: usize) {
+        self.scheduler.set_reductions(reductions);
+    }
+    pub fn identity(&self) -> &Identity {
+        &self.identity
     }
-    pub fn add_major(&mut self, major: Major) {
+    pub fn public_key(&self) -> PublicKey {
+        self.identity.public_key()
+    }
+    pub fn brigadier(&self) -> &Brigadier {
+        &self.brigadiers[0]
+    }
+    /// The identity of a process, derived from the garrison identity and the
+    /// pid, see [`Garrison::process_identity`].
+    pub fn identity_of(&self, pid: ProcessId) -> Option<&Identity> {
+        self.context(pid)?.identity.as_ref()
+    }
+    pub fn add_major(&mut self, major: Major) -> ProcessId {
         self.majors.push(major);
+        let pid = self.scheduler.spawn(ProcessRef::Major(self.majors.len() - 1), Some(self.brigadier_pid));
+        self.attach(pid);
+        pid
     }
-    pub fn add_corporal(&mut self, corporal: Corporal) {
+    pub fn add_corporal(&mut self, corporal: Corporal) -> Vec<ProcessId> {
+        let index = self.corporals.len();
+        let threads = corporal.threads();
         self.corporals.push(corporal);
+        (0..threads)
+            .map(|thread| {
+                let pid = self.scheduler.spawn(ProcessRef::Corporal(index, thread), Some(self.brigadier_pid));
+                self.attach(pid);
+                pid
+            })
+            .collect()
+    }
+    pub fn status(&self, pid: ProcessId) -> Option<Status> {
+        self.scheduler.status(pid)
+    }
+    /// Trap flag, link authorisations and mailbox of a process.
+    pub fn context(&self, pid: ProcessId) -> Option<&ProcessContext> {
+        Some(match self.scheduler.target(pid)? {
+            ProcessRef::Brigadier(i) => self.brigadiers[i].context(),
+            ProcessRef::Major(i) => self.majors[i].context(),
+            ProcessRef::Corporal(i, thread) => self.corporals[i].context(thread),
+        })
+    }
+    pub fn context_mut(&mut self, pid: ProcessId) -> Option<&mut ProcessContext> {
+        Some(match self.scheduler.target(pid)? {
+            ProcessRef::Brigadier(i) => self.brigadiers[i].context_mut(),
+            ProcessRef::Major(i) => self.majors[i].context_mut(),
+            ProcessRef::Corporal(i, thread) => self.corporals[i].context_mut(thread),
+        })
+    }
+    /// The value a process that is done returned, see [`Officer::result`].
+    pub fn result(&self, pid: ProcessId) -> Option<Value> {
+        match self.scheduler.target(pid)? {
+            ProcessRef::Brigadier(i) => self.brigadiers[i].result(),
+            ProcessRef::Major(i) => self.majors[i].result(),
+            ProcessRef::Corporal(i, thread) => self.corporals[i].result(thread),
+        }
+    }
+    /// Makes the processes waiting on `why` runnable again.
+    pub fn wake(&mut self, why: Suspension) -> usize {
+        self.scheduler.wake(why)
+    }
+    /// Puts `children` under `supervisor`. Brigadiers supervise majors and
+    /// majors supervise corporals.
+    pub fn supervise(
+        &mut self,
+        supervisor: ProcessId,
+        strategy: Strategy,
+        children: Vec<ProcessId>,
+        intensity: Intensity,
+    ) -> Result<GroupId, SupervisionError> {
+        let tier = self.scheduler.target(supervisor).ok_or(SupervisionError::UnknownProcess(supervisor))?;
+        for child in &children {
+            let child_tier = self.scheduler.target(*child).ok_or(SupervisionError::UnknownProcess(*child))?;
+            match (tier, child_tier) {
+                (ProcessRef::Brigadier(_), ProcessRef::Major(_)) => {}
+                (ProcessRef::Major(_), ProcessRef::Corporal(_, _)) => {}
+                _ => return Err(SupervisionError::WrongTier { supervisor, child: *child }),
+            }
+        }
+        let group = self.supervisors.add(supervisor, strategy, children.clone(), intensity)?;
+        for child in children {
+            self.scheduler.set_supervisor(child, supervisor);
+        }
+        Ok(group)
+    }
+    /// The child currently serving a standby group.
+    pub fn active(&self, group: GroupId) -> ProcessId {
+        self.supervisors.active(group)
+    }
+    /// Crash reports not yet handled by a supervisor, oldest first.
+    pub fn take_exit_reports(&mut self) -> Vec<ExitReport> {
+        self.exit_reports.drain(..).collect()
+    }
+    /// Brigadiers processes asked to install since the last call, with the
+    /// process that asked, oldest first. Installing them is up to the host.
+    pub fn take_install_requests(&mut self) -> Vec<(ProcessId, PublicKey)> {
+        self.install_requests.drain(..).collect()
+    }
+    /// Runs the next ready process for one time slice.
+    pub fn march(&mut self) -> RunState {
+        self.run_slice(self.scheduler.reductions());
+        self.scheduler.state()
+    }
+    /// Runs at most `steps` instructions, across as many processes as needed.
+    pub fn run_for(&mut self, steps: usize) -> RunState {
+        let mut left = steps;
+        while left > 0 {
+            let used = self.run_slice(left.min(self.scheduler.reductions()));
+            if used == 0 {
+                break;
+            }
+            left -= used;
+        }
+        self.scheduler.state()
+    }
+    /// Runs until every process has finished, crashed or is blocked.
+    pub fn run_until_idle(&mut self) -> RunState {
+        while self.run_slice(self.scheduler.reductions()) > 0 {}
+        self.scheduler.state()
+    }
+    /// Runs one process for up to `budget` instructions. A process that fails
+    /// is retired and reported to its supervisor, the others keep running.
+    fn run_slice(&mut self, budget: usize) -> usize {
+        let (pid, target) = match self.scheduler.pop_ready() {
+            None => return 0,
+            Some(next) => next,
+        };
+        let mut used = 0;
+        let mut last = Step::Running;
+        while used < budget && last == Step::Running {
+            used += 1;
+            self.ticks += 1;
+            last = match self.step(target) {
+                Ok(step) => step,
+                Err(e) => {
+                    self.scheduler.crash(pid);
+                    self.exited(pid, ExitReason::Crashed(e));
+                    return used;
+                }
+            };
+            self.wake_bound(target);
+            if last == Step::Blocked(Suspension::Spawn) {
+                self.spawn(pid, target);
+                last = Step::Running;
+            }
+            if !self.handle_requests(pid) || self.scheduler.is_queued(pid) {
+                // killed by an exit signal or restarted with a sibling, the
+                // restart queued it already
+                return used;
+            }
+        }
+        self.scheduler.reschedule(pid, last);
+        if last == Step::Done {
+            self.notify_exit(pid, ExitSignal::Normal);
+        }
+        used
+    }
+    /// Wakes the processes reading a variable `target` bound in its last
+    /// step.
+    fn wake_bound(&mut self, target: ProcessRef) {
+        let woken = match target {
+            ProcessRef::Brigadier(i) => self.brigadiers[i].take_woken(),
+            ProcessRef::Major(i) => self.majors[i].take_woken(),
+            ProcessRef::Corporal(i, thread) => self.corporals[i].take_woken(thread),
+        };
+        for pid in woken {
+            if let Some(Status::Blocked(why @ Suspension::Unbound(_))) = self.scheduler.status(pid) {
+                self.scheduler.wake_process(pid, why);
+            }
+        }
+    }
+    /// Serves the `spawn` call `pid` is blocked on. The new process has the
+    /// tier of `pid`, shares its variables and joins its supervisor:
+    /// corporals start it as one of their threads, officers as a new officer
+    /// of their module.
+    fn spawn(&mut self, pid: ProcessId, target: ProcessRef) {
+        let garrison = self.public_key();
+        let spawned = match target {
+            ProcessRef::Brigadier(i) => self.brigadiers[i].spawn(garrison).map(|brigadier| {
+                self.brigadiers.push(brigadier);
+                ProcessRef::Brigadier(self.brigadiers.len() - 1)
+            }),
+            ProcessRef::Major(i) => self.majors[i].spawn(garrison).map(|major| {
+                self.majors.push(major);
+                ProcessRef::Major(self.majors.len() - 1)
+            }),
+            ProcessRef::Corporal(i, thread) => {
+                self.corporals[i].spawn(thread, garrison).map(|new_thread| ProcessRef::Corporal(i, new_thread))
+            }
+        };
+        let spawned = spawned.map(|child| {
+            let supervisor = self.scheduler.supervisor(pid);
+            let child = self.scheduler.spawn(child, supervisor);
+            self.attach(child);
+            child
+        });
+        match target {
+            ProcessRef::Brigadier(i) => self.brigadiers[i].spawned(spawned),
+            ProcessRef::Major(i) => self.majors[i].spawned(spawned),
+            ProcessRef::Corporal(i, thread) => self.corporals[i].spawned(thread, spawned),
+        }
+    }
+    /// Applies the link, monitor and supervision requests `pid` made during
+    /// its last step, and queues its brigadier installs for the host. Returns false if a failed request killed it.
+    fn handle_requests(&mut self, pid: ProcessId) -> bool {
+        let context = self.context_mut(pid).expect("a known process");
+        let requests = core::mem::take(&mut context.requests);
+        let who = context.who();
+        for request in requests {
+            match request {
+                Request::Link(other) => {
+                    let refused = if !self.scheduler.is_alive(other) {
+                        Some(ExitSignal::NoProc)
+                    } else if !self.context(other).is_some_and(|target| target.allows_link(who)) {
+                        Some(ExitSignal::Unauthorised)
+                    } else {
+                        self.links.link(pid, other);
+                        None
+                    };
+                    if let Some(reason) = refused {
+                        if self.signal_exit(pid, other, reason) {
+                            return false;
+                        }
+                    }
+                }
+                Request::Unlink(other) => self.links.unlink(pid, other),
+                Request::Monitor(target, monitor) => {
+                    if self.scheduler.is_alive(target) {
+                        self.links.monitor(pid, monitor, target);
+                    } else {
+                        self.deliver(pid, Signal::Down { monitor, from: target, reason: ExitSignal::NoProc });
+                    }
+                }
+                Request::Demonitor(target) => self.links.demonitor(pid, target),
+                Request::Supervise(strategy, children) => {
+                    if let Err(e) = self.supervise(pid, strategy, children, Intensity::default()) {
+                        self.scheduler.crash(pid);
+                        self.exited(pid, ExitReason::Supervision(e));
+                        return false;
+                    }
+                }
+                Request::InstallBrigadier(garrison) => self.install_requests.push_back((pid, garrison)),
+            }
+        }
+        true
+    }
+    /// Tells the processes linked to or monitoring `pid` that it exited.
+    fn notify_exit(&mut self, pid: ProcessId, reason: ExitSignal) {
+        let (linked, watchers) = self.links.exit(pid);
+        for (watcher, monitor) in watchers {
+            self.deliver(watcher, Signal::Down { monitor, from: pid, reason });
+        }
+        for other in linked {
+            self.signal_exit(other, pid, reason);
+        }
+    }
+    /// Sends the exit signal of `from` to `pid`. A process trapping exits
+    /// gets it as a message, otherwise any reason but a normal exit kills it.
+    /// Returns whether `pid` was killed.
+    fn signal_exit(&mut self, pid: ProcessId, from: ProcessId, reason: ExitSignal) -> bool {
+        if !self.scheduler.is_alive(pid) {
+            return false;
+        }
+        if self.context(pid).expect("a known process").trap_exit {
+            self.deliver(pid, Signal::Exit { from, reason });
+            false
+        } else if reason != ExitSignal::Normal {
+            self.scheduler.crash(pid);
+            self.exited(pid, ExitReason::Linked { from, signal: reason });
+            true
+        } else {
+            false
+        }
+    }
+    fn deliver(&mut self, pid: ProcessId, signal: Signal) {
+        if !self.scheduler.is_alive(pid) {
+            return;
+        }
+        self.context_mut(pid).expect("a known process").mailbox.push_back(signal);
+        self.scheduler.wake_process(pid, Suspension::Receive);
+    }
+    /// Hands a crash to the supervision group of `pid`, or queues the report
+    /// for the host when no group supervises it.
+    fn exited(&mut self, pid: ProcessId, reason: ExitReason) {
+        self.notify_exit(pid, ExitSignal::Crashed);
+        let scheduler = &self.scheduler;
+        let decision = match self.supervisors.on_exit(pid, self.ticks, |p| scheduler.is_alive(p)) {
+            None => {
+                let supervisor = self.scheduler.supervisor(pid);
+                self.exit_reports.push_back(ExitReport { pid, supervisor, reason });
+                return;
+            }
+            Some(decision) => decision,
+        };
+        match decision.action {
+            Action::Tolerate => {}
+            Action::Restart(pids) => {
+                // live siblings restarted with the failed child exit first,
+                // so their links and monitors hear about it
+                for child in &pids {
+                    if self.scheduler.is_alive(*child) {
+                        self.scheduler.stop(*child);
+                        self.notify_exit(*child, ExitSignal::Stopped);
+                    }
+                }
+                for child in pids {
+                    // the code already compiled once, rebuilding the machine cannot fail
+                    self.restart(child).expect("restarting from compiled code");
+                }
+            }
+            Action::Escalate(children) => {
+                for child in children {
+                    if self.scheduler.is_alive(child) {
+                        self.scheduler.stop(child);
+                        self.notify_exit(child, ExitSignal::Stopped);
+                    }
+                }
+                self.scheduler.crash(decision.supervisor);
+                self.exited(decision.supervisor, ExitReason::Escalated(decision.group));
+            }
+        }
     }
-    pub fn march(&mut self) {
-        self.brigadier.march();
-        for major in &mut self.majors {
-            major.march();
+    fn restart(&mut self, pid: ProcessId) -> Result<(), Box<dyn Error>> {
+        match self.scheduler.target(pid).expect("restarting a known process") {
+            ProcessRef::Brigadier(i) => self.brigadiers[i].restart()?,
+            ProcessRef::Major(i) => self.majors[i].restart()?,
+            ProcessRef::Corporal(i, thread) => self.corporals[i].restart(thread)?,
         }
-        for corporal in &mut self.corporals {
-            corporal.march();
+        self.attach(pid);
+        self.scheduler.restart(pid);
+        Ok(())
+    }
+    /// The identity of process `pid` of this garrison: every process, and
+    /// every corporal thread, gets its own key. A restarted process keeps its
+    /// pid and so its key.
+    pub fn process_identity(&self, pid: ProcessId) -> Option<Identity> {
+        let tier = match self.scheduler.target(pid)? {
+            ProcessRef::Brigadier(_) => "brigadier",
+            ProcessRef::Major(_) => "major",
+            ProcessRef::Corporal(_, _) => "corporal",
+        };
+        Some(self.identity.derive(&format!("{}/{}", tier, pid.0)))
+    }
+    /// Gives the machine of `pid` a fresh context naming it.
+    fn attach(&mut self, pid: ProcessId) {
+        let identity = self.process_identity(pid).expect("attaching a known process");
+        let garrison = self.public_key();
+        let context = self.context_mut(pid).expect("a known process");
+        *context = ProcessContext::new(pid, identity);
+        context.garrison = Some(garrison);
+    }
+    fn step(&mut self, target: ProcessRef) -> Result<Step, ProcessError> {
+        match target {
+            ProcessRef::Brigadier(i) => self.brigadiers[i].step(),
+            ProcessRef::Major(i) => self.majors[i].step(),
+            ProcessRef::Corporal(i, thread) => self.corporals[i].step(thread),
         }
     }
 }
@@ -92,24 +491,427 @@ impl Garrison {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::{corporal_running, garrison, signed};
+
+    fn list(items: &[Value]) -> Value {
+        Value::List(items.iter().cloned().collect())
+    }
 
     #[test]
     fn garrison_works() {
         //let params = SioParams::new();
 
-        let brigadier_env = sio::create_brigadier_env();
-        let mut garrison = Garrison::new("/".to_string(), "brigadier".to_string(), brigadier_env);
+        let brigadier_env = sio::create_env::<sio::tier::Brigadier>();
+        let identity = Identity::from_seed([1; 32]);
+        let mut garrison = Garrison::new(signed(&identity, "brigadier"), identity.public_key(), "brigadier".to_string(), brigadier_env, identity.clone()).unwrap();
 
-        let major_env = sio::create_major_env();
-        let major = Major::new("/".to_string(),"/".to_string(), major_env).expect("reason");
+        let major_env = sio::create_env::<sio::tier::Major>();
+        let major = Major::new(signed(&identity, "major"), identity.public_key(),"/".to_string(), major_env).expect("reason");
         garrison.add_major(major);
 
-        let corporal_env = sio::create_corporal_env();
-        let corporal = Corporal::new("/".to_string(),"/".to_string(), corporal_env).expect("reason");
+        let corporal_env = sio::create_env::<sio::tier::Corporal>();
+        let corporal = Corporal::new(signed(&identity, "corporal"), identity.public_key(),"/".to_string(), corporal_env).expect("reason");
         garrison.add_corporal(corporal);
 
-        garrison.march();
-        assert_eq!(4, 4);
+        assert_eq!(garrison.run_for(1), RunState::Runnable);
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        assert!(garrison.take_exit_reports().is_empty());
+        for pid in 0..3 {
+            assert_eq!(garrison.status(ProcessId(pid)), Some(Status::Done));
+            assert_eq!(garrison.result(ProcessId(pid)), Some(Value::I64(1)));
+        }
+        // every process has a key of its own
+        let keys: Vec<PublicKey> = (0..3).map(|pid| garrison.identity_of(ProcessId(pid)).unwrap().public_key()).collect();
+        assert!(keys[0] != keys[1] && keys[1] != keys[2] && keys[0] != keys[2]);
+        assert_eq!(keys[2], identity.derive("corporal/2").public_key());
+    }
+
+    #[test]
+    fn garrison_identity() {
+        let identity = Identity::from_seed([2; 32]);
+        let garrison = garrison(&identity);
+        assert_eq!(garrison.public_key(), identity.public_key());
+        let brigadier = garrison.identity_of(ProcessId(0)).unwrap();
+        assert_eq!(brigadier.public_key(), identity.derive("brigadier/0").public_key());
+
+        let signature = brigadier.sign(b"report");
+        let brigadier_key = brigadier.public_key();
+        assert!(sio::identity::verify(&brigadier_key, b"report", &signature).is_ok());
+        assert!(sio::identity::verify(&garrison.public_key(), b"report", &signature).is_err());
+    }
+
+    #[test]
+    fn install_requests_reach_the_host() {
+        let identity = Identity::from_seed([2; 32]);
+        let mut garrison = garrison(&identity);
+        let other = Identity::from_seed([3; 32]).public_key();
+        // no tier in a garrison may call install_brigadier, ask as the
+        // general would
+        garrison.context_mut(ProcessId(0)).unwrap().requests.push(Request::InstallBrigadier(other));
+        assert!(garrison.take_install_requests().is_empty());
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        assert_eq!(garrison.take_install_requests(), vec![(ProcessId(0), other)]);
+        assert!(garrison.take_install_requests().is_empty());
+    }
+
+    #[test]
+    fn variables_are_bound_once() {
+        let identity = Identity::from_seed([1; 32]);
+        let (mut garrison, main) = corporal_running(&identity, "let x; x = 0; x = 0; x = 1;");
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        let reports = garrison.take_exit_reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].pid, main);
+        assert!(reports[0].reason.to_string().contains("variable 0 is already bound to I64(0), cannot bind it to I64(1)"));
+    }
+
+    #[test]
+    fn arithmetic_is_checked() {
+        let identity = Identity::from_seed([1; 32]);
+        let (mut garrison, main) = corporal_running(&identity, "[1 - 2, 0 - 1, -1];");
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        assert!(garrison.take_exit_reports().is_empty());
+        assert_eq!(garrison.result(main), Some(list(&[Value::I64(-1), Value::I64(-1), Value::I64(-1)])));
+
+        let (mut garrison, main) = corporal_running(&identity, "let x; x = 0 - 9223372036854775807; x - 2;");
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        let reports = garrison.take_exit_reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].pid, main);
+        assert!(reports[0].reason.to_string().contains("integer overflow in -"));
+    }
+
+    #[test]
+    fn operators_have_nifs() {
+        let identity = Identity::from_seed([1; 32]);
+        let (mut garrison, main) = corporal_running(
+            &identity,
+            "[7 / 2, 7 - 2 * 2 != 2 + 2, -1 < 0, 1 > 0, 2 >= 3, 2 <= 2,
+            7.0 / 2.0, rem(7, 2), \"a\" < \"b\", !false,
+            true and 1 == 2, false or true,
+            1 == 1 or 1 / 0 == 0, !(1 == 0 and 1 / 0 == 0)];",
+        );
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        assert!(garrison.take_exit_reports().is_empty());
+        let (t, f) = (Value::Bool(true), Value::Bool(false));
+        assert_eq!(
+            garrison.result(main),
+            Some(list(&[
+                Value::I64(3), t.clone(), t.clone(), t.clone(), f.clone(), t.clone(),
+                Value::F64(3.5), Value::I64(1), t.clone(), t.clone(),
+                f, t.clone(),
+                t.clone(), t,
+            ]))
+        );
+
+        let (mut garrison, main) = corporal_running(&identity, "1 / 0;");
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        let reports = garrison.take_exit_reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].pid, main);
+        assert!(reports[0].reason.to_string().contains("division by zero in /"));
+    }
+
+    #[test]
+    fn floats_convert_explicitly() {
+        let identity = Identity::from_seed([1; 32]);
+        let (mut garrison, main) = corporal_running(&identity, "[to_int(sqrt(16.0) - 0.5), to_int(to_float(3)), sqrt(16.0)];");
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        assert!(garrison.take_exit_reports().is_empty());
+        assert_eq!(garrison.result(main), Some(list(&[Value::I64(3), Value::I64(3), Value::F64(4.0)])));
+
+        let (mut garrison, _) = corporal_running(&identity, "1.0 + 1;");
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        assert_eq!(garrison.take_exit_reports().len(), 1);
+    }
+
+    #[test]
+    fn strings_are_values() {
+        let identity = Identity::from_seed([1; 32]);
+        let (mut garrison, main) = corporal_running(
+            &identity,
+            "[concat(\"corporal\", to_string(1)), format(\"corporal{}\", 1), len(slice(\"corporal1\", 0, 8)), parse_int(\"8\")];",
+        );
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        assert!(garrison.take_exit_reports().is_empty());
+        let name = Value::Str("corporal1".into());
+        assert_eq!(garrison.result(main), Some(list(&[name.clone(), name, Value::I64(8), Value::I64(8)])));
+    }
+
+    #[test]
+    fn lists_are_values() {
+        let identity = Identity::from_seed([1; 32]);
+        let mut garrison = garrison(&identity);
+        let src = format!(
+            "url author : {};
+            corporal author::App {{
+                pub main :: () {{
+                    let xs = [1, 2, 3];
+                    let ys; ys = xs[0] = 0;
+                    [
+                        xs[2],
+                        ys,
+                        len(append(xs, [0])),
+                        map(double, xs),
+                        fold(add, 0, filter(small, xs)),
+                        split(\"a,b\", \",\"),
+                        cons(\"a\", tail([0, \"b\"])),
+                        xs
+                    ];
+                }}
+                double :: (x: i64) -> i64 {{
+                    x * 2;
+                }}
+                add :: (a: i64, b: i64) -> i64 {{
+                    a + b;
+                }}
+                small :: (x: i64) -> bool {{
+                    x <= 2;
+                }}
+            }}",
+            identity.public_key(),
+        );
+        let corporal = Corporal::new(SignedModule::sign(&identity, src), identity.public_key(), "/".to_string(), sio::create_env::<sio::tier::Corporal>()).unwrap();
+        let main = garrison.add_corporal(corporal)[0];
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        assert!(garrison.take_exit_reports().is_empty());
+
+        let ints = |ns: &[i64]| list(&ns.iter().copied().map(Value::I64).collect::<Vec<_>>());
+        let ab = list(&[Value::Str("a".into()), Value::Str("b".into())]);
+        assert_eq!(
+            garrison.result(main),
+            Some(list(&[
+                Value::I64(3),
+                ints(&[0, 2, 3]),
+                Value::I64(4),
+                ints(&[2, 4, 6]),
+                Value::I64(3),
+                ab.clone(),
+                ab,
+                // setting an element copied the list
+                ints(&[1, 2, 3]),
+            ]))
+        );
+    }
+
+    #[test]
+    fn pids_are_values() {
+        let identity = Identity::from_seed([1; 32]);
+        let (mut garrison, main) = corporal_running(
+            &identity,
+            "let me = self();
+            [me, me == head([self(), 0]), hash(me) == hash(self()), slice(to_string(me), 0, 1), garrison(), (1 < 2) != false];",
+        );
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        assert!(garrison.take_exit_reports().is_empty());
+        let me = Value::Pid(sio::Pid { garrison: identity.public_key(), local: main });
+        assert_eq!(
+            garrison.result(main),
+            Some(list(&[
+                me,
+                Value::Bool(true),
+                Value::Bool(true),
+                Value::Str("<".into()),
+                Value::Key(identity.public_key()),
+                Value::Bool(true),
+            ]))
+        );
+    }
+
+    #[test]
+    fn verify_module_rejects_foreign_code() {
+        let author = Identity::from_seed([1; 32]);
+        let code = signed(&author, "corporal");
+        assert!(verify_module(&code, &author.public_key()).is_ok());
+
+        let mut tampered = code.clone();
+        tampered.source = tampered.source.replace("1;", "2;");
+        assert!(verify_module(&tampered, &author.public_key()).is_err());
+
+        // validly signed, but by a key other than the one the path names
+        let impostor = Identity::from_seed([2; 32]);
+        let resigned = SignedModule::sign(&impostor, code.source.clone());
+        assert!(resigned.verify().is_ok());
+        assert!(verify_module(&resigned, &impostor.public_key()).is_err());
+
+        // validly signed by the key its path names, but not the key expected
+        let foreign = signed(&impostor, "corporal");
+        assert!(verify_module(&foreign, &impostor.public_key()).is_ok());
+        let e = verify_module(&foreign, &author.public_key()).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            format!("module rejected: module signed by {} but expected from {}", impostor.public_key(), author.public_key())
+        );
+        assert!(Corporal::new(foreign, author.public_key(), "/".to_string(), sio::create_env::<sio::tier::Corporal>()).is_err());
+    }
+
+    struct Checksum;
+
+    impl sio::ffi::HostFunction for Checksum {
+        type Args = (Vec<String>,);
+        type Ret = Result<i64, String>;
+        fn call((words,): (Vec<String>,)) -> Result<i64, String> {
+            if words.is_empty() {
+                return Err("no words to sum".to_string());
+            }
+            Ok(words.iter().map(|word| word.len() as i64).sum())
+        }
+    }
+
+    #[test]
+    fn host_functions_are_called() {
+        let identity = Identity::from_seed([1; 32]);
+        // the exit reports and the result of `body`
+        let run = |body: &str| {
+            let mut garrison = garrison(&identity);
+            let mut env = sio::create_env::<sio::tier::Corporal>();
+            let checksum = sio::ffi::add_host_function::<Checksum>(&mut env, "host::words::checksum").unwrap();
+            assert_eq!(checksum.params, &["list"]);
+            assert!(sio::ffi::add_host_function::<Checksum>(&mut env, "host::words::checksum").is_none());
+            let src = format!(
+                "url author : {};
+                corporal author::App {{
+                    checksum :: host::/words/checksum :: (words: list) -> int;
+                    pub main :: () {{
+                        {}
+                    }}
+                }}",
+                identity.public_key(),
+                body
+            );
+            let corporal = Corporal::with_host_functions(SignedModule::sign(&identity, src), identity.public_key(), "/".to_string(), env, &[checksum]).unwrap();
+            let main = garrison.add_corporal(corporal)[0];
+            assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+            (garrison.take_exit_reports(), garrison.result(main))
+        };
+        let (reports, result) = run("checksum([\"ab\", \"cde\"]);");
+        assert!(reports.is_empty());
+        assert_eq!(result, Some(Value::I64(5)));
+
+        let (reports, result) = run("checksum([]);");
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].reason.to_string().contains("no words to sum"));
+        assert_eq!(result, None);
+        assert_eq!(run("checksum([1]);").0.len(), 1);
+    }
+
+    struct Double;
+
+    impl sio::ffi::HostFunction for Double {
+        type Args = (i64,);
+        type Ret = i64;
+        fn call((n,): (i64,)) -> i64 {
+            n * 2
+        }
+    }
+
+    #[test]
+    fn documented_host_function_runs() {
+        // the example of the sio::ffi documentation
+        let identity = Identity::from_seed([1; 32]);
+        let mut garrison = garrison(&identity);
+        let mut env = sio::create_env::<sio::tier::Corporal>();
+        let double = sio::ffi::add_host_function::<Double>(&mut env, "host::math::double").unwrap();
+        let src = format!(
+            "url author : {};
+            corporal author::App {{
+                double :: host::/math/double :: (n: int) -> int;
+                pub main :: () {{
+                    double(21);
+                }}
+            }}",
+            identity.public_key()
+        );
+        let corporal = Corporal::with_host_functions(SignedModule::sign(&identity, src), identity.public_key(), "/".to_string(), env, &[double]).unwrap();
+        let main = garrison.add_corporal(corporal)[0];
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        assert!(garrison.take_exit_reports().is_empty());
+        assert_eq!(garrison.result(main), Some(Value::I64(42)));
+    }
+
+    #[test]
+    fn ffi_declarations_are_checked_against_the_host() {
+        let identity = Identity::from_seed([1; 32]);
+        let compiles = |tier: &str, declaration: &str| {
+            let src = format!(
+                "url author : {};
+                {} author::App {{
+                    {}
+                    pub main :: () {{
+                        1;
+                    }}
+                }}",
+                identity.public_key(),
+                tier,
+                declaration
+            );
+            let code = SignedModule::sign(&identity, src);
+            let path = "/".to_string();
+            if tier == "major" {
+                let mut env = sio::create_env::<sio::tier::Major>();
+                let checksum = sio::ffi::add_host_function::<Checksum>(&mut env, "host::words::checksum").unwrap();
+                Major::with_host_functions(code, identity.public_key(), path, env, &[checksum]).is_ok()
+            } else {
+                let mut env = sio::create_env::<sio::tier::Corporal>();
+                let checksum = sio::ffi::add_host_function::<Checksum>(&mut env, "host::words::checksum").unwrap();
+                Corporal::with_host_functions(code, identity.public_key(), path, env, &[checksum]).is_ok()
+            }
+        };
+        assert!(compiles("corporal", "checksum :: host::/words/checksum :: (words: list) -> int;"));
+        assert!(compiles("major", "checksum :: host::/words/checksum :: (words: list) -> i64;"));
+        // another arity, another parameter type, another result type and a
+        // url nobody registered
+        assert!(!compiles("corporal", "checksum :: host::/words/checksum :: (words: list, n: int) -> int;"));
+        assert!(!compiles("corporal", "checksum :: host::/words/checksum :: (words: str) -> int;"));
+        assert!(!compiles("corporal", "checksum :: host::/words/checksum :: (words: list) -> str;"));
+        assert!(!compiles("major", "checksum :: host::/words/count :: (words: list) -> int;"));
     }
-}
 
+    #[test]
+    fn literals_are_checked_for_every_tier() {
+        let identity = Identity::from_seed([1; 32]);
+        let module = |tier: &str, body: &str| {
+            let src = format!(
+                "url author : {};
+                {} author::App {{
+                    pub main :: () {{
+                        let x;
+                        x = {};
+                    }}
+                }}",
+                identity.public_key(),
+                tier,
+                body
+            );
+            SignedModule::sign(&identity, src)
+        };
+        let compile = |tier: &str, body: &str| -> Result<(), Box<dyn Error>> {
+            let code = module(tier, body);
+            let path = "/".to_string();
+            match tier {
+                "general" => General::new(code, identity.public_key(), path, sio::create_env::<sio::tier::General>()).map(|_| ()),
+                "brigadier" => Brigadier::new(code, identity.public_key(), path, sio::create_env::<sio::tier::Brigadier>()).map(|_| ()),
+                "major" => Major::new(code, identity.public_key(), path, sio::create_env::<sio::tier::Major>()).map(|_| ()),
+                _ => Corporal::new(code, identity.public_key(), path, sio::create_env::<sio::tier::Corporal>()).map(|_| ()),
+            }
+        };
+        for tier in ["general", "brigadier", "major", "corporal"] {
+            for fits in ["255u8", "0xffu8", "0b1010", "0o17i16", "9223372036854775807", "18446744073709551615u64"] {
+                assert!(compile(tier, fits).is_ok(), "{} rejects {}", tier, fits);
+            }
+            // out of range, out of range for the suffix, digits outside the
+            // radix, a radix without digits and an unknown suffix
+            for fails in ["9223372036854775808", "256u8", "0b102", "0x", "1u7"] {
+                let e = compile(tier, fails).expect_err(&format!("{} accepts {}", tier, fails));
+                let Some(CompileError(werbolg_compile::CompilationError::LiteralNotSupported(span, werbolg_core::Literal::Number(n)))) = e.downcast_ref() else {
+                    panic!("{} rejects {} with {}", tier, fails, e);
+                };
+                // the span of the literal in the source
+                let start = module(tier, fails).source.find(&format!("x = {};", fails)).unwrap() + "x = ".len();
+                assert_eq!(*span, start..start + fails.len());
+                let n: &str = n.as_ref();
+                assert_eq!(n, fails);
+            }
+        }
+    }
+}
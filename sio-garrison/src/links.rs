@@ -0,0 +1,251 @@
+//! Links and monitors between processes.
+//!
+//! A link is bidirectional: when either end exits the other receives an exit
+//! signal. A monitor is one way: the watcher receives a `down` message when
+//! the target exits, and the target never learns about it.
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+use sio::{MonitorRef, ProcessId};
+
+#[derive(Default)]
+pub struct Links {
+    /// Linked pairs, smallest pid first.
+    links: BTreeSet<(ProcessId, ProcessId)>,
+    /// Target of each monitor, by watcher and reference.
+    monitors: BTreeMap<(ProcessId, MonitorRef), ProcessId>,
+}
+
+fn pair(a: ProcessId, b: ProcessId) -> (ProcessId, ProcessId) {
+    if a <= b { (a, b) } else { (b, a) }
+}
+
+impl Links {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Links `a` and `b`. Linking a process to itself does nothing.
+    pub fn link(&mut self, a: ProcessId, b: ProcessId) {
+        if a != b {
+            self.links.insert(pair(a, b));
+        }
+    }
+
+    pub fn unlink(&mut self, a: ProcessId, b: ProcessId) {
+        self.links.remove(&pair(a, b));
+    }
+
+    pub fn linked(&self, pid: ProcessId) -> Vec<ProcessId> {
+        self.links
+            .iter()
+            .filter_map(|(a, b)| if *a == pid { Some(*b) } else if *b == pid { Some(*a) } else { None })
+            .collect()
+    }
+
+    pub fn monitor(&mut self, watcher: ProcessId, monitor: MonitorRef, target: ProcessId) {
+        self.monitors.insert((watcher, monitor), target);
+    }
+
+    /// Drops every monitor `watcher` holds on `target`.
+    pub fn demonitor(&mut self, watcher: ProcessId, target: ProcessId) {
+        self.monitors.retain(|(w, _), t| !(*w == watcher && *t == target));
+    }
+
+    /// Forgets `pid` after it exited. Returns the processes linked to it and
+    /// the monitors watching it. The monitors `pid` held are dropped.
+    pub fn exit(&mut self, pid: ProcessId) -> (Vec<ProcessId>, Vec<(ProcessId, MonitorRef)>) {
+        let linked = self.linked(pid);
+        self.links.retain(|(a, b)| *a != pid && *b != pid);
+        let watchers = self
+            .monitors
+            .iter()
+            .filter(|(_, target)| **target == pid)
+            .map(|(key, _)| *key)
+            .collect();
+        self.monitors.retain(|(watcher, _), target| *watcher != pid && *target != pid);
+        (linked, watchers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn links_are_bidirectional() {
+        let mut l = Links::new();
+        l.link(ProcessId(2), ProcessId(1));
+        l.link(ProcessId(1), ProcessId(3));
+        l.link(ProcessId(1), ProcessId(1));
+        assert_eq!(l.linked(ProcessId(1)), vec![ProcessId(2), ProcessId(3)]);
+        assert_eq!(l.linked(ProcessId(2)), vec![ProcessId(1)]);
+        l.unlink(ProcessId(3), ProcessId(1));
+        assert_eq!(l.linked(ProcessId(3)), vec![]);
+
+        let (linked, watchers) = l.exit(ProcessId(2));
+        assert_eq!(linked, vec![ProcessId(1)]);
+        assert!(watchers.is_empty());
+        assert_eq!(l.linked(ProcessId(1)), vec![]);
+    }
+
+    #[test]
+    fn monitors_are_one_way() {
+        let mut l = Links::new();
+        l.monitor(ProcessId(1), MonitorRef(0), ProcessId(2));
+        l.monitor(ProcessId(1), MonitorRef(1), ProcessId(3));
+        l.monitor(ProcessId(4), MonitorRef(0), ProcessId(2));
+        l.demonitor(ProcessId(4), ProcessId(2));
+
+        // the watcher exiting tells the target nothing and drops its monitors
+        let (linked, watchers) = l.exit(ProcessId(1));
+        assert!(linked.is_empty() && watchers.is_empty());
+        assert_eq!(l.exit(ProcessId(3)), (vec![], vec![]));
+
+        l.monitor(ProcessId(5), MonitorRef(7), ProcessId(2));
+        assert_eq!(l.exit(ProcessId(2)), (vec![], vec![(ProcessId(5), MonitorRef(7))]));
+    }
+}
+
+#[cfg(test)]
+mod garrison_tests {
+    use alloc::{format, string::String, vec, vec::Vec};
+    use sio::{ExitSignal, Identity, ProcessId, Request, Signal, Value};
+    use crate::testing::{add_corporal, add_major, garrison, three_tiers};
+    use crate::{RunState, Status};
+
+    #[test]
+    fn exits_reach_links_and_monitors() {
+        let identity = Identity::from_seed([1; 32]);
+        let (mut garrison, brigadier, major, corporal) = three_tiers(&identity);
+        // one instruction per slice, so every process links before any exits
+        garrison.set_reductions(1);
+        let context = garrison.context_mut(corporal).unwrap();
+        context.trap_exit = true;
+        context.requests.push(Request::Link(major));
+        let monitor = context.monitor(brigadier);
+
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        assert!(garrison.take_exit_reports().is_empty());
+        let mailbox: Vec<Signal> = garrison.context(corporal).unwrap().mailbox.iter().copied().collect();
+        assert_eq!(
+            mailbox,
+            vec![
+                Signal::Down { monitor, from: brigadier, reason: ExitSignal::Normal },
+                Signal::Exit { from: major, reason: ExitSignal::Normal },
+            ]
+        );
+    }
+
+    #[test]
+    fn refused_links_kill_the_requester() {
+        let identity = Identity::from_seed([1; 32]);
+        let (mut garrison, brigadier, major, corporal) = three_tiers(&identity);
+        garrison.set_reductions(1);
+        garrison.context_mut(brigadier).unwrap().link_auth = Some(vec![]);
+        garrison.context_mut(corporal).unwrap().requests.push(Request::Link(brigadier));
+        garrison.context_mut(major).unwrap().requests.push(Request::Link(ProcessId(42)));
+
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        let reasons: Vec<(ProcessId, String)> = garrison
+            .take_exit_reports()
+            .into_iter()
+            .map(|report| (report.pid, report.reason.to_string()))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (major, format!("linked process {} exited: NoProc", ProcessId(42))),
+                (corporal, format!("linked process {} exited: Unauthorised", brigadier)),
+            ]
+        );
+        assert_eq!(garrison.status(brigadier), Some(Status::Done));
+    }
+
+    #[test]
+    fn trapping_officers_receive_structured_signals() {
+        let identity = Identity::from_seed([1; 32]);
+        let mut garrison = garrison(&identity);
+        garrison.set_reductions(1);
+        let major = add_major(&mut garrison, &identity, "[receive_signal(), receive_signal(), receive_signal(), receive_signal()];");
+        let finishing = add_corporal(&mut garrison, &identity, "1;");
+        let crashing = add_corporal(&mut garrison, &identity, "1 / 0;");
+        garrison.context_mut(finishing).unwrap().link_auth = Some(vec![]);
+        let context = garrison.context_mut(major).unwrap();
+        context.trap_exit = true;
+        context.requests.push(Request::Link(crashing));
+        let monitor = context.monitor(finishing);
+        context.requests.push(Request::Link(ProcessId(42)));
+        context.requests.push(Request::Link(finishing));
+
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        let reports = garrison.take_exit_reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].pid, crashing);
+
+        let pid = |local| Value::Pid(sio::Pid { garrison: identity.public_key(), local });
+        let signal = |kind: &str, from, reason: &str, monitor: Value| {
+            Value::List([Value::Str(kind.into()), pid(from), Value::Str(reason.into()), monitor].into_iter().collect())
+        };
+        let expected = [
+            signal("exit", ProcessId(42), "noproc", Value::Unit),
+            signal("exit", finishing, "unauthorised", Value::Unit),
+            signal("down", finishing, "normal", Value::U64(monitor.0)),
+            signal("exit", crashing, "crashed", Value::Unit),
+        ];
+        let Some(Value::List(received)) = garrison.result(major) else {
+            panic!("expected the signals, got {:?}", garrison.result(major));
+        };
+        // the two exits requested first arrive first, the others race
+        let received: Vec<Value> = received.iter().cloned().collect();
+        assert_eq!(received[..2], expected[..2]);
+        assert_eq!(received.len(), expected.len());
+        for signal in &expected[2..] {
+            assert!(received[2..].contains(signal), "{} not in {:?}", signal, received);
+        }
+    }
+
+    #[test]
+    fn link_auth_is_per_process() {
+        let identity = Identity::from_seed([1; 32]);
+        let mut garrison = garrison(&identity);
+        garrison.set_reductions(1);
+        // the major allows its garrison on top of what the test allows, then
+        // waits for good
+        let major = add_major(&mut garrison, &identity, "link_auth(garrison()); let x; x + 1;");
+        let allowed = add_corporal(&mut garrison, &identity, "1;");
+        let refused = add_corporal(&mut garrison, &identity, "1;");
+        let allowed_key = garrison.identity_of(allowed).unwrap().public_key();
+        assert_ne!(Some(allowed_key), garrison.identity_of(refused).map(Identity::public_key));
+        garrison.context_mut(major).unwrap().link_auth = Some(vec![allowed_key]);
+        garrison.context_mut(allowed).unwrap().requests.push(Request::Link(major));
+        garrison.context_mut(refused).unwrap().requests.push(Request::Link(major));
+
+        assert_eq!(garrison.run_until_idle(), RunState::Idle);
+        let reports: Vec<(ProcessId, String)> = garrison
+            .take_exit_reports()
+            .into_iter()
+            .map(|report| (report.pid, report.reason.to_string()))
+            .collect();
+        assert_eq!(reports, vec![(refused, format!("linked process {} exited: Unauthorised", major))]);
+        assert_eq!(garrison.status(allowed), Some(Status::Done));
+        assert_eq!(
+            garrison.context(major).unwrap().link_auth,
+            Some(vec![allowed_key, garrison.public_key()])
+        );
+    }
+
+    #[test]
+    fn link_auth_list_allows_every_key() {
+        let identity = Identity::from_seed([1; 32]);
+        let mut garrison = garrison(&identity);
+        let major = add_major(&mut garrison, &identity, "link_auth_list([garrison(), garrison()]);");
+
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        assert!(garrison.take_exit_reports().is_empty());
+        // the second time the key is allowed already
+        let added = Value::List([Value::Bool(true), Value::Bool(false)].into_iter().collect());
+        assert_eq!(garrison.result(major), Some(added));
+        assert_eq!(garrison.context(major).unwrap().link_auth, Some(vec![garrison.public_key()]));
+    }
+}
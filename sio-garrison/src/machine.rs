@@ -0,0 +1,98 @@
+use sio::{
+    Allocator, Dataflow, Environment, ExecutionEnviron, ExecutionMachine, Literal, PublicKey, Resolved, Spawn, SpawnError, State, Value, literal_mapper, literal_to_value, sequence_constructor
+};
+use werbolg_core::{AbsPath, FunId, Ident, Namespace, ValueFun, ir::Module};
+use werbolg_exec::{ExecutionParams, WerRefCount};
+use werbolg_compile::{compile, CompilationUnit};
+use werbolg_lang_common::{Report, ReportKind, Source};
+use alloc::{format, string::ToString, vec, vec::Vec, boxed::Box};
+use core::error::Error;
+use crate::{report_print, error::CompileError};
+
+pub fn compile_module(
+    //params: SioParams,
+    env: &mut Environment,
+    source: Source,
+    module: Module,
+) -> Result<CompilationUnit<Literal>, Box<dyn Error>> {
+    let module_ns = Namespace::root().append(Ident::from("main"));
+    let modules = vec![(module_ns.clone(), module)];
+    let compilation_params = werbolg_compile::CompilationParams {
+        literal_mapper,
+        sequence_constructor: Some(sequence_constructor()),
+    };
+    let cu = match compile(&compilation_params, modules, env) {
+        Err(e) => {
+            let report = Report::new(ReportKind::Error, format!("Compilation Error: {:?}", e))
+                .lines_before(1)
+                .lines_after(1)
+                .highlight(e.span().unwrap(), format!("compilation error here"));
+            report_print(&source, report)?;
+            return Err(Box::new(CompileError(e)));
+        }
+        Ok(m) => m,
+    };
+    //if params.dump_instr {
+    //    let mut out = String::new();
+    //    code_dump(&mut out, &cu.code, &cu.funs).expect("writing to string work");
+        //println!("{}", out);
+    //}
+    Ok(cu)
+}
+
+pub fn entry_point(cu: &CompilationUnit<Literal>) -> FunId {
+    let module_ns = Namespace::root().append(Ident::from("main"));
+    cu
+        .funs_tbl
+        .get(&AbsPath::new(&module_ns, &Ident::from("main")))
+        .expect("existing function as entry point")
+}
+
+/// Builds an execution machine running `fun(args)`.
+pub fn build_thread (
+    ee: WerRefCount<ExecutionEnviron>,
+    cu: WerRefCount<CompilationUnit<Literal>>,
+    fun: FunId,
+    args: &[Value],
+    state: State,
+) -> Result<ExecutionMachine, Box<dyn Error>> {
+    let execution_params = ExecutionParams {
+        literal_to_value,
+    };
+    let allocator = Allocator {};
+    let mut em = werbolg_exec::ExecutionMachine::new(
+        cu,
+        ee,
+        execution_params, allocator, state);
+    werbolg_exec::initialize(&mut em, fun, args).map_err(|e| format!("cannot start thread: {:?}", e))?;
+    Ok(em)
+}
+
+/// What `value` is bound to, `None` while it is an unbound variable.
+pub fn bound_value(store: &Dataflow<Value>, value: &Value) -> Option<Value> {
+    match store.resolve(value) {
+        Ok(Resolved::Value(value)) => Some(value),
+        _ => None,
+    }
+}
+
+/// Takes the `spawn` call `em` is blocked on, the function and arguments of
+/// the process to start in `garrison`.
+pub fn spawn_request(em: &mut ExecutionMachine, garrison: PublicKey) -> Result<(FunId, Vec<Value>), SpawnError> {
+    match em.userdata.spawn.take() {
+        Some(Spawn::Requested { garrison: target, .. }) if target != garrison => Err(SpawnError::OtherGarrison(target)),
+        Some(Spawn::Requested { fun: ValueFun::Fun(fun), args, .. }) => Ok((fun, args)),
+        _ => Err(SpawnError::NotAFunction),
+    }
+}
+
+/// Builds the machine of a process `spawn_request` asked for.
+pub fn build_spawned(
+    ee: WerRefCount<ExecutionEnviron>,
+    cu: WerRefCount<CompilationUnit<Literal>>,
+    fun: FunId,
+    args: &[Value],
+    state: State,
+) -> Result<ExecutionMachine, SpawnError> {
+    build_thread(ee, cu, fun, args, state).map_err(|e| SpawnError::Start(e.to_string()))
+}
@@ -0,0 +1,169 @@
+//! The single process tiers: generals, brigadiers and majors run one
+//! execution machine each and only differ in their [`Tier`].
+use sio::{
+    Environment, ExecutionEnviron, ExecutionMachine, Literal, ProcessContext, ProcessId, PublicKey, Spawn, SpawnError, Suspend, SignedModule, State, Tier, Value, tier
+};
+use werbolg_core::FunId;
+use sio::ffi::Signature;
+use werbolg_exec::{ExecutionError, WerRefCount};
+use werbolg_compile::CompilationUnit;
+use alloc::{vec, vec::Vec, boxed::Box, string::String};
+use core::error::Error;
+use core::marker::PhantomData;
+use crate::{run_frontend, verify_module, scheduler::Step, error::ProcessError};
+use crate::machine::{bound_value, build_spawned, build_thread, compile_module, entry_point, spawn_request};
+
+pub type General = Officer<tier::General>;
+pub type Brigadier = Officer<tier::Brigadier>;
+pub type Major = Officer<tier::Major>;
+
+pub struct Officer<T: Tier> {
+    em: ExecutionMachine,
+    cu: WerRefCount<CompilationUnit<Literal>>,
+    ee: WerRefCount<ExecutionEnviron>,
+    /// Function and arguments the officer started with.
+    start: (FunId, Vec<Value>),
+    /// What the started function returned, once it did.
+    result: Option<Value>,
+    tier: PhantomData<T>,
+}
+
+impl<T: Tier> Officer<T> {
+    /// Compiles `code`, which must be signed by `author`.
+    pub fn new(
+        code: SignedModule,
+        author: PublicKey,
+        path: String,
+        //params: SioParams,
+        env: Environment,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::with_host_functions(code, author, path, env, &[])
+    }
+    /// Compiles `code` like [`Officer::new`], its FFI declarations binding the
+    /// host functions of `hosts`, which must be registered in `env`.
+    pub fn with_host_functions(
+        code: SignedModule,
+        author: PublicKey,
+        path: String,
+        mut env: Environment,
+        hosts: &[Signature],
+    ) -> Result<Self, Box<dyn Error>> {
+        verify_module(&code, &author)?;
+        let (source, module) = run_frontend(code.source, path, T::KEY_TIER, hosts)?;
+        let cu = compile_module(/*params, */ &mut env, source, module)?;
+        let ee = WerRefCount::new(werbolg_exec::ExecutionEnviron::from_compile_environment(env.finalize()));
+        let cu = WerRefCount::new(cu);
+        let main = entry_point(&cu);
+        let em = build_thread(ee.clone(), cu.clone(), main, &[], State::default())?;
+        Ok(Self { em, cu, ee, start: (main, vec![]), result: None, tier: PhantomData })
+    }
+    /// A fresh machine state sharing the officer's variables.
+    fn state(&self) -> State {
+        State { store: self.em.userdata.store.clone(), ..Default::default() }
+    }
+    pub fn context(&self) -> &ProcessContext {
+        &self.em.userdata.context
+    }
+    pub fn context_mut(&mut self) -> &mut ProcessContext {
+        &mut self.em.userdata.context
+    }
+    /// Serves the `spawn` call the officer is blocked on, if it targets
+    /// `garrison`: the new officer runs the requested function of the same
+    /// module and shares the variables of this one. Its pid goes back
+    /// through [`Officer::spawned`].
+    pub fn spawn(&mut self, garrison: PublicKey) -> Result<Self, SpawnError> {
+        let (fun, args) = spawn_request(&mut self.em, garrison)?;
+        let em = build_spawned(self.ee.clone(), self.cu.clone(), fun, &args, self.state())?;
+        Ok(Self { em, cu: self.cu.clone(), ee: self.ee.clone(), start: (fun, args), result: None, tier: PhantomData })
+    }
+    /// Hands the result of a `spawn` call back to the officer.
+    pub fn spawned(&mut self, pid: Result<ProcessId, SpawnError>) {
+        self.em.userdata.spawn = Some(Spawn::Done(pid));
+    }
+    /// Processes waiting on a variable bound since the last call.
+    pub fn take_woken(&mut self) -> Vec<ProcessId> {
+        core::mem::take(&mut self.em.userdata.woken)
+    }
+    /// The value the started function returned, with its variables resolved. `None` while
+    /// it runs or when it returned a variable nobody bound.
+    pub fn result(&self) -> Option<Value> {
+        bound_value(&self.em.userdata.store.borrow(), self.result.as_ref()?)
+    }
+    /// Replaces the execution machine with a fresh one, started where the
+    /// officer first started.
+    pub fn restart(&mut self) -> Result<(), Box<dyn Error>> {
+        let (fun, args) = &self.start;
+        self.em = build_thread(self.ee.clone(), self.cu.clone(), *fun, args, self.state())?;
+        self.result = None;
+        Ok(())
+    }
+    /// Executes one instruction.
+    pub fn step(&mut self) -> Result<Step, ProcessError> {
+        match werbolg_exec::step(&mut self.em) {
+            Ok(None) => Ok(Step::Running),
+            Ok(Some(v)) => {
+                self.result = Some(v);
+                Ok(Step::Done)
+            }
+            Err(e) => match self.em.userdata.take_suspension() {
+                Some(why) => Ok(Step::Blocked(why)),
+                None => Err(self.crash(e)),
+            },
+        }
+    }
+    fn crash(&self, error: ExecutionError) -> ProcessError {
+        let mut debug_state = String::new();
+        self.em.debug_state(&mut debug_state).expect("writing to string work");
+        ProcessError {
+            tier: T::KEY_TIER,
+            who: self.context().who(),
+            thread: None,
+            ip: self.em.ip,
+            error,
+            debug_state,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod general_tests {
+    //use alloc::vec::Vec;
+    //use alloc::vec;
+    use alloc::string::ToString;
+    use sio::{create_env, Identity, Request, SignedModule};
+    use super::*;
+    static src: &str =
+        "
+        url public_key : spub132yw8ht5p8cetl2jmvknewjawt9xwzdlrk2pyxlnwjyqrdq0dawqxrffgs;
+        url type : src;
+        url name : app_name;
+        url app : public_key::type::name;
+        general app::General {
+            pub main :: () {
+                let here = garrison();
+                [install_brigadier(here), install_brigadier(here)];
+            }
+        }
+        ";
+
+    #[test]
+    fn basic_general_test() {
+        let env = create_env::<tier::General>();
+        // src is rooted at the public key of this identity
+        let identity = Identity::from_seed([1; 32]);
+        let code = SignedModule::sign(&identity, src.to_string());
+        let mut general = General::new(code, identity.public_key(), "/".to_string(), env).expect("General failure reason:");
+        let garrison = identity.derive("garrison").public_key();
+        general.context_mut().garrison = Some(garrison);
+        let mut steps = 0;
+        while general.step().expect("the general does not fail") != Step::Done {
+            steps += 1;
+            assert!(steps < 1000, "the general is still running");
+        }
+        // the installations are requests for the host, the general only
+        // learns that they were made
+        assert_eq!(general.context().requests, vec![Request::InstallBrigadier(garrison), Request::InstallBrigadier(garrison)]);
+        assert_eq!(general.result(), Some(Value::List([Value::Bool(true), Value::Bool(true)].into_iter().collect())));
+    }
+}
@@ -0,0 +1,510 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use hashbrown::HashMap;
+use sio::{ProcessId, Suspension};
+
+/// Number of instructions a process may execute before it is preempted.
+pub const DEFAULT_REDUCTIONS: usize = 2000;
+
+/// Where the execution machine of a process lives in its garrison.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ProcessRef {
+    /// The brigadier of the garrison is the first, the others are the
+    /// processes it spawned.
+    Brigadier(usize),
+    Major(usize),
+    /// A corporal and one of its threads.
+    Corporal(usize, usize),
+}
+
+/// Outcome of executing one instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    Running,
+    Blocked(Suspension),
+    Done,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Ready,
+    Blocked(Suspension),
+    Done,
+    /// Failed with an execution error, never scheduled again.
+    Crashed,
+    /// Stopped by its supervisor.
+    Stopped,
+}
+
+/// What the garrison can do next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunState {
+    /// Some process is ready to run.
+    Runnable,
+    /// Nothing is ready, but some process waits on a variable or mailbox.
+    Idle,
+    /// Every process has finished or crashed.
+    Terminated,
+}
+
+struct Entry {
+    target: ProcessRef,
+    supervisor: Option<ProcessId>,
+    status: Status,
+    /// Whether the pid is on the run queue, so it is never queued twice.
+    queued: bool,
+}
+
+pub struct Scheduler {
+    reductions: usize,
+    /// Every process ever spawned, pids are handed out in order so the pid
+    /// is the index.
+    processes: Vec<Entry>,
+    /// Blocked processes by what they wait on, so waking touches only them.
+    waiting: HashMap<Suspension, Vec<ProcessId>>,
+    run_queue: VecDeque<ProcessId>,
+}
+
+impl Scheduler {
+    pub fn new(reductions: usize) -> Self {
+        assert!(reductions > 0, "a process needs at least one reduction per slice");
+        Self {
+            reductions,
+            processes: Vec::new(),
+            waiting: HashMap::new(),
+            run_queue: VecDeque::new(),
+        }
+    }
+
+    pub fn reductions(&self) -> usize {
+        self.reductions
+    }
+
+    pub fn set_reductions(&mut self, reductions: usize) {
+        assert!(reductions > 0, "a process needs at least one reduction per slice");
+        self.reductions = reductions;
+    }
+
+    pub fn spawn(&mut self, target: ProcessRef, supervisor: Option<ProcessId>) -> ProcessId {
+        let pid = ProcessId(self.processes.len() as u64);
+        self.processes.push(Entry { target, supervisor, status: Status::Ready, queued: false });
+        self.enqueue(pid);
+        pid
+    }
+
+    fn entry(&self, pid: ProcessId) -> Option<&Entry> {
+        self.processes.get(usize::try_from(pid.0).ok()?)
+    }
+
+    fn entry_mut(&mut self, pid: ProcessId) -> Option<&mut Entry> {
+        self.processes.get_mut(usize::try_from(pid.0).ok()?)
+    }
+
+    pub fn status(&self, pid: ProcessId) -> Option<Status> {
+        self.entry(pid).map(|e| e.status)
+    }
+
+    pub fn target(&self, pid: ProcessId) -> Option<ProcessRef> {
+        self.entry(pid).map(|e| e.target)
+    }
+
+    pub fn supervisor(&self, pid: ProcessId) -> Option<ProcessId> {
+        self.entry(pid).and_then(|e| e.supervisor)
+    }
+
+    pub fn set_supervisor(&mut self, pid: ProcessId, supervisor: ProcessId) {
+        if let Some(entry) = self.entry_mut(pid) {
+            entry.supervisor = Some(supervisor);
+        }
+    }
+
+    /// Sets the status of `pid`, keeping the index of blocked processes up
+    /// to date.
+    fn set_status(&mut self, pid: ProcessId, status: Status) {
+        let entry = self.entry_mut(pid).expect("a known process");
+        let previous = core::mem::replace(&mut entry.status, status);
+        if let Status::Blocked(why) = previous {
+            if let Some(pids) = self.waiting.get_mut(&why) {
+                pids.retain(|p| *p != pid);
+                if pids.is_empty() {
+                    self.waiting.remove(&why);
+                }
+            }
+        }
+        if let Status::Blocked(why) = status {
+            self.waiting.entry(why).or_default().push(pid);
+        }
+    }
+
+    fn enqueue(&mut self, pid: ProcessId) {
+        let entry = self.entry_mut(pid).expect("queuing a known process");
+        if !entry.queued {
+            entry.queued = true;
+            self.run_queue.push_back(pid);
+        }
+    }
+
+    /// Whether `pid` waits on the run queue. A process taken off the queue is
+    /// back on it before the end of its slice only if it was restarted.
+    pub fn is_queued(&self, pid: ProcessId) -> bool {
+        self.entry(pid).is_some_and(|e| e.queued)
+    }
+
+    /// Whether the process is ready or blocked, i.e. has not exited.
+    pub fn is_alive(&self, pid: ProcessId) -> bool {
+        matches!(self.status(pid), Some(Status::Ready) | Some(Status::Blocked(_)))
+    }
+
+    /// Takes the next ready process off the run queue. It must be handed back
+    /// with [`Scheduler::reschedule`] at the end of its slice.
+    pub fn pop_ready(&mut self) -> Option<(ProcessId, ProcessRef)> {
+        let pid = self.run_queue.pop_front()?;
+        let entry = self.entry_mut(pid).expect("queued processes are known");
+        entry.queued = false;
+        Some((pid, entry.target))
+    }
+
+    pub fn reschedule(&mut self, pid: ProcessId, last: Step) {
+        let status = match last {
+            Step::Running => {
+                self.enqueue(pid);
+                Status::Ready
+            }
+            Step::Blocked(why) => Status::Blocked(why),
+            Step::Done => Status::Done,
+        };
+        self.set_status(pid, status);
+    }
+
+    /// Retires a process that failed.
+    pub fn crash(&mut self, pid: ProcessId) {
+        self.retire(pid, Status::Crashed);
+    }
+
+    pub fn stop(&mut self, pid: ProcessId) {
+        self.retire(pid, Status::Stopped);
+    }
+
+    fn retire(&mut self, pid: ProcessId, status: Status) {
+        self.set_status(pid, status);
+        if self.is_queued(pid) {
+            self.entry_mut(pid).expect("retiring a known process").queued = false;
+            self.run_queue.retain(|p| *p != pid);
+        }
+    }
+
+    /// Puts a process back on the run queue after its machine was rebuilt.
+    pub fn restart(&mut self, pid: ProcessId) {
+        self.set_status(pid, Status::Ready);
+        self.enqueue(pid);
+    }
+
+    /// Makes every process blocked on `why` ready again, returns how many woke.
+    pub fn wake(&mut self, why: Suspension) -> usize {
+        let pids = self.waiting.remove(&why).unwrap_or_default();
+        for pid in &pids {
+            self.entry_mut(*pid).expect("waiting processes are known").status = Status::Ready;
+            self.enqueue(*pid);
+        }
+        pids.len()
+    }
+
+    /// Makes `pid` ready again if it is blocked on `why`.
+    pub fn wake_process(&mut self, pid: ProcessId, why: Suspension) -> bool {
+        if self.status(pid) != Some(Status::Blocked(why)) {
+            return false;
+        }
+        self.set_status(pid, Status::Ready);
+        self.enqueue(pid);
+        true
+    }
+
+    pub fn state(&self) -> RunState {
+        if !self.run_queue.is_empty() {
+            RunState::Runnable
+        } else if !self.waiting.is_empty() {
+            RunState::Idle
+        } else {
+            RunState::Terminated
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_robin() {
+        let mut s = Scheduler::new(DEFAULT_REDUCTIONS);
+        let a = s.spawn(ProcessRef::Brigadier(0), None);
+        let b = s.spawn(ProcessRef::Major(0), Some(a));
+        assert_eq!(s.pop_ready(), Some((a, ProcessRef::Brigadier(0))));
+        s.reschedule(a, Step::Running);
+        assert_eq!(s.pop_ready(), Some((b, ProcessRef::Major(0))));
+        s.reschedule(b, Step::Done);
+        assert_eq!(s.pop_ready(), Some((a, ProcessRef::Brigadier(0))));
+        s.reschedule(a, Step::Running);
+        assert_eq!(s.state(), RunState::Runnable);
+        assert_eq!(s.status(b), Some(Status::Done));
+    }
+
+    #[test]
+    fn blocked_until_woken() {
+        let mut s = Scheduler::new(1);
+        let a = s.spawn(ProcessRef::Corporal(0, 0), None);
+        let b = s.spawn(ProcessRef::Corporal(0, 1), None);
+        s.pop_ready();
+        s.reschedule(a, Step::Blocked(Suspension::Unbound(7)));
+        s.pop_ready();
+        s.reschedule(b, Step::Blocked(Suspension::Receive));
+        assert_eq!(s.pop_ready(), None);
+        assert_eq!(s.state(), RunState::Idle);
+
+        assert_eq!(s.wake(Suspension::Unbound(8)), 0);
+        assert_eq!(s.wake(Suspension::Unbound(7)), 1);
+        assert_eq!(s.status(a), Some(Status::Ready));
+        assert_eq!(s.pop_ready(), Some((a, ProcessRef::Corporal(0, 0))));
+        s.reschedule(a, Step::Done);
+        assert_eq!(s.state(), RunState::Idle);
+
+        assert!(!s.wake_process(a, Suspension::Receive));
+        assert_eq!(s.status(ProcessId(9)), None);
+        assert!(!s.wake_process(ProcessId(9), Suspension::Receive));
+        assert!(s.wake_process(b, Suspension::Receive));
+        assert!(!s.wake_process(b, Suspension::Receive));
+        s.pop_ready();
+        s.reschedule(b, Step::Done);
+        assert_eq!(s.state(), RunState::Terminated);
+    }
+
+    #[test]
+    fn crashed_processes_are_retired() {
+        let mut s = Scheduler::new(DEFAULT_REDUCTIONS);
+        let brigadier = s.spawn(ProcessRef::Brigadier(0), None);
+        let major = s.spawn(ProcessRef::Major(0), Some(brigadier));
+        assert_eq!(s.supervisor(major), Some(brigadier));
+        s.pop_ready();
+        s.reschedule(brigadier, Step::Running);
+        assert_eq!(s.pop_ready(), Some((major, ProcessRef::Major(0))));
+        s.crash(major);
+        assert_eq!(s.status(major), Some(Status::Crashed));
+        assert_eq!(s.pop_ready(), Some((brigadier, ProcessRef::Brigadier(0))));
+        s.reschedule(brigadier, Step::Done);
+        assert_eq!(s.pop_ready(), None);
+        assert_eq!(s.state(), RunState::Terminated);
+
+        s.restart(major);
+        assert!(s.is_alive(major));
+        s.stop(major);
+        assert_eq!(s.status(major), Some(Status::Stopped));
+        assert_eq!(s.state(), RunState::Terminated);
+    }
+
+    #[test]
+    fn stopping_a_blocked_process_drops_it_from_the_waiters() {
+        let mut s = Scheduler::new(1);
+        let a = s.spawn(ProcessRef::Corporal(0, 0), None);
+        let b = s.spawn(ProcessRef::Corporal(0, 1), None);
+        s.pop_ready();
+        s.reschedule(a, Step::Blocked(Suspension::Receive));
+        s.pop_ready();
+        s.reschedule(b, Step::Blocked(Suspension::Receive));
+        s.stop(a);
+        assert_eq!(s.state(), RunState::Idle);
+        assert_eq!(s.wake(Suspension::Receive), 1);
+        assert_eq!(s.status(a), Some(Status::Stopped));
+        assert_eq!(s.pop_ready(), Some((b, ProcessRef::Corporal(0, 1))));
+        assert_eq!(s.pop_ready(), None);
+        assert_eq!(s.wake(Suspension::Receive), 0);
+    }
+
+    #[test]
+    fn restarted_processes_are_queued_once() {
+        let mut s = Scheduler::new(DEFAULT_REDUCTIONS);
+        let a = s.spawn(ProcessRef::Major(0), None);
+        let b = s.spawn(ProcessRef::Major(1), None);
+        assert_eq!(s.pop_ready(), Some((a, ProcessRef::Major(0))));
+        assert!(!s.is_queued(a));
+        // restarted while running, then handed back at the end of its slice
+        s.restart(a);
+        s.restart(a);
+        assert!(s.is_queued(a));
+        s.reschedule(a, Step::Running);
+        assert_eq!(s.pop_ready(), Some((b, ProcessRef::Major(1))));
+        s.reschedule(b, Step::Done);
+        assert_eq!(s.pop_ready(), Some((a, ProcessRef::Major(0))));
+        assert_eq!(s.pop_ready(), None);
+    }
+}
+
+#[cfg(test)]
+mod garrison_tests {
+    use alloc::{format, string::ToString};
+    use sio::{Identity, ProcessId, Suspension, Value};
+    use crate::testing::{add_major, corporal_running, signed_running};
+    use crate::{ExitReason, Garrison, ProcessRef, RunState, Status};
+
+    #[test]
+    fn threads_are_spawned() {
+        let identity = Identity::from_seed([1; 32]);
+        let (mut garrison, main) = corporal_running(
+            &identity,
+            "thread {
+                2;
+            }
+            1;",
+        );
+        assert_eq!(garrison.corporals[0].threads(), 1);
+
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        assert!(garrison.take_exit_reports().is_empty());
+        let spawned = ProcessId(main.0 + 1);
+        assert_eq!(garrison.corporals[0].threads(), 2);
+        assert_eq!(garrison.status(spawned), Some(Status::Done));
+        assert_eq!(garrison.context(spawned).unwrap().garrison, Some(identity.public_key()));
+        assert_eq!(garrison.result(main), Some(Value::I64(1)));
+        assert_eq!(garrison.result(spawned), Some(Value::I64(2)));
+    }
+
+    #[test]
+    fn reads_wait_for_bindings() {
+        let identity = Identity::from_seed([1; 32]);
+        let (mut garrison, main) = corporal_running(
+            &identity,
+            "let x;
+            thread {
+                x = 0;
+            }
+            if x == 0 {
+                true;
+            } else {
+                false;
+            }",
+        );
+        // the brigadier runs first, then main reads x before the thread had
+        // a chance to bind it
+        assert_eq!(garrison.march(), RunState::Runnable);
+        assert_eq!(garrison.march(), RunState::Runnable);
+        assert_eq!(garrison.status(main), Some(Status::Blocked(Suspension::Unbound(0))));
+
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        assert!(garrison.take_exit_reports().is_empty());
+        assert_eq!(garrison.status(main), Some(Status::Done));
+        assert_eq!(garrison.status(ProcessId(main.0 + 1)), Some(Status::Done));
+        assert_eq!(garrison.result(main), Some(Value::Bool(true)));
+    }
+
+    #[test]
+    fn threads_capture_every_variable_they_use() {
+        let identity = Identity::from_seed([1; 32]);
+        let (mut garrison, main) = corporal_running(
+            &identity,
+            "let x = 1;
+            let y = 2;
+            let z;
+            thread {
+                z = x + y;
+            }
+            z;",
+        );
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        assert!(garrison.take_exit_reports().is_empty());
+        assert_eq!(garrison.result(main), Some(Value::I64(3)));
+    }
+
+    #[test]
+    fn nifs_wait_for_their_arguments() {
+        let identity = Identity::from_seed([1; 32]);
+        let (mut garrison, main) = corporal_running(
+            &identity,
+            "let s;
+            thread {
+                s = to_string(100 + 23);
+            }
+            len(s);",
+        );
+        // the brigadier runs first, then main calls `len` right after
+        // spawning, before the thread bound `s`
+        assert_eq!(garrison.march(), RunState::Runnable);
+        assert_eq!(garrison.march(), RunState::Runnable);
+        assert!(matches!(garrison.status(main), Some(Status::Blocked(Suspension::Unbound(_)))));
+
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        assert!(garrison.take_exit_reports().is_empty());
+        assert_eq!(garrison.result(main), Some(Value::I64(3)));
+    }
+
+    #[test]
+    fn officers_spawn_processes_of_their_tier() {
+        let identity = Identity::from_seed([1; 32]);
+        let code = signed_running(&identity, "brigadier", "let x; thread { x = 1; } x + 1;");
+        let mut garrison = Garrison::new(code, identity.public_key(), "brigadier".to_string(), sio::create_env::<sio::tier::Brigadier>(), identity.clone()).unwrap();
+        let major = add_major(&mut garrison, &identity, "let x; thread { x = 2; } x + 1;");
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        assert!(garrison.take_exit_reports().is_empty());
+
+        assert_eq!(garrison.result(ProcessId(0)), Some(Value::I64(2)));
+        assert_eq!(garrison.result(major), Some(Value::I64(3)));
+        // the brigadier spawned first, then the major
+        let (spawned_brigadier, spawned_major) = (ProcessId(major.0 + 1), ProcessId(major.0 + 2));
+        assert_eq!(garrison.scheduler.target(spawned_brigadier), Some(ProcessRef::Brigadier(1)));
+        assert_eq!(garrison.scheduler.target(spawned_major), Some(ProcessRef::Major(1)));
+        assert_eq!(garrison.status(spawned_major), Some(Status::Done));
+        assert_eq!(garrison.scheduler.supervisor(spawned_major), Some(ProcessId(0)));
+        assert_eq!(
+            garrison.identity_of(spawned_major).unwrap().public_key(),
+            identity.derive(&format!("major/{}", spawned_major.0)).public_key()
+        );
+    }
+
+    #[test]
+    fn spawning_into_another_garrison_fails() {
+        let identity = Identity::from_seed([1; 32]);
+        let other = Identity::from_seed([2; 32]).public_key();
+        let (mut garrison, main) = corporal_running(&identity, "thread { 1; } 2;");
+        // the process believes it runs in `other`, the garrison refuses to
+        // spawn there
+        garrison.context_mut(main).unwrap().garrison = Some(other);
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        let reports = garrison.take_exit_reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].pid, main);
+        let ExitReason::Crashed(e) = &reports[0].reason else {
+            panic!("expected a crash, got {:?}", reports[0].reason);
+        };
+        assert!(e.to_string().contains(&format!("cannot spawn into garrison {}", other)), "{}", e);
+        assert_eq!(garrison.corporals[0].threads(), 1);
+    }
+
+    #[test]
+    fn crashes_are_reported_with_their_process() {
+        let identity = Identity::from_seed([1; 32]);
+        let (mut garrison, main) = corporal_running(
+            &identity,
+            "thread {
+                1 / 0;
+            }
+            1;",
+        );
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+        let spawned = ProcessId(main.0 + 1);
+        assert_eq!(garrison.status(main), Some(Status::Done));
+        assert_eq!(garrison.result(main), Some(Value::I64(1)));
+        assert_eq!(garrison.status(spawned), Some(Status::Crashed));
+
+        let reports = garrison.take_exit_reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].pid, spawned);
+        assert_eq!(reports[0].supervisor, Some(ProcessId(0)));
+        let ExitReason::Crashed(e) = &reports[0].reason else {
+            panic!("expected a crash, got {}", reports[0].reason);
+        };
+        assert_eq!(e.tier, sio::KeyTier::Corporal);
+        assert_eq!(e.who, Some(identity.derive(&format!("corporal/{}", spawned.0)).public_key()));
+        assert_eq!(e.thread, Some(1));
+        assert!(e.debug_state.contains(&format!("ip={}", e.ip)), "{}", e.debug_state);
+        assert!(e.to_string().starts_with(&format!("Corporal {} thread 1 failed at {}", e.who.unwrap(), e.ip)));
+        assert!(e.to_string().contains("division by zero in /"));
+    }
+}
@@ -0,0 +1,358 @@
+//! Supervision groups.
+//!
+//! A brigadier supervises groups of majors and a major supervises groups of
+//! corporals. When a child crashes its group's strategy decides which children
+//! to restart. A group that restarts more often than its [`Intensity`] allows
+//! gives up: its children are stopped and the failure escalates to the
+//! supervisor's own group.
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::fmt;
+use sio::ProcessId;
+pub use sio::Strategy;
+
+/// At most `max_restarts` restarts within `period` reductions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Intensity {
+    pub max_restarts: usize,
+    pub period: u64,
+}
+
+impl Default for Intensity {
+    fn default() -> Self {
+        Intensity { max_restarts: 3, period: 10_000 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GroupId(pub usize);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// The group tolerates the failure, nothing to restart.
+    Tolerate,
+    Restart(Vec<ProcessId>),
+    /// Too many restarts: stop these children and fail the supervisor.
+    Escalate(Vec<ProcessId>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Decision {
+    pub group: GroupId,
+    pub supervisor: ProcessId,
+    pub action: Action,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SupervisionError {
+    UnknownProcess(ProcessId),
+    /// Brigadiers supervise majors and majors supervise corporals.
+    WrongTier { supervisor: ProcessId, child: ProcessId },
+    AlreadySupervised(ProcessId),
+    NoChildren,
+    /// `k` is zero or larger than the group.
+    InvalidK { k: usize, n: usize },
+}
+
+impl fmt::Display for SupervisionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SupervisionError::UnknownProcess(pid) => write!(f, "unknown process {}", pid),
+            SupervisionError::WrongTier { supervisor, child } => {
+                write!(f, "{} cannot supervise {}", supervisor, child)
+            }
+            SupervisionError::AlreadySupervised(pid) => write!(f, "{} is already in a group", pid),
+            SupervisionError::NoChildren => write!(f, "a group needs at least one child"),
+            SupervisionError::InvalidK { k, n } => write!(f, "cannot require {} of {} children", k, n),
+        }
+    }
+}
+
+struct Group {
+    supervisor: ProcessId,
+    strategy: Strategy,
+    children: Vec<ProcessId>,
+    intensity: Intensity,
+    restarts: VecDeque<u64>,
+    /// Index of the active child, for standby groups.
+    active: usize,
+    stopped: bool,
+}
+
+#[derive(Default)]
+pub struct Supervisors {
+    groups: Vec<Group>,
+}
+
+impl Supervisors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a group. Tier checks are done by the garrison, which knows what
+    /// each process is.
+    pub fn add(
+        &mut self,
+        supervisor: ProcessId,
+        strategy: Strategy,
+        children: Vec<ProcessId>,
+        intensity: Intensity,
+    ) -> Result<GroupId, SupervisionError> {
+        if children.is_empty() {
+            return Err(SupervisionError::NoChildren);
+        }
+        if let Strategy::KOfN(k) = strategy {
+            if k == 0 || k > children.len() {
+                return Err(SupervisionError::InvalidK { k, n: children.len() });
+            }
+        }
+        if let Some(pid) = children.iter().find(|pid| self.group_of(**pid).is_some()) {
+            return Err(SupervisionError::AlreadySupervised(*pid));
+        }
+        self.groups.push(Group {
+            supervisor,
+            strategy,
+            children,
+            intensity,
+            restarts: VecDeque::new(),
+            active: 0,
+            stopped: false,
+        });
+        Ok(GroupId(self.groups.len() - 1))
+    }
+
+    pub fn group_of(&self, pid: ProcessId) -> Option<GroupId> {
+        self.groups
+            .iter()
+            .position(|g| !g.stopped && g.children.contains(&pid))
+            .map(GroupId)
+    }
+
+    pub fn children(&self, group: GroupId) -> &[ProcessId] {
+        &self.groups[group.0].children
+    }
+
+    /// The child currently serving a standby group.
+    pub fn active(&self, group: GroupId) -> ProcessId {
+        let g = &self.groups[group.0];
+        g.children[g.active]
+    }
+
+    /// Decides what to do about `pid` crashing at time `now`. `alive` tells
+    /// whether a sibling is still running. Returns `None` for processes no
+    /// group supervises.
+    pub fn on_exit(&mut self, pid: ProcessId, now: u64, alive: impl Fn(ProcessId) -> bool) -> Option<Decision> {
+        let group = self.group_of(pid)?;
+        let g = &mut self.groups[group.0];
+        let failed = g.children.iter().position(|c| *c == pid).expect("child of its group");
+
+        let restart = match g.strategy {
+            Strategy::Series => g.children[failed..].to_vec(),
+            Strategy::Parallel => alloc::vec![pid],
+            Strategy::KOfN(k) => {
+                let survivors = g.children.iter().filter(|c| **c != pid && alive(**c)).count();
+                if survivors >= k {
+                    Vec::new()
+                } else {
+                    g.children.iter().copied().filter(|c| *c == pid || !alive(*c)).collect()
+                }
+            }
+            Strategy::Standby => {
+                if failed == g.active {
+                    let n = g.children.len();
+                    if let Some(next) = (1..n)
+                        .map(|i| (g.active + i) % n)
+                        .find(|i| alive(g.children[*i]))
+                    {
+                        g.active = next;
+                    }
+                }
+                alloc::vec![pid]
+            }
+        };
+
+        let action = if restart.is_empty() {
+            Action::Tolerate
+        } else {
+            while g.restarts.front().is_some_and(|t| now.saturating_sub(*t) > g.intensity.period) {
+                g.restarts.pop_front();
+            }
+            g.restarts.push_back(now);
+            if g.restarts.len() > g.intensity.max_restarts {
+                g.stopped = true;
+                Action::Escalate(g.children.clone())
+            } else {
+                Action::Restart(restart)
+            }
+        };
+        Some(Decision { group, supervisor: g.supervisor, action })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    const SUPERVISOR: ProcessId = ProcessId(0);
+
+    fn pids(n: u64) -> Vec<ProcessId> {
+        (1..=n).map(ProcessId).collect()
+    }
+
+    fn restarts(d: Option<Decision>) -> Vec<ProcessId> {
+        match d.map(|d| d.action) {
+            Some(Action::Restart(pids)) => pids,
+            other => panic!("expected a restart, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn series_and_parallel() {
+        let mut s = Supervisors::new();
+        s.add(SUPERVISOR, Strategy::Series, pids(3), Intensity::default()).unwrap();
+        s.add(SUPERVISOR, Strategy::Parallel, vec![ProcessId(4), ProcessId(5)], Intensity::default()).unwrap();
+        // the failed child and the ones started after it
+        assert_eq!(restarts(s.on_exit(ProcessId(2), 0, |_| true)), vec![ProcessId(2), ProcessId(3)]);
+        assert_eq!(restarts(s.on_exit(ProcessId(1), 0, |_| true)), pids(3));
+        assert_eq!(restarts(s.on_exit(ProcessId(3), 0, |_| true)), vec![ProcessId(3)]);
+        assert_eq!(restarts(s.on_exit(ProcessId(5), 0, |_| true)), vec![ProcessId(5)]);
+        assert_eq!(s.on_exit(ProcessId(9), 0, |_| true), None);
+        assert_eq!(
+            s.add(SUPERVISOR, Strategy::Parallel, vec![ProcessId(1)], Intensity::default()),
+            Err(SupervisionError::AlreadySupervised(ProcessId(1)))
+        );
+    }
+
+    #[test]
+    fn k_of_n() {
+        let mut s = Supervisors::new();
+        s.add(SUPERVISOR, Strategy::KOfN(2), pids(4), Intensity::default()).unwrap();
+        let dead = [ProcessId(1)];
+        let alive = |pid: ProcessId| !dead.contains(&pid);
+        assert_eq!(s.on_exit(ProcessId(1), 0, alive).unwrap().action, Action::Tolerate);
+
+        let dead = [ProcessId(1), ProcessId(2)];
+        let alive = |pid: ProcessId| !dead.contains(&pid);
+        assert_eq!(s.on_exit(ProcessId(2), 0, alive).unwrap().action, Action::Tolerate);
+
+        let dead = [ProcessId(1), ProcessId(2), ProcessId(3)];
+        let alive = |pid: ProcessId| !dead.contains(&pid);
+        assert_eq!(restarts(s.on_exit(ProcessId(3), 0, alive)), pids(3));
+
+        assert_eq!(
+            s.add(SUPERVISOR, Strategy::KOfN(3), vec![ProcessId(8), ProcessId(9)], Intensity::default()),
+            Err(SupervisionError::InvalidK { k: 3, n: 2 })
+        );
+    }
+
+    #[test]
+    fn standby_promotes_a_spare() {
+        let mut s = Supervisors::new();
+        let group = s.add(SUPERVISOR, Strategy::Standby, pids(3), Intensity::default()).unwrap();
+        assert_eq!(s.active(group), ProcessId(1));
+        assert_eq!(restarts(s.on_exit(ProcessId(1), 0, |pid| pid != ProcessId(1))), vec![ProcessId(1)]);
+        assert_eq!(s.active(group), ProcessId(2));
+        // a spare failing leaves the active child alone
+        assert_eq!(restarts(s.on_exit(ProcessId(3), 0, |pid| pid != ProcessId(3))), vec![ProcessId(3)]);
+        assert_eq!(s.active(group), ProcessId(2));
+    }
+
+    #[test]
+    fn intensity_escalates() {
+        let mut s = Supervisors::new();
+        let intensity = Intensity { max_restarts: 2, period: 100 };
+        s.add(SUPERVISOR, Strategy::Parallel, pids(2), intensity).unwrap();
+        restarts(s.on_exit(ProcessId(1), 0, |_| true));
+        restarts(s.on_exit(ProcessId(1), 50, |_| true));
+        // the restart at 0 is outside the window by now
+        restarts(s.on_exit(ProcessId(2), 120, |_| true));
+        let d = s.on_exit(ProcessId(2), 130, |_| true).unwrap();
+        assert_eq!(d.supervisor, SUPERVISOR);
+        assert_eq!(d.action, Action::Escalate(pids(2)));
+        // a stopped group no longer supervises its children
+        assert_eq!(s.on_exit(ProcessId(1), 140, |_| true), None);
+    }
+}
+
+#[cfg(test)]
+mod garrison_tests {
+    use alloc::{string::String, vec, vec::Vec};
+    use sio::{ExitSignal, Identity, ProcessId, Request, Signal, Suspension};
+    use crate::testing::{add_corporal, add_major, garrison, three_tiers};
+    use crate::{Intensity, RunState, Status, Strategy, SupervisionError};
+
+    #[test]
+    fn supervise_checks_tiers() {
+        let identity = Identity::from_seed([1; 32]);
+        let (mut garrison, brigadier, major, corporal) = three_tiers(&identity);
+        let spare = add_corporal(&mut garrison, &identity, "1;");
+
+        assert_eq!(
+            garrison.supervise(brigadier, Strategy::Parallel, vec![corporal], Intensity::default()),
+            Err(SupervisionError::WrongTier { supervisor: brigadier, child: corporal })
+        );
+        let group = garrison.supervise(major, Strategy::Standby, vec![corporal, spare], Intensity::default()).unwrap();
+        assert_eq!(garrison.active(group), corporal);
+        assert!(garrison.supervise(brigadier, Strategy::Series, vec![major], Intensity::default()).is_ok());
+        assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+    }
+
+    #[test]
+    fn series_restarts_the_failed_child_and_the_ones_after_it() {
+        let identity = Identity::from_seed([1; 32]);
+        let (mut garrison, _, major, _) = three_tiers(&identity);
+        garrison.set_reductions(1);
+        // blocked for good on a variable nobody binds
+        let children = [(); 3].map(|_| add_corporal(&mut garrison, &identity, "let x; x + 1;"));
+        let watcher = add_corporal(&mut garrison, &identity, "let x; x + 1;");
+        let failing = add_corporal(&mut garrison, &identity, "1 / 0;");
+        garrison.supervise(major, Strategy::Series, children.to_vec(), Intensity::default()).unwrap();
+        // the middle child dies with the process it links to
+        garrison.context_mut(children[1]).unwrap().requests.push(Request::Link(failing));
+        // a fresh context after a restart clears the flag
+        garrison.context_mut(children[0]).unwrap().trap_exit = true;
+        garrison.context_mut(children[2]).unwrap().trap_exit = true;
+        let context = garrison.context_mut(watcher).unwrap();
+        context.trap_exit = true;
+        context.requests.push(Request::Link(children[2]));
+        let monitor = context.monitor(children[2]);
+
+        assert_eq!(garrison.run_until_idle(), RunState::Idle);
+        let reports = garrison.take_exit_reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].pid, failing);
+        assert!(garrison.context(children[0]).unwrap().trap_exit);
+        assert!(!garrison.context(children[2]).unwrap().trap_exit);
+        for child in children {
+            assert!(matches!(garrison.status(child), Some(Status::Blocked(Suspension::Unbound(_)))));
+        }
+        // the sibling restarted with the failed child exited as far as its
+        // links and monitors can tell
+        let mailbox: Vec<Signal> = garrison.context(watcher).unwrap().mailbox.iter().copied().collect();
+        assert_eq!(
+            mailbox,
+            vec![
+                Signal::Down { monitor, from: children[2], reason: ExitSignal::Stopped },
+                Signal::Exit { from: children[2], reason: ExitSignal::Stopped },
+            ]
+        );
+    }
+
+    #[test]
+    fn supervise_is_checked_by_the_garrison() {
+        let identity = Identity::from_seed([1; 32]);
+        let refusal = |body: &str| -> String {
+            let mut garrison = garrison(&identity);
+            let major = add_major(&mut garrison, &identity, body);
+            assert_eq!(garrison.run_until_idle(), RunState::Terminated);
+            let reports = garrison.take_exit_reports();
+            assert_eq!(reports.len(), 1);
+            assert_eq!(reports[0].pid, major);
+            reports[0].reason.to_string()
+        };
+        assert_eq!(refusal("supervise(\"parallel\", [self()]);"), "supervision refused: <1> cannot supervise <1>");
+        assert_eq!(refusal("supervise_k_of_n(1, []);"), "supervision refused: a group needs at least one child");
+        assert!(refusal("supervise(\"rest\", [self()]);").contains("unknown supervision strategy \\\"rest\\\""));
+    }
+}
@@ -0,0 +1,61 @@
+//! Fixtures shared by the tests of the garrison and its parts.
+use sio::{Identity, ProcessId, SignedModule};
+use alloc::{format, string::ToString};
+use crate::{Corporal, Garrison, Major};
+
+/// A module of `tier` whose `main` returns 1.
+pub fn signed(author: &Identity, tier: &str) -> SignedModule {
+    signed_running(author, tier, "1;")
+}
+
+/// A module of `tier` whose `main` runs `body`.
+pub fn signed_running(author: &Identity, tier: &str, body: &str) -> SignedModule {
+    let src = format!(
+        "url author : {};
+        {} author::App {{
+            pub main :: () {{
+                {}
+            }}
+        }}",
+        author.public_key(),
+        tier,
+        body
+    );
+    SignedModule::sign(author, src)
+}
+
+/// A garrison of `identity` whose brigadier returns right away.
+pub fn garrison(identity: &Identity) -> Garrison {
+    let code = signed(identity, "brigadier");
+    Garrison::new(code, identity.public_key(), "brigadier".to_string(), sio::create_env::<sio::tier::Brigadier>(), identity.clone()).unwrap()
+}
+
+/// Adds a major running `body` to `garrison`.
+pub fn add_major(garrison: &mut Garrison, identity: &Identity, body: &str) -> ProcessId {
+    let code = signed_running(identity, "major", body);
+    garrison.add_major(Major::new(code, identity.public_key(), "/".to_string(), sio::create_env::<sio::tier::Major>()).unwrap())
+}
+
+/// Adds a corporal running `body` to `garrison`, returns its main thread.
+pub fn add_corporal(garrison: &mut Garrison, identity: &Identity, body: &str) -> ProcessId {
+    let code = signed_running(identity, "corporal", body);
+    let corporal = Corporal::new(code, identity.public_key(), "/".to_string(), sio::create_env::<sio::tier::Corporal>()).unwrap();
+    garrison.add_corporal(corporal)[0]
+}
+
+/// A garrison with one process of each tier: its brigadier, a major and a
+/// corporal, all returning right away.
+pub fn three_tiers(identity: &Identity) -> (Garrison, ProcessId, ProcessId, ProcessId) {
+    let mut garrison = garrison(identity);
+    let major = add_major(&mut garrison, identity, "1;");
+    let corporal = add_corporal(&mut garrison, identity, "1;");
+    (garrison, ProcessId(0), major, corporal)
+}
+
+/// A garrison running a corporal whose `main` runs `body`, with the main
+/// thread of the corporal.
+pub fn corporal_running(identity: &Identity, body: &str) -> (Garrison, ProcessId) {
+    let mut garrison = garrison(identity);
+    let main = add_corporal(&mut garrison, identity, body);
+    (garrison, main)
+}
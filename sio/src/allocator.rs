@@ -1,23 +1,8 @@
-use crate::value::{GeneralValue, BrigadierValue, MajorValue, CorporalValue};
+use crate::value::Value;
 use werbolg_exec::WAllocator;
 
-pub struct GeneralAllocator;
-pub struct BrigadierAllocator;
-pub struct MajorAllocator;
-pub struct CorporalAllocator;
+pub struct Allocator;
 
-impl WAllocator for GeneralAllocator {
-    type Value = GeneralValue;
-}
-
-impl WAllocator for BrigadierAllocator {
-    type Value = BrigadierValue;
-}
-
-impl WAllocator for MajorAllocator {
-    type Value = MajorValue;
-}
-
-impl WAllocator for CorporalAllocator {
-    type Value = CorporalValue;
+impl WAllocator for Allocator {
+    type Value = Value;
 }
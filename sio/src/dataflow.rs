@@ -0,0 +1,148 @@
+//! Dataflow memory: logic variables that are bound at most once.
+//!
+//! `let x;` creates an unbound variable. Binding it again is only allowed
+//! with an equal value, and reading it before it is bound suspends the reader
+//! until some other thread binds it.
+use crate::process::ProcessId;
+use alloc::vec::Vec;
+use core::fmt;
+use single_assignment_store::{DataflowStore, Unify, UnifyError};
+
+pub type VarId = u64;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BindError<V> {
+    /// The variable holds `bound`, which differs from `value`.
+    AlreadyBound { var: VarId, bound: V, value: V },
+    UnknownVar(VarId),
+}
+
+impl<V: fmt::Debug> fmt::Display for BindError<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindError::AlreadyBound { var, bound, value } => {
+                write!(f, "variable {} is already bound to {:?}, cannot bind it to {:?}", var, bound, value)
+            }
+            BindError::UnknownVar(var) => write!(f, "unknown variable {}", var),
+        }
+    }
+}
+
+/// A value with its bound variables followed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Resolved<V> {
+    Unbound(VarId),
+    Value(V),
+}
+
+#[derive(Clone)]
+pub struct Dataflow<V> {
+    next: VarId,
+    vars: DataflowStore<VarId, V>,
+}
+
+impl<V> Default for Dataflow<V> {
+    fn default() -> Self {
+        Dataflow { next: 0, vars: DataflowStore::new() }
+    }
+}
+
+impl<V: Unify<VarId>> Dataflow<V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn new_var(&mut self) -> VarId {
+        let var = self.next;
+        self.next += 1;
+        self.vars.declare(var);
+        var
+    }
+
+    /// Follows `value` through bound variables.
+    pub fn resolve(&self, value: &V) -> Result<Resolved<V>, BindError<V>> {
+        let value = self.vars.walk(value).map_err(|e| self.bind_error(value, e))?;
+        Ok(match value.key() {
+            Some(var) => Resolved::Unbound(*var),
+            None => Resolved::Value(value),
+        })
+    }
+
+    /// Unifies `target` with `value`. Binding two unbound variables makes
+    /// them one, binding a bound variable checks the values are equal.
+    /// Returns the processes that waited on the variables it bound.
+    pub fn bind(&mut self, target: &V, value: &V) -> Result<Vec<ProcessId>, BindError<V>> {
+        match self.vars.unify(target, value) {
+            Ok(woken) => Ok(woken.into_iter().map(ProcessId).collect()),
+            Err(e) => Err(self.bind_error(target, e)),
+        }
+    }
+
+    /// Has the [`bind`] of `var` return `pid`.
+    ///
+    /// [`bind`]: Dataflow::bind
+    pub fn wait(&mut self, var: VarId, pid: ProcessId) {
+        self.vars.wait_on(&var, pid.0);
+    }
+
+    fn bind_error(&self, target: &V, e: UnifyError<VarId, V>) -> BindError<V> {
+        match e {
+            UnifyError::UnknownKey(var) => BindError::UnknownVar(var),
+            UnifyError::Mismatch(bound, value) => BindError::AlreadyBound {
+                var: target.key().copied().unwrap_or_default(),
+                bound,
+                value,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum V {
+        Var(VarId),
+        Int(u64),
+    }
+
+    impl Unify<VarId> for V {
+        fn key(&self) -> Option<&VarId> {
+            match self {
+                V::Var(id) => Some(id),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn bind_once() {
+        let mut d = Dataflow::new();
+        let x = V::Var(d.new_var());
+        assert_eq!(d.resolve(&x), Ok(Resolved::Unbound(0)));
+        d.wait(0, ProcessId(1));
+        assert_eq!(d.bind(&x, &V::Int(3)), Ok(vec![ProcessId(1)]));
+        assert_eq!(d.resolve(&x), Ok(Resolved::Value(V::Int(3))));
+
+        // rebinding to an equal value is fine, to another one is not
+        assert_eq!(d.bind(&x, &V::Int(3)), Ok(vec![]));
+        assert_eq!(d.bind(&x, &V::Int(4)), Err(BindError::AlreadyBound { var: 0, bound: V::Int(3), value: V::Int(4) }));
+        assert_eq!(d.resolve(&V::Var(9)), Err(BindError::UnknownVar(9)));
+    }
+
+    #[test]
+    fn variables_bound_to_variables() {
+        let mut d = Dataflow::new();
+        let x = V::Var(d.new_var());
+        let y = V::Var(d.new_var());
+        assert_eq!(d.bind(&x, &y), Ok(vec![]));
+        assert!(matches!(d.resolve(&x), Ok(Resolved::Unbound(_))));
+        d.wait(0, ProcessId(3));
+        d.wait(1, ProcessId(4));
+        assert_eq!(d.bind(&V::Int(7), &x), Ok(vec![ProcessId(3), ProcessId(4)]));
+        assert_eq!(d.resolve(&x), Ok(Resolved::Value(V::Int(7))));
+        assert_eq!(d.resolve(&y), Ok(Resolved::Value(V::Int(7))));
+    }
+}
@@ -0,0 +1,1078 @@
+use crate::value::{Value, ValueInt, BOOL_KIND, F64_KIND, FUN_KIND, I64_KIND, PID_KIND, STR_KIND};
+use werbolg_compile::{CompilationError, CallArity};
+use werbolg_core::{AbsPath, Ident, Literal as IrLiteral, Namespace, NifId, Span};
+use werbolg_core::id::IdF;
+use werbolg_exec::{ExecutionError, NIFCall, Valuable, WAllocator};
+use crate::{Environment, ExecutionMachine};
+use crate::tier::{self, Tier};
+use crate::dataflow::{BindError, Resolved};
+use crate::list::List;
+use crate::process::{Pid, ProcessContext, ProcessId, Request, Signal, Spawn, SpawnError, Strategy, Suspend, Suspension};
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::{Hash, Hasher};
+
+/// `unbound()`, a fresh dataflow variable.
+fn nif_unbound(em: &mut ExecutionMachine) -> Result<Value, ExecutionError> {
+    let var = em.userdata.store.borrow_mut().new_var();
+    Ok(Value::Var(var))
+}
+
+/// `read(v)`, the value bound to `v`. Blocks until some other process binds
+/// it when `v` is an unbound variable.
+fn nif_read(em: &mut ExecutionMachine) -> Result<Value, ExecutionError> {
+    let (_, args) = em.stack.get_call_and_args(em.current_arity);
+    let resolved = em.userdata.store.borrow().resolve(&args[0]).map_err(bind_error)?;
+    match resolved {
+        Resolved::Value(value) => Ok(value),
+        Resolved::Unbound(var) => {
+            let pid = em.userdata.context.pid;
+            em.userdata.store.borrow_mut().wait(var, pid);
+            em.userdata.suspend(Suspension::Unbound(var));
+            Err(ExecutionError::UserPanic {
+                message: "blocked on an unbound variable".to_string(),
+            })
+        }
+    }
+}
+
+/// `bind(x, v)`, binds the variable `x` to `v`. Binding an already bound
+/// variable is only allowed with an equal value.
+fn nif_bind(em: &mut ExecutionMachine) -> Result<Value, ExecutionError> {
+    let (_, args) = em.stack.get_call_and_args(em.current_arity);
+    let woken = em.userdata.store.borrow_mut().bind(&args[0], &args[1]).map_err(bind_error)?;
+    em.userdata.woken.extend(woken);
+    Ok(Value::Unit)
+}
+
+fn bind_error(e: BindError<Value>) -> ExecutionError {
+    ExecutionError::UserPanic { message: e.to_string() }
+}
+
+fn nif_unit<A: WAllocator>(_: &A, _args: &[Value]) -> Result<Value, ExecutionError> {
+    Ok(Value::Unit)
+}
+
+/// Matches two integers of the same kind, binding them to `$x` and `$y` in
+/// `$e`. Any other pair of values is a kind error.
+macro_rules! int_op {
+    ($a:expr, $b:expr, |$x:ident, $y:ident| $e:expr) => {
+        match ($a, $b) {
+            (Value::I8($x), Value::I8($y)) => $e,
+            (Value::I16($x), Value::I16($y)) => $e,
+            (Value::I32($x), Value::I32($y)) => $e,
+            (Value::I64($x), Value::I64($y)) => $e,
+            (Value::U8($x), Value::U8($y)) => $e,
+            (Value::U16($x), Value::U16($y)) => $e,
+            (Value::U32($x), Value::U32($y)) => $e,
+            (Value::U64($x), Value::U64($y)) => $e,
+            (a, b) => Err(ExecutionError::ValueKindUnexpected {
+                value_expected: if a.is_int() { a.descriptor() } else { I64_KIND },
+                value_got: if a.is_int() { b.descriptor() } else { a.descriptor() },
+            }),
+        }
+    };
+    ($a:expr, |$x:ident| $e:expr) => {
+        match $a {
+            Value::I8($x) => $e,
+            Value::I16($x) => $e,
+            Value::I32($x) => $e,
+            Value::I64($x) => $e,
+            Value::U8($x) => $e,
+            Value::U16($x) => $e,
+            Value::U32($x) => $e,
+            Value::U64($x) => $e,
+            a => Err(ExecutionError::ValueKindUnexpected {
+                value_expected: I64_KIND,
+                value_got: a.descriptor(),
+            }),
+        }
+    };
+}
+
+/// Like `int_op`, with `$f` for two floats.
+macro_rules! num_op {
+    ($a:expr, $b:expr, |$x:ident, $y:ident| $e:expr, $f:expr) => {
+        match ($a, $b) {
+            (Value::F64($x), Value::F64($y)) => $f,
+            (Value::F64(_), b) => Err(ExecutionError::ValueKindUnexpected {
+                value_expected: F64_KIND,
+                value_got: b.descriptor(),
+            }),
+            (a, b) => int_op!(a, b, |$x, $y| $e),
+        }
+    };
+    ($a:expr, |$x:ident| $e:expr, $f:expr) => {
+        match $a {
+            Value::F64($x) => $f,
+            a => int_op!(a, |$x| $e),
+        }
+    };
+}
+
+/// The result of a checked operation, or an overflow error naming `op`.
+fn checked<T: Into<Value>>(op: &str, n: Option<T>) -> Result<Value, ExecutionError> {
+    n.map(Into::into).ok_or_else(|| ExecutionError::UserPanic {
+        message: format!("integer overflow in {}", op),
+    })
+}
+
+fn nif_plus<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    num_op!(&args[0], &args[1], |n1, n2| checked("+", n1.checked_add(*n2)), Ok(Value::F64(n1 + n2)))
+}
+
+fn nif_sub<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    num_op!(&args[0], &args[1], |n1, n2| checked("-", n1.checked_sub(*n2)), Ok(Value::F64(n1 - n2)))
+}
+
+fn nif_mul<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    num_op!(&args[0], &args[1], |n1, n2| checked("*", n1.checked_mul(*n2)), Ok(Value::F64(n1 * n2)))
+}
+
+fn division_by_zero(op: &str) -> ExecutionError {
+    ExecutionError::UserPanic {
+        message: format!("division by zero in {}", op),
+    }
+}
+
+/// `a / b`, rounding toward zero for integers. Integers fail on a zero `b`,
+/// floats follow IEEE 754.
+fn nif_div<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    num_op!(
+        &args[0],
+        &args[1],
+        |n1, n2| if *n2 == 0 { Err(division_by_zero("/")) } else { checked("/", n1.checked_div(*n2)) },
+        Ok(Value::F64(n1 / n2))
+    )
+}
+
+/// `rem(a, b)`, the remainder of `a / b`, with the sign of `a`.
+fn nif_rem<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    num_op!(
+        &args[0],
+        &args[1],
+        |n1, n2| if *n2 == 0 { Err(division_by_zero("rem")) } else { checked("rem", n1.checked_rem(*n2)) },
+        Ok(Value::F64(n1 % n2))
+    )
+}
+
+/// `neg(n)`, fails for the minimum of a signed kind and for any unsigned `n` but 0.
+fn nif_neg<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    num_op!(&args[0], |n1| checked("neg", n1.checked_neg()), Ok(Value::F64(-n1)))
+}
+
+fn nif_wrapping_add<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    int_op!(&args[0], &args[1], |n1, n2| Ok(n1.wrapping_add(*n2).into()))
+}
+
+fn nif_wrapping_sub<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    int_op!(&args[0], &args[1], |n1, n2| Ok(n1.wrapping_sub(*n2).into()))
+}
+
+fn nif_wrapping_mul<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    int_op!(&args[0], &args[1], |n1, n2| Ok(n1.wrapping_mul(*n2).into()))
+}
+
+fn nif_wrapping_neg<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    int_op!(&args[0], |n1| Ok(n1.wrapping_neg().into()))
+}
+
+fn nif_saturating_add<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    int_op!(&args[0], &args[1], |n1, n2| Ok(n1.saturating_add(*n2).into()))
+}
+
+fn nif_saturating_sub<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    int_op!(&args[0], &args[1], |n1, n2| Ok(n1.saturating_sub(*n2).into()))
+}
+
+fn nif_saturating_mul<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    int_op!(&args[0], &args[1], |n1, n2| Ok(n1.saturating_mul(*n2).into()))
+}
+
+/// Like `num_op`, for the ordered kinds: numbers, strings and pids.
+macro_rules! cmp_op {
+    ($a:expr, $b:expr, |$x:ident, $y:ident| $e:expr) => {
+        match ($a, $b) {
+            (Value::Str($x), Value::Str($y)) => $e,
+            (Value::Str(_), b) => Err(ExecutionError::ValueKindUnexpected {
+                value_expected: STR_KIND,
+                value_got: b.descriptor(),
+            }),
+            (Value::Pid($x), Value::Pid($y)) => $e,
+            (Value::Pid(_), b) => Err(ExecutionError::ValueKindUnexpected {
+                value_expected: PID_KIND,
+                value_got: b.descriptor(),
+            }),
+            (a, b) => num_op!(a, b, |$x, $y| $e, $e),
+        }
+    };
+}
+
+/// Whether `a` and `b` are equal. Every kind has equality, lists compare
+/// item by item, but both values must be of the same kind.
+fn equal(a: &Value, b: &Value) -> Result<bool, ExecutionError> {
+    if a.descriptor() != b.descriptor() {
+        return Err(ExecutionError::ValueKindUnexpected {
+            value_expected: a.descriptor(),
+            value_got: b.descriptor(),
+        });
+    }
+    Ok(a == b)
+}
+
+fn nif_eq<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    Ok(Value::Bool(equal(&args[0], &args[1])?))
+}
+
+fn nif_le<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    cmp_op!(&args[0], &args[1], |n1, n2| Ok(Value::Bool(n1 <= n2)))
+}
+
+fn nif_ne<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    Ok(Value::Bool(!equal(&args[0], &args[1])?))
+}
+
+fn nif_lt<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    cmp_op!(&args[0], &args[1], |n1, n2| Ok(Value::Bool(n1 < n2)))
+}
+
+fn nif_gt<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    cmp_op!(&args[0], &args[1], |n1, n2| Ok(Value::Bool(n1 > n2)))
+}
+
+fn nif_ge<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    cmp_op!(&args[0], &args[1], |n1, n2| Ok(Value::Bool(n1 >= n2)))
+}
+
+/// Adds a NIF applying a `libm` function to floats.
+macro_rules! float_fn {
+    ($name:ident, $f:path) => {
+        fn $name<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+            Ok(Value::F64($f(args[0].float()?)))
+        }
+    };
+}
+
+float_fn!(nif_log, libm::log);
+float_fn!(nif_exp, libm::exp);
+float_fn!(nif_sqrt, libm::sqrt);
+float_fn!(nif_floor, libm::floor);
+float_fn!(nif_ceil, libm::ceil);
+float_fn!(nif_round, libm::round);
+float_fn!(nif_trunc, libm::trunc);
+float_fn!(nif_abs, libm::fabs);
+float_fn!(nif_sin, libm::sin);
+float_fn!(nif_cos, libm::cos);
+float_fn!(nif_tan, libm::tan);
+
+fn nif_pow<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    Ok(Value::F64(libm::pow(args[0].float()?, args[1].float()?)))
+}
+
+/// `to_float(n)`, the float nearest to an integer of any kind.
+fn nif_to_float<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    int_op!(&args[0], |n1| Ok(Value::F64(*n1 as f64)))
+}
+
+/// `to_int(x)`, the `i64` of a float rounded towards zero. Fails for NaN and
+/// out of range floats rather than saturating.
+fn nif_to_int<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    let x = libm::trunc(args[0].float()?);
+    // i64::MAX rounds up to 2^63 as a float, hence the strict bound
+    if x >= i64::MIN as f64 && x < i64::MAX as f64 {
+        Ok(Value::I64(x as i64))
+    } else {
+        Err(ExecutionError::UserPanic {
+            message: format!("{} does not fit in an i64", x),
+        })
+    }
+}
+
+/// A non negative `i64`, as string and list positions are written.
+fn index_arg(v: &Value) -> Result<usize, ExecutionError> {
+    match v {
+        Value::I64(n) => usize::try_from(*n).map_err(|_| ExecutionError::UserPanic {
+            message: format!("negative index {}", n),
+        }),
+        _ => Err(ExecutionError::ValueKindUnexpected {
+            value_expected: I64_KIND,
+            value_got: v.descriptor(),
+        }),
+    }
+}
+
+fn nif_concat<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    let (s1, s2) = (args[0].str()?, args[1].str()?);
+    let mut s = String::with_capacity(s1.len() + s2.len());
+    s.push_str(s1);
+    s.push_str(s2);
+    Ok(Value::Str(s.into()))
+}
+
+/// `len(s)`, the number of characters of a string or elements of a list.
+fn nif_len<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    match &args[0] {
+        Value::Str(s) => Ok(Value::I64(s.chars().count() as i64)),
+        Value::List(list) => Ok(Value::I64(list.len() as i64)),
+        v => Err(ExecutionError::ValueKindUnexpected {
+            value_expected: STR_KIND,
+            value_got: v.descriptor(),
+        }),
+    }
+}
+
+/// `slice(s, start, end)`, the characters of `s` from `start` up to `end`.
+fn nif_slice<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    let s = args[0].str()?;
+    let (start, end) = (index_arg(&args[1])?, index_arg(&args[2])?);
+    // byte offsets of every character, and of the end of the string
+    let mut offsets = s.char_indices().map(|(i, _)| i).chain(core::iter::once(s.len()));
+    let out_of_bounds = || ExecutionError::UserPanic {
+        message: format!("slice {}..{} is out of bounds of {:?}", start, end, s),
+    };
+    if start > end {
+        return Err(out_of_bounds());
+    }
+    let from = offsets.nth(start).ok_or_else(out_of_bounds)?;
+    let to = if end == start { from } else { offsets.nth(end - start - 1).ok_or_else(out_of_bounds)? };
+    Ok(Value::Str(s[from..to].into()))
+}
+
+fn nif_to_string<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    Ok(Value::Str(args[0].to_string().into()))
+}
+
+fn nif_parse_int<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    let s = args[0].str()?;
+    s.trim().parse().map(Value::I64).map_err(|_| ExecutionError::UserPanic {
+        message: format!("{:?} is not an integer", s),
+    })
+}
+
+fn nif_parse_float<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    let s = args[0].str()?;
+    s.trim().parse().map(Value::F64).map_err(|_| ExecutionError::UserPanic {
+        message: format!("{:?} is not a number", s),
+    })
+}
+
+/// `format(template, v)`, `template` with its first `{}` replaced by `v`.
+fn nif_format<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    let template = args[0].str()?;
+    match template.split_once("{}") {
+        Some((before, after)) => Ok(Value::Str(format!("{}{}{}", before, args[1], after).into())),
+        None => Err(ExecutionError::UserPanic {
+            message: format!("{:?} has no {{}} to format {} into", template, args[1]),
+        }),
+    }
+}
+
+/// `split(s, sep)`, the list of the pieces of `s` between each `sep`.
+fn nif_split<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    let (s, sep) = (args[0].str()?, args[1].str()?);
+    if sep.is_empty() {
+        return Err(ExecutionError::UserPanic {
+            message: format!("cannot split {:?} on an empty separator", s),
+        });
+    }
+    Ok(Value::List(s.split(&**sep).map(|piece| Value::Str(piece.into())).collect()))
+}
+
+/// The sequence constructor, `[a, b]` calls it with every element.
+fn nif_list<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    Ok(Value::List(args.iter().cloned().collect()))
+}
+
+fn out_of_bounds(list: &List<Value>, index: usize) -> ExecutionError {
+    ExecutionError::UserPanic {
+        message: format!("index {} is out of bounds of a list of {}", index, list.len()),
+    }
+}
+
+/// `get(l, i)`, what `l[i]` lowers to.
+fn nif_get<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    let (list, index) = (args[0].list()?, index_arg(&args[1])?);
+    list.get(index).cloned().ok_or_else(|| out_of_bounds(list, index))
+}
+
+/// `set(l, i, v)`, what `l[i] = v` lowers to: a copy of `l` with `v` at `i`,
+/// `l` itself never changes.
+fn nif_set<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    let (list, index) = (args[0].list()?, index_arg(&args[1])?);
+    list.set(index, args[2].clone()).map(Value::List).ok_or_else(|| out_of_bounds(list, index))
+}
+
+fn empty_list(op: &str) -> ExecutionError {
+    ExecutionError::UserPanic {
+        message: format!("{} of an empty list", op),
+    }
+}
+
+fn nif_head<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    args[0].list()?.head().cloned().ok_or_else(|| empty_list("head"))
+}
+
+fn nif_tail<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    args[0].list()?.tail().map(Value::List).ok_or_else(|| empty_list("tail"))
+}
+
+/// `cons(v, l)`, `l` with `v` in front.
+fn nif_cons<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    Ok(Value::List(args[1].list()?.cons(args[0].clone())))
+}
+
+fn nif_append<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    Ok(Value::List(args[0].list()?.append(args[1].list()?)))
+}
+
+fn nif_is_empty<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    Ok(Value::Bool(args[0].list()?.is_empty()))
+}
+
+// FNV-1a, which needs no random state
+struct Fnv(u64);
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+/// `hash(v)` of a pid, key, string, bool or integer, as a `u64`.
+fn nif_hash<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    let mut hasher = Fnv(0xcbf29ce484222325);
+    match &args[0] {
+        Value::Pid(pid) => pid.hash(&mut hasher),
+        Value::Key(key) => key.hash(&mut hasher),
+        Value::Str(s) => s.hash(&mut hasher),
+        Value::Bool(b) => b.hash(&mut hasher),
+        v => int_op!(v, |n| {
+            n.hash(&mut hasher);
+            Ok(())
+        })?,
+    }
+    Ok(Value::U64(hasher.finish()))
+}
+
+/// The id of `v` in the garrison of `context`, links and monitors do not
+/// reach other garrisons.
+fn local_pid(context: &ProcessContext, v: &Value) -> Result<ProcessId, ExecutionError> {
+    let pid = v.pid()?;
+    if context.garrison != Some(pid.garrison) {
+        return Err(ExecutionError::UserPanic {
+            message: format!("{} runs in another garrison", pid),
+        });
+    }
+    Ok(pid.local)
+}
+
+/// The pid of `local` in the garrison running this process.
+fn pid_value(context: &ProcessContext, local: ProcessId) -> Result<Value, ExecutionError> {
+    match context.garrison {
+        Some(garrison) => Ok(Value::Pid(Pid { garrison, local })),
+        None => Err(ExecutionError::UserPanic {
+            message: "process is not running in a garrison".to_string(),
+        }),
+    }
+}
+
+fn bool_arg(v: &Value) -> Result<bool, ExecutionError> {
+    match v {
+        Value::Bool(b) => Ok(*b),
+        _ => Err(ExecutionError::ValueKindUnexpected {
+            value_expected: BOOL_KIND,
+            value_got: v.descriptor(),
+        }),
+    }
+}
+
+fn nif_not<A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    Ok(Value::Bool(!bool_arg(&args[0])?))
+}
+
+/// `trap_exit(flag)`, returns the previous flag.
+fn nif_trap_exit(em: &mut ExecutionMachine) -> Result<Value, ExecutionError> {
+    let (_, args) = em.stack.get_call_and_args(em.current_arity);
+    let flag = bool_arg(&args[0])?;
+    let previous = core::mem::replace(&mut em.userdata.context.trap_exit, flag);
+    Ok(Value::Bool(previous))
+}
+
+fn nif_link(em: &mut ExecutionMachine) -> Result<Value, ExecutionError> {
+    let (_, args) = em.stack.get_call_and_args(em.current_arity);
+    let pid = local_pid(&em.userdata.context, &args[0])?;
+    em.userdata.context.requests.push(Request::Link(pid));
+    Ok(Value::Bool(true))
+}
+
+fn nif_unlink(em: &mut ExecutionMachine) -> Result<Value, ExecutionError> {
+    let (_, args) = em.stack.get_call_and_args(em.current_arity);
+    let pid = local_pid(&em.userdata.context, &args[0])?;
+    em.userdata.context.requests.push(Request::Unlink(pid));
+    Ok(Value::Bool(true))
+}
+
+/// `monitor(pid)`, returns the monitor reference.
+fn nif_monitor(em: &mut ExecutionMachine) -> Result<Value, ExecutionError> {
+    let (_, args) = em.stack.get_call_and_args(em.current_arity);
+    let pid = local_pid(&em.userdata.context, &args[0])?;
+    let monitor = em.userdata.context.monitor(pid);
+    Ok(Value::U64(monitor.0))
+}
+
+fn nif_demonitor(em: &mut ExecutionMachine) -> Result<Value, ExecutionError> {
+    let (_, args) = em.stack.get_call_and_args(em.current_arity);
+    let pid = local_pid(&em.userdata.context, &args[0])?;
+    em.userdata.context.requests.push(Request::Demonitor(pid));
+    Ok(Value::Bool(true))
+}
+
+/// `link_auth(who)`, allows `who` to link to this process. Once a process
+/// has an authorisation list only the keys on it may link.
+fn nif_link_auth(em: &mut ExecutionMachine) -> Result<Value, ExecutionError> {
+    let (_, args) = em.stack.get_call_and_args(em.current_arity);
+    let who = args[0].key()?;
+    match &mut em.userdata.context.link_auth {
+        Some(list) => list.push(who),
+        list => *list = Some(vec![who]),
+    }
+    Ok(Value::Bool(true))
+}
+
+/// `link_auth_list(whos)`, allows every key of the list `whos` to link to
+/// this process, see `link_auth`. Returns a list telling for each key
+/// whether it was not allowed already.
+fn nif_link_auth_list(em: &mut ExecutionMachine) -> Result<Value, ExecutionError> {
+    let (_, args) = em.stack.get_call_and_args(em.current_arity);
+    let whos = args[0].list()?.iter().map(|who| who.key()).collect::<Result<Vec<_>, _>>()?;
+    let list = em.userdata.context.link_auth.get_or_insert_with(Vec::new);
+    let added = whos
+        .into_iter()
+        .map(|who| {
+            let new = !list.contains(&who);
+            if new {
+                list.push(who);
+            }
+            Value::Bool(new)
+        })
+        .collect();
+    Ok(Value::List(added))
+}
+
+/// `install_brigadier(garrison)`, asks the host of this general to install a
+/// brigadier into `garrison`.
+fn nif_install_brigadier(em: &mut ExecutionMachine) -> Result<Value, ExecutionError> {
+    let (_, args) = em.stack.get_call_and_args(em.current_arity);
+    let garrison = args[0].key()?;
+    em.userdata.context.requests.push(Request::InstallBrigadier(garrison));
+    Ok(Value::Bool(true))
+}
+
+/// The local ids of the pids in the list `v`.
+fn local_pids(context: &ProcessContext, v: &Value) -> Result<Vec<ProcessId>, ExecutionError> {
+    v.list()?.iter().map(|pid| local_pid(context, pid)).collect()
+}
+
+/// `supervise(strategy, children)`, makes this process the supervisor of a
+/// group of `children` restarted by the strategy `"series"`, `"parallel"` or
+/// `"standby"`.
+fn nif_supervise(em: &mut ExecutionMachine) -> Result<Value, ExecutionError> {
+    let (_, args) = em.stack.get_call_and_args(em.current_arity);
+    let strategy = match &**args[0].str()? {
+        "series" => Strategy::Series,
+        "parallel" => Strategy::Parallel,
+        "standby" => Strategy::Standby,
+        other => {
+            return Err(ExecutionError::UserPanic {
+                message: format!("unknown supervision strategy {:?}", other),
+            })
+        }
+    };
+    let children = local_pids(&em.userdata.context, &args[1])?;
+    em.userdata.context.requests.push(Request::Supervise(strategy, children));
+    Ok(Value::Bool(true))
+}
+
+/// `supervise_k_of_n(k, children)`, like `supervise` for a group that stays
+/// healthy while `k` of its children survive.
+fn nif_supervise_k_of_n(em: &mut ExecutionMachine) -> Result<Value, ExecutionError> {
+    let (_, args) = em.stack.get_call_and_args(em.current_arity);
+    let k = index_arg(&args[0])?;
+    let children = local_pids(&em.userdata.context, &args[1])?;
+    em.userdata.context.requests.push(Request::Supervise(Strategy::KOfN(k), children));
+    Ok(Value::Bool(true))
+}
+
+/// `[kind, from, reason, ref]`, the list `receive_signal` returns for
+/// `signal`: `"exit"` or `"down"`, the pid of the process that exited, why it
+/// exited, see [`ExitSignal::name`](crate::ExitSignal::name), and for a down
+/// message the reference `monitor` returned, `()` for an exit.
+fn signal_value(context: &ProcessContext, signal: Signal) -> Result<Value, ExecutionError> {
+    let (kind, from, reason, monitor) = match signal {
+        Signal::Exit { from, reason } => ("exit", from, reason, Value::Unit),
+        Signal::Down { monitor, from, reason } => ("down", from, reason, Value::U64(monitor.0)),
+    };
+    let fields = [Value::Str(kind.into()), pid_value(context, from)?, Value::Str(reason.name().into()), monitor];
+    Ok(Value::List(fields.into_iter().collect()))
+}
+
+/// `receive_signal()`, waits for an exit or down message, see
+/// [`signal_value`].
+fn nif_receive_signal(em: &mut ExecutionMachine) -> Result<Value, ExecutionError> {
+    match em.userdata.context.mailbox.pop_front() {
+        Some(signal) => signal_value(&em.userdata.context, signal),
+        None => {
+            em.userdata.suspend(Suspension::Receive);
+            Err(ExecutionError::UserPanic {
+                message: "blocked on an empty mailbox".to_string(),
+            })
+        }
+    }
+}
+
+/// `self()`, the pid of this process.
+fn nif_self(em: &mut ExecutionMachine) -> Result<Value, ExecutionError> {
+    pid_value(&em.userdata.context, em.userdata.context.pid)
+}
+
+/// `garrison()`, the key of the garrison running this process.
+fn nif_garrison(em: &mut ExecutionMachine) -> Result<Value, ExecutionError> {
+    match em.userdata.context.garrison {
+        Some(key) => Ok(Value::Key(key)),
+        None => Err(ExecutionError::UserPanic {
+            message: "process is not running in a garrison".to_string(),
+        }),
+    }
+}
+
+/// `spawn(fun, args, garrison)`, starts `fun` with the list `args` as its
+/// arguments as a new process and returns its pid. `spawn_thread` is the same
+/// NIF under the name threads lower to, open to every tier. The garrison serves the
+/// request between two steps, after which the call runs again and picks up
+/// the result. Only the garrison running the caller is reachable, spawning
+/// into another is an error.
+fn nif_spawn(em: &mut ExecutionMachine) -> Result<Value, ExecutionError> {
+    match em.userdata.spawn.take() {
+        Some(Spawn::Done(Ok(local))) => pid_value(&em.userdata.context, local),
+        Some(Spawn::Done(Err(e))) => Err(ExecutionError::UserPanic { message: e.to_string() }),
+        _ => {
+            let (_, args) = em.stack.get_call_and_args(em.current_arity);
+            let fun = args[0].fun().ok_or(ExecutionError::ValueKindUnexpected {
+                value_expected: FUN_KIND,
+                value_got: args[0].descriptor(),
+            })?;
+            let fun_args = args[1].list()?.iter().cloned().collect();
+            let garrison = args[2].key()?;
+            if em.userdata.context.garrison != Some(garrison) {
+                return Err(ExecutionError::UserPanic {
+                    message: SpawnError::OtherGarrison(garrison).to_string(),
+                });
+            }
+            em.userdata.spawn = Some(Spawn::Requested { fun, args: fun_args, garrison });
+            em.userdata.suspend(Suspension::Spawn);
+            Err(ExecutionError::UserPanic {
+                message: "waiting for the garrison to spawn".to_string(),
+            })
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum Literal {
+    Bool(bool),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    /// The bits of an `f64`, literals have to be `Eq` and `Hash`.
+    Float(u64),
+    Str(Rc<str>),
+}
+
+pub fn literal_to_value(lit: &Literal) -> Value {
+    match lit {
+        Literal::Bool(b) => Value::Bool(*b),
+        Literal::I8(n) => Value::I8(*n),
+        Literal::I16(n) => Value::I16(*n),
+        Literal::I32(n) => Value::I32(*n),
+        Literal::I64(n) => Value::I64(*n),
+        Literal::U8(n) => Value::U8(*n),
+        Literal::U16(n) => Value::U16(*n),
+        Literal::U32(n) => Value::U32(*n),
+        Literal::U64(n) => Value::U64(*n),
+        Literal::Float(bits) => Value::F64(f64::from_bits(*bits)),
+        Literal::Str(s) => Value::Str(s.clone()),
+    }
+}
+
+/// The integer `text` stands for: an optional `-`, digits with an optional
+/// radix prefix, `0x`, `0o` or `0b`, followed by an optional type suffix such
+/// as `u8`. Without a suffix the integer is a [`ValueInt`]. [`None`] when the
+/// number does not fit the type or the suffix names none.
+fn int_literal(text: &str) -> Option<Literal> {
+    // the sign is checked with the digits, so `-128i8` fits where `128i8` does not
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(text) => ("-", text),
+        None => ("", text),
+    };
+    let (radix, rest) = match text.get(..2) {
+        Some("0x") => (16, &text[2..]),
+        Some("0o") => (8, &text[2..]),
+        Some("0b") => (2, &text[2..]),
+        _ => (10, text),
+    };
+    let end = rest.find(|c: char| !c.is_digit(radix)).unwrap_or(rest.len());
+    let (digits, suffix) = rest.split_at(end);
+    let digits = format!("{}{}", sign, digits);
+    macro_rules! parse {
+        ($t:ty, $variant:ident) => {
+            <$t>::from_str_radix(&digits, radix).ok().map(Literal::$variant)
+        };
+    }
+    match suffix {
+        "" => parse!(ValueInt, I64),
+        "i8" => parse!(i8, I8),
+        "i16" => parse!(i16, I16),
+        "i32" => parse!(i32, I32),
+        "i64" => parse!(i64, I64),
+        "u8" => parse!(u8, U8),
+        "u16" => parse!(u16, U16),
+        "u32" => parse!(u32, U32),
+        "u64" => parse!(u64, U64),
+        _ => None,
+    }
+}
+
+// support every werbolg core literal but bytes, numbers that do not fit are
+// errors at the span of the literal
+pub fn literal_mapper(span: Span, lit: IrLiteral) -> Result<Literal, CompilationError> {
+    match lit {
+        IrLiteral::Bool(b) => {
+            let b = b.as_ref() == "true";
+            Ok(Literal::Bool(b))
+        }
+        IrLiteral::Number(s) => match int_literal(s.as_ref()) {
+            Some(literal) => Ok(literal),
+            None => Err(CompilationError::LiteralNotSupported(span, IrLiteral::Number(s))),
+        },
+        IrLiteral::String(s) => {
+            let s: &str = s.as_ref();
+            Ok(Literal::Str(Rc::from(s)))
+        }
+        IrLiteral::Decimal(s) => match s.as_ref().parse::<f64>() {
+            Ok(v) if v.is_finite() => Ok(Literal::Float(v.to_bits())),
+            _ => Err(CompilationError::LiteralNotSupported(span, IrLiteral::Decimal(s))),
+        },
+        IrLiteral::Bytes(_) => Err(CompilationError::LiteralNotSupported(span, lit)),
+    }
+}
+
+/// The NIF that builds lists, for `CompilationParams::sequence_constructor`.
+/// It is the first one [`create_env`] adds.
+pub fn sequence_constructor() -> NifId {
+    NifId::from_collection_len(0)
+}
+
+/// The NIFs `T` is allowed.
+pub fn create_env<T: Tier>() -> Environment {
+    macro_rules! add_raw_nif {
+        ($env:ident, $i:literal, $arity:literal, $e:expr) => {
+            if tier::allows(T::KEY_TIER, $i) {
+                let nif = NIFCall::Raw($e).info($i, CallArity::try_from($arity as usize).unwrap());
+                let path = AbsPath::new(&Namespace::root(), &Ident::from($i));
+                let _ = $env.add_nif(&path, nif);
+            }
+        };
+    }
+    macro_rules! add_pure_nif {
+        ($env:ident, $i:literal, $arity:literal, $e:expr) => {
+            if tier::allows(T::KEY_TIER, $i) {
+                let nif = NIFCall::Pure($e).info($i, CallArity::try_from($arity as usize).unwrap());
+                let path = AbsPath::new(&Namespace::root(), &Ident::from($i));
+                let _ = $env.add_nif(&path, nif);
+            }
+        };
+    }
+    let mut env = Environment::new();
+    add_pure_nif!(env, "list", 0, nif_list);
+    add_raw_nif!(env, "unbound", 0, nif_unbound);
+    add_raw_nif!(env, "read", 1, nif_read);
+    add_raw_nif!(env, "bind", 2, nif_bind);
+    add_pure_nif!(env, "unit", 0, nif_unit);
+    add_pure_nif!(env, "+", 2, nif_plus);
+    add_pure_nif!(env, "-", 2, nif_sub);
+    add_pure_nif!(env, "*", 2, nif_mul);
+    add_pure_nif!(env, "/", 2, nif_div);
+    add_pure_nif!(env, "rem", 2, nif_rem);
+    add_pure_nif!(env, "==", 2, nif_eq);
+    add_pure_nif!(env, "!=", 2, nif_ne);
+    add_pure_nif!(env, "<", 2, nif_lt);
+    add_pure_nif!(env, "<=", 2, nif_le);
+    add_pure_nif!(env, ">", 2, nif_gt);
+    add_pure_nif!(env, ">=", 2, nif_ge);
+    add_pure_nif!(env, "not", 1, nif_not);
+    add_pure_nif!(env, "hash", 1, nif_hash);
+    add_pure_nif!(env, "neg", 1, nif_neg);
+    add_pure_nif!(env, "wrapping_add", 2, nif_wrapping_add);
+    add_pure_nif!(env, "wrapping_sub", 2, nif_wrapping_sub);
+    add_pure_nif!(env, "wrapping_mul", 2, nif_wrapping_mul);
+    add_pure_nif!(env, "wrapping_neg", 1, nif_wrapping_neg);
+    add_pure_nif!(env, "saturating_add", 2, nif_saturating_add);
+    add_pure_nif!(env, "saturating_sub", 2, nif_saturating_sub);
+    add_pure_nif!(env, "saturating_mul", 2, nif_saturating_mul);
+    add_pure_nif!(env, "log", 1, nif_log);
+    add_pure_nif!(env, "exp", 1, nif_exp);
+    add_pure_nif!(env, "sqrt", 1, nif_sqrt);
+    add_pure_nif!(env, "floor", 1, nif_floor);
+    add_pure_nif!(env, "ceil", 1, nif_ceil);
+    add_pure_nif!(env, "round", 1, nif_round);
+    add_pure_nif!(env, "trunc", 1, nif_trunc);
+    add_pure_nif!(env, "abs", 1, nif_abs);
+    add_pure_nif!(env, "sin", 1, nif_sin);
+    add_pure_nif!(env, "cos", 1, nif_cos);
+    add_pure_nif!(env, "tan", 1, nif_tan);
+    add_pure_nif!(env, "pow", 2, nif_pow);
+    add_pure_nif!(env, "to_float", 1, nif_to_float);
+    add_pure_nif!(env, "to_int", 1, nif_to_int);
+    add_pure_nif!(env, "concat", 2, nif_concat);
+    add_pure_nif!(env, "len", 1, nif_len);
+    add_pure_nif!(env, "slice", 3, nif_slice);
+    add_pure_nif!(env, "to_string", 1, nif_to_string);
+    add_pure_nif!(env, "parse_int", 1, nif_parse_int);
+    add_pure_nif!(env, "parse_float", 1, nif_parse_float);
+    add_pure_nif!(env, "format", 2, nif_format);
+    add_pure_nif!(env, "split", 2, nif_split);
+    add_pure_nif!(env, "get", 2, nif_get);
+    add_pure_nif!(env, "set", 3, nif_set);
+    add_pure_nif!(env, "head", 1, nif_head);
+    add_pure_nif!(env, "tail", 1, nif_tail);
+    add_pure_nif!(env, "cons", 2, nif_cons);
+    add_pure_nif!(env, "append", 2, nif_append);
+    add_pure_nif!(env, "is_empty", 1, nif_is_empty);
+    add_raw_nif!(env, "trap_exit", 1, nif_trap_exit);
+    add_raw_nif!(env, "link_with", 1, nif_link);
+    add_raw_nif!(env, "delink", 1, nif_unlink);
+    add_raw_nif!(env, "monitor", 1, nif_monitor);
+    add_raw_nif!(env, "demonitor", 1, nif_demonitor);
+    add_raw_nif!(env, "link_auth", 1, nif_link_auth);
+    add_raw_nif!(env, "link_auth_list", 1, nif_link_auth_list);
+    add_raw_nif!(env, "supervise", 2, nif_supervise);
+    add_raw_nif!(env, "supervise_k_of_n", 2, nif_supervise_k_of_n);
+    add_raw_nif!(env, "receive_signal", 0, nif_receive_signal);
+    add_raw_nif!(env, "self", 0, nif_self);
+    add_raw_nif!(env, "garrison", 0, nif_garrison);
+    add_raw_nif!(env, "spawn", 3, nif_spawn);
+    add_raw_nif!(env, "spawn_thread", 3, nif_spawn);
+    add_raw_nif!(env, "install_brigadier", 1, nif_install_brigadier);
+    env
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(text: &str) -> Result<Literal, CompilationError> {
+        literal_mapper(3..3 + text.len(), IrLiteral::Number(text.into()))
+    }
+
+    fn rejected(result: Result<Literal, CompilationError>, text: &str) -> bool {
+        matches!(result, Err(CompilationError::LiteralNotSupported(span, _)) if span == (3..3 + text.len()))
+    }
+
+    /// What the pure NIF `nif` returns for `args`, the message of the panic
+    /// when it fails.
+    fn call(nif: fn(&crate::allocator::Allocator, &[Value]) -> Result<Value, ExecutionError>, args: &[Value]) -> Result<Value, String> {
+        nif(&crate::allocator::Allocator, args).map_err(|e| match e {
+            ExecutionError::UserPanic { message } => message,
+            e => format!("{:?}", e),
+        })
+    }
+
+    fn list(items: &[Value]) -> Value {
+        Value::List(items.iter().cloned().collect())
+    }
+
+    fn text(s: &str) -> Value {
+        Value::Str(s.into())
+    }
+
+    #[test]
+    fn arithmetic_is_checked() {
+        assert_eq!(call(nif_sub, &[Value::I64(1), Value::I64(2)]), Ok(Value::I64(-1)));
+        assert_eq!(call(nif_div, &[Value::I64(-7), Value::I64(2)]), Ok(Value::I64(-3)));
+        assert_eq!(call(nif_rem, &[Value::I64(-7), Value::I64(2)]), Ok(Value::I64(-1)));
+        assert_eq!(call(nif_mul, &[Value::U8(16), Value::U8(15)]), Ok(Value::U8(240)));
+        assert_eq!(call(nif_sub, &[Value::I64(i64::MIN), Value::I64(1)]), Err("integer overflow in -".to_string()));
+        assert_eq!(call(nif_plus, &[Value::U8(255), Value::U8(1)]), Err("integer overflow in +".to_string()));
+        assert_eq!(call(nif_neg, &[Value::I64(i64::MIN)]), Err("integer overflow in neg".to_string()));
+        assert_eq!(call(nif_div, &[Value::I64(1), Value::I64(0)]), Err("division by zero in /".to_string()));
+        assert_eq!(call(nif_rem, &[Value::I64(1), Value::I64(0)]), Err("division by zero in rem".to_string()));
+        // wrapping and saturating instead of failing, on request
+        assert_eq!(call(nif_wrapping_add, &[Value::U8(255), Value::U8(1)]), Ok(Value::U8(0)));
+        assert_eq!(call(nif_saturating_sub, &[Value::I64(i64::MIN), Value::I64(1)]), Ok(Value::I64(i64::MIN)));
+        // no implicit conversion between kinds
+        assert!(call(nif_plus, &[Value::I64(1), Value::U8(1)]).is_err());
+        assert!(call(nif_plus, &[Value::F64(1.0), Value::I64(1)]).is_err());
+    }
+
+    #[test]
+    fn floats_convert_explicitly() {
+        assert_eq!(call(nif_div, &[Value::F64(7.0), Value::F64(2.0)]), Ok(Value::F64(3.5)));
+        assert_eq!(call(nif_div, &[Value::F64(1.0), Value::F64(0.0)]), Ok(Value::F64(f64::INFINITY)));
+        assert_eq!(call(nif_sqrt, &[Value::F64(16.0)]), Ok(Value::F64(4.0)));
+        assert_eq!(call(nif_to_float, &[Value::U8(3)]), Ok(Value::F64(3.0)));
+        assert_eq!(call(nif_to_int, &[Value::F64(-3.5)]), Ok(Value::I64(-3)));
+        assert_eq!(call(nif_to_int, &[Value::F64(1e19)]), Err("10000000000000000000 does not fit in an i64".to_string()));
+        assert!(call(nif_to_int, &[Value::F64(f64::NAN)]).is_err());
+        assert!(call(nif_sqrt, &[Value::I64(16)]).is_err());
+    }
+
+    #[test]
+    fn strings_are_values() {
+        assert_eq!(call(nif_concat, &[text("corporal"), text("1")]), Ok(text("corporal1")));
+        assert_eq!(call(nif_format, &[text("corporal{}!"), Value::I64(1)]), Ok(text("corporal1!")));
+        assert_eq!(call(nif_format, &[text("corporal"), Value::I64(1)]), Err("\"corporal\" has no {} to format 1 into".to_string()));
+        assert_eq!(call(nif_len, &[text("héllo")]), Ok(Value::I64(5)));
+        assert_eq!(call(nif_slice, &[text("héllo"), Value::I64(1), Value::I64(3)]), Ok(text("él")));
+        assert_eq!(call(nif_slice, &[text("héllo"), Value::I64(2), Value::I64(2)]), Ok(text("")));
+        assert_eq!(
+            call(nif_slice, &[text("abc"), Value::I64(1), Value::I64(4)]),
+            Err("slice 1..4 is out of bounds of \"abc\"".to_string())
+        );
+        assert_eq!(call(nif_to_string, &[Value::I64(-8)]), Ok(text("-8")));
+        assert_eq!(call(nif_parse_int, &[text(" 8 ")]), Ok(Value::I64(8)));
+        assert_eq!(call(nif_parse_int, &[text("8.0")]), Err("\"8.0\" is not an integer".to_string()));
+        assert_eq!(call(nif_parse_float, &[text("0.5")]), Ok(Value::F64(0.5)));
+        assert_eq!(call(nif_split, &[text("a,b,"), text(",")]), Ok(list(&[text("a"), text("b"), text("")])));
+        assert!(call(nif_split, &[text("a,b"), text("")]).is_err());
+    }
+
+    #[test]
+    fn lists_are_values() {
+        let xs = list(&[Value::I64(1), Value::I64(2), Value::I64(3)]);
+        assert_eq!(call(nif_list, &[Value::I64(1), Value::I64(2), Value::I64(3)]), Ok(xs.clone()));
+        assert_eq!(call(nif_get, &[xs.clone(), Value::I64(2)]), Ok(Value::I64(3)));
+        assert_eq!(call(nif_get, &[xs.clone(), Value::I64(3)]), Err("index 3 is out of bounds of a list of 3".to_string()));
+        assert_eq!(call(nif_get, &[xs.clone(), Value::I64(-1)]), Err("negative index -1".to_string()));
+        assert_eq!(
+            call(nif_set, &[xs.clone(), Value::I64(0), Value::I64(0)]),
+            Ok(list(&[Value::I64(0), Value::I64(2), Value::I64(3)]))
+        );
+        assert_eq!(call(nif_len, &[xs.clone()]), Ok(Value::I64(3)));
+        assert_eq!(call(nif_head, &[xs.clone()]), Ok(Value::I64(1)));
+        assert_eq!(call(nif_tail, &[xs.clone()]), Ok(list(&[Value::I64(2), Value::I64(3)])));
+        assert_eq!(call(nif_tail, &[list(&[])]), Err("tail of an empty list".to_string()));
+        assert_eq!(call(nif_cons, &[Value::I64(0), list(&[Value::I64(1)])]), Ok(list(&[Value::I64(0), Value::I64(1)])));
+        assert_eq!(
+            call(nif_append, &[list(&[Value::I64(1)]), list(&[text("b")])]),
+            Ok(list(&[Value::I64(1), text("b")]))
+        );
+        assert_eq!(call(nif_is_empty, &[list(&[])]), Ok(Value::Bool(true)));
+        // every operation returned a new list
+        assert_eq!(xs, list(&[Value::I64(1), Value::I64(2), Value::I64(3)]));
+    }
+
+    #[test]
+    fn every_kind_has_equality() {
+        use crate::allocator::Allocator;
+        use crate::PublicKey;
+
+        // the boolean a comparison NIF returns, `None` when it fails
+        let compare = |nif: fn(&Allocator, &[Value]) -> Result<Value, ExecutionError>, a: Value, b: Value| {
+            match nif(&Allocator, &[a, b]) {
+                Ok(Value::Bool(result)) => Some(result),
+                _ => None,
+            }
+        };
+        let key = |byte| Value::Key(PublicKey::from_bytes([byte; 32]));
+        let list = |items: &[Value]| Value::List(items.iter().cloned().collect());
+        assert_eq!(compare(nif_eq, Value::Bool(true), Value::Bool(true)), Some(true));
+        assert_eq!(compare(nif_eq, Value::Bool(true), Value::Bool(false)), Some(false));
+        assert_eq!(compare(nif_eq, key(7), key(7)), Some(true));
+        assert_eq!(compare(nif_eq, key(7), key(8)), Some(false));
+        assert_eq!(compare(nif_ne, key(7), key(8)), Some(true));
+        assert_eq!(compare(nif_eq, Value::Unit, Value::Unit), Some(true));
+        assert_eq!(compare(nif_eq, list(&[Value::I64(1), key(7)]), list(&[Value::I64(1), key(7)])), Some(true));
+        assert_eq!(compare(nif_eq, list(&[Value::I64(1)]), list(&[Value::Str("1".into())])), Some(false));
+        assert_eq!(compare(nif_eq, Value::Bool(true), Value::I64(1)), None);
+
+        // only the ordered kinds compare
+        assert_eq!(compare(nif_lt, Value::Bool(false), Value::Bool(true)), None);
+        assert_eq!(compare(nif_lt, key(7), key(8)), None);
+        assert_eq!(compare(nif_lt, Value::Str("a".into()), Value::Str("b".into())), Some(true));
+    }
+
+    #[test]
+    fn signals_are_structured() {
+        use crate::process::{ExitSignal, MonitorRef};
+        use crate::PublicKey;
+
+        let garrison = PublicKey::from_bytes([7; 32]);
+        let context = ProcessContext { garrison: Some(garrison), ..Default::default() };
+        let from = ProcessId(3);
+        let pid = Value::Pid(Pid { garrison, local: from });
+        let fields = |value: Value| value.list().unwrap().iter().cloned().collect::<alloc::vec::Vec<_>>();
+        let reasons = [
+            (ExitSignal::Normal, "normal"),
+            (ExitSignal::Crashed, "crashed"),
+            (ExitSignal::Stopped, "stopped"),
+            (ExitSignal::NoProc, "noproc"),
+            (ExitSignal::Unauthorised, "unauthorised"),
+        ];
+        for (reason, name) in reasons {
+            let exit = signal_value(&context, Signal::Exit { from, reason }).unwrap();
+            assert_eq!(fields(exit), vec![Value::Str("exit".into()), pid.clone(), Value::Str(name.into()), Value::Unit]);
+            let down = signal_value(&context, Signal::Down { monitor: MonitorRef(5), from, reason }).unwrap();
+            assert_eq!(fields(down), vec![Value::Str("down".into()), pid.clone(), Value::Str(name.into()), Value::U64(5)]);
+        }
+        // pids only make sense within a garrison
+        let outside = ProcessContext::default();
+        assert!(signal_value(&outside, Signal::Exit { from, reason: ExitSignal::Normal }).is_err());
+    }
+
+    #[test]
+    fn integers_have_radixes_and_suffixes() {
+        assert!(matches!(number("42"), Ok(Literal::I64(42))));
+        assert!(matches!(number("0xff"), Ok(Literal::I64(255))));
+        assert!(matches!(number("0o17"), Ok(Literal::I64(15))));
+        assert!(matches!(number("0b101u8"), Ok(Literal::U8(5))));
+        assert!(matches!(number("0xffu8"), Ok(Literal::U8(255))));
+        assert!(matches!(number("7i32"), Ok(Literal::I32(7))));
+        assert!(matches!(number("18446744073709551615u64"), Ok(Literal::U64(u64::MAX))));
+    }
+
+    #[test]
+    fn negative_integers_reach_the_minimum() {
+        assert!(matches!(number("-128i8"), Ok(Literal::I8(i8::MIN))));
+        assert!(matches!(number("-32768i16"), Ok(Literal::I16(i16::MIN))));
+        assert!(matches!(number("-2147483648i32"), Ok(Literal::I32(i32::MIN))));
+        assert!(matches!(number("-9223372036854775808i64"), Ok(Literal::I64(i64::MIN))));
+        assert!(matches!(number("-9223372036854775808"), Ok(Literal::I64(i64::MIN))));
+        assert!(matches!(number("-0x80i8"), Ok(Literal::I8(i8::MIN))));
+        assert!(matches!(number("-7"), Ok(Literal::I64(-7))));
+        for text in ["-129i8", "-32769i16", "-9223372036854775809", "-1u8", "--1", "-"] {
+            assert!(rejected(number(text), text), "{} is accepted", text);
+        }
+    }
+
+    #[test]
+    fn numbers_that_do_not_fit_are_errors() {
+        for text in ["9223372036854775808", "256u8", "128i8", "0x1_0", "0b102", "0x", "1u7", "1f64"] {
+            assert!(rejected(number(text), text), "{} is accepted", text);
+        }
+        let huge = alloc::format!("1{}.0", "0".repeat(400));
+        assert!(rejected(literal_mapper(3..3 + huge.len(), IrLiteral::Decimal(huge.as_str().into())), &huge));
+    }
+}
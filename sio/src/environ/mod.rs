@@ -1,4 +0,0 @@
-pub mod general;
-pub mod brigadier;
-pub mod major;
-pub mod corporal;
@@ -0,0 +1,310 @@
+//! Host functions: Rust functions an embedder registers under a url, which
+//! modules bind with an FFI declaration `name :: url :: (params) -> ret;`.
+//!
+//! A host function is a type implementing [`HostFunction`], its arguments and
+//! result convert from and to sio values through [`FromValue`] and
+//! [`IntoValue`]. An argument of the wrong kind fails the call.
+//!
+//! Registering a host function returns its [`Signature`], the url with the
+//! sio types of its parameters and result. The embedder hands the
+//! signatures to the module constructors and the frontend checks every FFI
+//! declaration against them: the url must be registered, the parameters and
+//! the result must be of those types. `int` is the same type as `i64`,
+//! `float` the same as `f64`, and a host taking `any` takes every type.
+//!
+//! ```ignore
+//! struct Double;
+//!
+//! impl HostFunction for Double {
+//!     type Args = (i64,);
+//!     type Ret = i64;
+//!     fn call((n,): (i64,)) -> i64 {
+//!         n * 2
+//!     }
+//! }
+//!
+//! let mut env = sio::create_env::<sio::tier::Corporal>();
+//! let double = sio::ffi::add_host_function::<Double>(&mut env, "host::math::double").unwrap();
+//! // checks `double :: host::/math/double :: (n: int) -> int;`
+//! let corporal = sio_garrison::Corporal::with_host_functions(code, author, path, env, &[double]);
+//! ```
+use crate::key::PublicKey;
+use crate::list::List;
+use crate::process::Pid;
+use crate::value::{Value, BOOL_KIND, F64_KIND, I16_KIND, I32_KIND, I64_KIND, I8_KIND, U16_KIND, U32_KIND, U64_KIND, U8_KIND};
+use crate::Environment;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use werbolg_compile::CallArity;
+use werbolg_core::{AbsPath, Ident, Namespace};
+use werbolg_exec::{ExecutionError, NIFCall, Valuable, WAllocator};
+
+/// A Rust type a host function takes as argument.
+pub trait FromValue: Sized {
+    /// The sio type of the parameter.
+    const TYPE: &'static str;
+    fn from_value(v: &Value) -> Result<Self, ExecutionError>;
+}
+
+/// A Rust type a host function returns.
+pub trait IntoValue {
+    /// The sio type of the result.
+    const TYPE: &'static str;
+    fn into_value(self) -> Result<Value, ExecutionError>;
+}
+
+/// The arguments of a host function, a tuple of [`FromValue`]s.
+pub trait FromValues: Sized {
+    const ARITY: usize;
+    /// The sio types of the parameters, in order.
+    const TYPES: &'static [&'static str];
+    fn from_values(args: &[Value]) -> Result<Self, ExecutionError>;
+}
+
+/// A host function. It is a plain function of its arguments: it keeps no
+/// state and does not see the process calling it, anything it needs comes
+/// in through its arguments.
+pub trait HostFunction {
+    type Args: FromValues;
+    type Ret: IntoValue;
+    fn call(args: Self::Args) -> Self::Ret;
+}
+
+/// The url of a host function with the sio types it takes and returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub url: &'static str,
+    pub params: &'static [&'static str],
+    pub ret: &'static str,
+}
+
+impl Signature {
+    pub fn of<H: HostFunction>(url: &'static str) -> Self {
+        Signature { url, params: H::Args::TYPES, ret: H::Ret::TYPE }
+    }
+}
+
+/// Whether a value declared of type `declared` fits where the host expects
+/// `host`.
+pub fn type_matches(host: &str, declared: &str) -> bool {
+    let canonical = |name| match name {
+        "int" => "i64",
+        "float" => "f64",
+        name => name,
+    };
+    host == "any" || canonical(host) == canonical(declared)
+}
+
+/// Registers `H` under `url`, the `::` separated parts of the url FFI
+/// declarations name it by: `host::/math/double` is registered as
+/// `host::math::double`. Returns its signature, `None` when
+/// the url is already taken.
+pub fn add_host_function<H: HostFunction>(env: &mut Environment, url: &'static str) -> Option<Signature> {
+    let arity = CallArity::try_from(H::Args::ARITY).expect("host functions take few arguments");
+    let nif = NIFCall::Pure(host_nif::<H, _>).info(url, arity);
+    let path = AbsPath::new(&Namespace::root(), &Ident::from(url));
+    env.add_nif(&path, nif).ok().map(|_| Signature::of::<H>(url))
+}
+
+fn host_nif<H: HostFunction, A: WAllocator>(_: &A, args: &[Value]) -> Result<Value, ExecutionError> {
+    H::call(H::Args::from_values(args)?).into_value()
+}
+
+fn unexpected(expected: werbolg_exec::ValueKind, v: &Value) -> ExecutionError {
+    ExecutionError::ValueKindUnexpected {
+        value_expected: expected,
+        value_got: v.descriptor(),
+    }
+}
+
+impl FromValue for Value {
+    const TYPE: &'static str = "any";
+    fn from_value(v: &Value) -> Result<Self, ExecutionError> {
+        Ok(v.clone())
+    }
+}
+
+impl FromValue for bool {
+    const TYPE: &'static str = "bool";
+    fn from_value(v: &Value) -> Result<Self, ExecutionError> {
+        match v {
+            Value::Bool(b) => Ok(*b),
+            _ => Err(unexpected(BOOL_KIND, v)),
+        }
+    }
+}
+
+macro_rules! number_from_value {
+    ($($t:ty => $variant:ident, $kind:ident),*) => {
+        $(
+            impl FromValue for $t {
+                const TYPE: &'static str = stringify!($t);
+                fn from_value(v: &Value) -> Result<Self, ExecutionError> {
+                    match v {
+                        Value::$variant(n) => Ok(*n),
+                        _ => Err(unexpected($kind, v)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+number_from_value!(
+    i8 => I8, I8_KIND, i16 => I16, I16_KIND, i32 => I32, I32_KIND, i64 => I64, I64_KIND,
+    u8 => U8, U8_KIND, u16 => U16, U16_KIND, u32 => U32, U32_KIND, u64 => U64, U64_KIND,
+    f64 => F64, F64_KIND
+);
+
+impl FromValue for Rc<str> {
+    const TYPE: &'static str = "str";
+    fn from_value(v: &Value) -> Result<Self, ExecutionError> {
+        v.str().cloned()
+    }
+}
+
+impl FromValue for String {
+    const TYPE: &'static str = "str";
+    fn from_value(v: &Value) -> Result<Self, ExecutionError> {
+        Ok(v.str()?.to_string())
+    }
+}
+
+impl FromValue for List<Value> {
+    const TYPE: &'static str = "list";
+    fn from_value(v: &Value) -> Result<Self, ExecutionError> {
+        v.list().cloned()
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    const TYPE: &'static str = "list";
+    fn from_value(v: &Value) -> Result<Self, ExecutionError> {
+        v.list()?.iter().map(T::from_value).collect()
+    }
+}
+
+impl FromValue for PublicKey {
+    const TYPE: &'static str = "key";
+    fn from_value(v: &Value) -> Result<Self, ExecutionError> {
+        v.key()
+    }
+}
+
+impl FromValue for Pid {
+    const TYPE: &'static str = "pid";
+    fn from_value(v: &Value) -> Result<Self, ExecutionError> {
+        v.pid()
+    }
+}
+
+impl IntoValue for Value {
+    const TYPE: &'static str = "any";
+    fn into_value(self) -> Result<Value, ExecutionError> {
+        Ok(self)
+    }
+}
+
+impl IntoValue for () {
+    const TYPE: &'static str = "unit";
+    fn into_value(self) -> Result<Value, ExecutionError> {
+        Ok(Value::Unit)
+    }
+}
+
+macro_rules! into_value {
+    ($($t:ty => $variant:ident, $name:literal),*) => {
+        $(
+            impl IntoValue for $t {
+                const TYPE: &'static str = $name;
+                fn into_value(self) -> Result<Value, ExecutionError> {
+                    Ok(Value::$variant(self))
+                }
+            }
+        )*
+    };
+}
+
+into_value!(
+    bool => Bool, "bool", i8 => I8, "i8", i16 => I16, "i16", i32 => I32, "i32", i64 => I64, "i64",
+    u8 => U8, "u8", u16 => U16, "u16", u32 => U32, "u32", u64 => U64, "u64", f64 => F64, "f64",
+    Rc<str> => Str, "str", List<Value> => List, "list", PublicKey => Key, "key", Pid => Pid, "pid"
+);
+
+impl IntoValue for String {
+    const TYPE: &'static str = "str";
+    fn into_value(self) -> Result<Value, ExecutionError> {
+        Ok(Value::Str(Rc::from(self)))
+    }
+}
+
+impl IntoValue for &str {
+    const TYPE: &'static str = "str";
+    fn into_value(self) -> Result<Value, ExecutionError> {
+        Ok(Value::Str(Rc::from(self)))
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    const TYPE: &'static str = "list";
+    fn into_value(self) -> Result<Value, ExecutionError> {
+        Ok(Value::List(self.into_iter().map(T::into_value).collect::<Result<_, _>>()?))
+    }
+}
+
+/// A host function failing with `Err(message)` crashes the calling process
+/// with that message.
+impl<T: IntoValue> IntoValue for Result<T, String> {
+    const TYPE: &'static str = T::TYPE;
+    fn into_value(self) -> Result<Value, ExecutionError> {
+        match self {
+            Ok(v) => v.into_value(),
+            Err(message) => Err(ExecutionError::UserPanic { message }),
+        }
+    }
+}
+
+macro_rules! from_values {
+    ($arity:literal; $($t:ident $i:tt),*) => {
+        impl<$($t: FromValue),*> FromValues for ($($t,)*) {
+            const ARITY: usize = $arity;
+            const TYPES: &'static [&'static str] = &[$($t::TYPE),*];
+            #[allow(unused_variables)]
+            fn from_values(args: &[Value]) -> Result<Self, ExecutionError> {
+                Ok(($($t::from_value(&args[$i])?,)*))
+            }
+        }
+    };
+}
+
+from_values!(0;);
+from_values!(1; A 0);
+from_values!(2; A 0, B 1);
+from_values!(3; A 0, B 1, C 2);
+from_values!(4; A 0, B 1, C 2, D 3);
+from_values!(5; A 0, B 1, C 2, D 3, E 4);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Join;
+
+    impl HostFunction for Join {
+        type Args = (Vec<String>, Rc<str>);
+        type Ret = Result<String, String>;
+        fn call((parts, separator): (Vec<String>, Rc<str>)) -> Result<String, String> {
+            Ok(parts.join(&separator))
+        }
+    }
+
+    #[test]
+    fn signatures_carry_the_sio_types() {
+        let join = Signature::of::<Join>("host::text::join");
+        assert_eq!(join, Signature { url: "host::text::join", params: &["list", "str"], ret: "str" });
+        assert!(type_matches("i64", "int"));
+        assert!(type_matches("any", "pid"));
+        assert!(!type_matches("u8", "int"));
+    }
+}
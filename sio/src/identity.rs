@@ -0,0 +1,136 @@
+//! Ed25519 identities.
+//!
+//! Garrisons and the processes they host (brigadiers, majors, corporals) each
+//! own an [`Identity`]. The public half is the [`PublicKey`] everything in sio
+//! is addressed by, the secret half signs what the owner sends or publishes so
+//! the receiver can attribute it.
+use crate::key::{PublicKey, KEY_LEN};
+use core::fmt;
+use ed25519_dalek::{Signer, SigningKey, VerifyingKey};
+use sha2::{Digest, Sha512};
+
+pub const SEED_LEN: usize = 32;
+pub const SIGNATURE_LEN: usize = 64;
+
+#[derive(Clone)]
+pub struct Identity {
+    signing: SigningKey,
+}
+
+impl Identity {
+    /// Creates the identity for a 32 byte secret seed. The same seed always
+    /// yields the same keypair.
+    pub fn from_seed(seed: [u8; SEED_LEN]) -> Self {
+        Identity {
+            signing: SigningKey::from_bytes(&seed),
+        }
+    }
+
+    /// Derives a child identity, e.g. the brigadier of a garrison, from this
+    /// identity's secret and a label. Distinct labels give unrelated keys.
+    pub fn derive(&self, label: &str) -> Self {
+        let digest = Sha512::new()
+            .chain_update(b"sio identity derivation")
+            .chain_update(self.signing.as_bytes())
+            .chain_update(label.as_bytes())
+            .finalize();
+        let mut seed = [0u8; SEED_LEN];
+        seed.copy_from_slice(&digest[..SEED_LEN]);
+        Identity::from_seed(seed)
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey::from_bytes(self.signing.verifying_key().to_bytes())
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        Signature(self.signing.sign(message).to_bytes())
+    }
+}
+
+impl fmt::Debug for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Identity({})", self.public_key())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Signature([u8; SIGNATURE_LEN]);
+
+impl Signature {
+    pub const fn from_bytes(bytes: [u8; SIGNATURE_LEN]) -> Self {
+        Signature(bytes)
+    }
+
+    pub fn to_bytes(&self) -> [u8; SIGNATURE_LEN] {
+        self.0
+    }
+}
+
+impl fmt::Debug for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Signature(")?;
+        for b in &self.0[..8] {
+            write!(f, "{:02x}", b)?;
+        }
+        write!(f, "..)")
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdentityError {
+    /// The public key is not a valid ed25519 point.
+    InvalidKey,
+    /// The signature does not match the message and key.
+    BadSignature,
+}
+
+impl fmt::Display for IdentityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdentityError::InvalidKey => write!(f, "invalid ed25519 public key"),
+            IdentityError::BadSignature => write!(f, "signature verification failed"),
+        }
+    }
+}
+
+/// Checks that `signature` was produced over `message` by the owner of `key`.
+pub fn verify(key: &PublicKey, message: &[u8], signature: &Signature) -> Result<(), IdentityError> {
+    let bytes: &[u8; KEY_LEN] = key.as_bytes();
+    let verifying = VerifyingKey::from_bytes(bytes).map_err(|_| IdentityError::InvalidKey)?;
+    let signature = ed25519_dalek::Signature::from_bytes(&signature.0);
+    verifying
+        .verify_strict(message, &signature)
+        .map_err(|_| IdentityError::BadSignature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_and_verify() {
+        let identity = Identity::from_seed([7; SEED_LEN]);
+        let signature = identity.sign(b"march");
+        assert_eq!(verify(&identity.public_key(), b"march", &signature), Ok(()));
+        assert_eq!(
+            verify(&identity.public_key(), b"retreat", &signature),
+            Err(IdentityError::BadSignature)
+        );
+        let other = Identity::from_seed([8; SEED_LEN]);
+        assert_eq!(
+            verify(&other.public_key(), b"march", &signature),
+            Err(IdentityError::BadSignature)
+        );
+    }
+
+    #[test]
+    fn derivation_is_deterministic() {
+        let garrison = Identity::from_seed([1; SEED_LEN]);
+        let a = garrison.derive("brigadier");
+        let b = Identity::from_seed([1; SEED_LEN]).derive("brigadier");
+        assert_eq!(a.public_key(), b.public_key());
+        assert_ne!(a.public_key(), garrison.derive("major").public_key());
+        assert_ne!(a.public_key(), garrison.public_key());
+    }
+}
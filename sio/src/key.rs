@@ -0,0 +1,342 @@
+//! Public key literals.
+//!
+//! Every garrison, process and module in sio is addressed by a 32 byte public
+//! key. The only accepted textual form is a bech32m string: a human readable
+//! prefix naming the tier the key belongs to, the `1` separator, the 32 bytes
+//! as 5 bit groups and a 6 character checksum:
+//!
+//! ```text
+//! spub1...    a key not bound to any tier (garrisons, modules)
+//! spubg1...   a general
+//! spubb1...   a brigadier
+//! spubm1...   a major
+//! spubc1...   a corporal
+//! ```
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+pub const KEY_LEN: usize = 32;
+
+const SEPARATOR: char = '1';
+const CHECKSUM_LEN: usize = 6;
+/// Characters after the separator: the key as 5 bit groups and the checksum.
+const DATA_LEN: usize = (KEY_LEN * 8).div_ceil(5) + CHECKSUM_LEN;
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+const GENERATOR: [u32; 5] = [0x3b6a_57b2, 0x2650_8e6d, 0x1ea1_19fa, 0x3d42_33dd, 0x2a14_62b3];
+
+/// Human readable prefixes, most specific first so that prefix matching
+/// never picks `spub` for a tiered key.
+const PREFIXES: [(&str, Option<KeyTier>); 5] = [
+    ("spubg", Some(KeyTier::General)),
+    ("spubb", Some(KeyTier::Brigadier)),
+    ("spubm", Some(KeyTier::Major)),
+    ("spubc", Some(KeyTier::Corporal)),
+    ("spub", None),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum KeyTier {
+    General,
+    Brigadier,
+    Major,
+    Corporal,
+}
+
+impl KeyTier {
+    pub fn prefix(tier: Option<KeyTier>) -> &'static str {
+        match tier {
+            None => "spub",
+            Some(KeyTier::General) => "spubg",
+            Some(KeyTier::Brigadier) => "spubb",
+            Some(KeyTier::Major) => "spubm",
+            Some(KeyTier::Corporal) => "spubc",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PublicKey([u8; KEY_LEN]);
+
+impl PublicKey {
+    pub const fn from_bytes(bytes: [u8; KEY_LEN]) -> Self {
+        PublicKey(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; KEY_LEN] {
+        &self.0
+    }
+
+    /// Encodes the key with the prefix of `tier`.
+    pub fn encode(&self, tier: Option<KeyTier>) -> String {
+        encode(KeyTier::prefix(tier), &self.0)
+    }
+}
+
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.encode(None))
+    }
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PublicKey({})", self)
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = KeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        KeyLiteral::from_str(s).map(|lit| lit.key)
+    }
+}
+
+/// A decoded key literal, remembering the tier prefix it was written with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyLiteral {
+    pub tier: Option<KeyTier>,
+    pub key: PublicKey,
+}
+
+impl fmt::Display for KeyLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.key.encode(self.tier))
+    }
+}
+
+impl FromStr for KeyLiteral {
+    type Err = KeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        decode(s)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyError {
+    MissingSeparator,
+    UnknownPrefix(String),
+    MixedCase,
+    InvalidCharacter(char),
+    InvalidLength(usize),
+    InvalidPadding,
+    InvalidChecksum,
+    /// Bare 64 digit hex, `sio<hex>` and `siopub<hex>` spellings used before
+    /// keys had a checksum.
+    LegacyHex,
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyError::MissingSeparator => write!(f, "missing '1' separator"),
+            KeyError::UnknownPrefix(p) => write!(f, "unknown key prefix '{}'", p),
+            KeyError::MixedCase => write!(f, "mixed case"),
+            KeyError::InvalidCharacter(c) => write!(f, "invalid character '{}'", c),
+            KeyError::InvalidLength(n) => write!(f, "expected {} bytes, got {}", KEY_LEN, n),
+            KeyError::InvalidPadding => write!(f, "invalid padding"),
+            KeyError::InvalidChecksum => write!(f, "invalid checksum"),
+            KeyError::LegacyHex => write!(f, "hex keys are no longer accepted, use the spub1... form"),
+        }
+    }
+}
+
+/// Returns true if `s` is written like a key literal and should be decoded as one
+/// rather than treated as an identifier or a number: a known prefix, the
+/// separator and bech32 characters, whatever their count, or a legacy hex key.
+/// `spub1` alone stays an identifier.
+pub fn looks_like_key(s: &str) -> bool {
+    if s.contains('_') {
+        return false;
+    }
+    if is_legacy_hex(s) {
+        return true;
+    }
+    PREFIXES.iter().any(|(prefix, _)| {
+        s.len() > prefix.len() + 1
+            && s[..prefix.len()].eq_ignore_ascii_case(prefix)
+            && s.as_bytes()[prefix.len()] == SEPARATOR as u8
+            && s[prefix.len() + 1..].bytes().all(|b| CHARSET.contains(&b.to_ascii_lowercase()))
+    })
+}
+
+fn is_legacy_hex(s: &str) -> bool {
+    match s.strip_prefix("siopub").or_else(|| s.strip_prefix("sio")) {
+        Some(hex) => hex.len() >= 2 * KEY_LEN && hex.bytes().all(|b| b.is_ascii_hexdigit()),
+        None => s.len() == 2 * KEY_LEN && s.bytes().all(|b| b.is_ascii_hexdigit()),
+    }
+}
+
+pub fn decode(s: &str) -> Result<KeyLiteral, KeyError> {
+    if is_legacy_hex(s) {
+        return Err(KeyError::LegacyHex);
+    }
+    let has_lower = s.bytes().any(|b| b.is_ascii_lowercase());
+    let has_upper = s.bytes().any(|b| b.is_ascii_uppercase());
+    if has_lower && has_upper {
+        return Err(KeyError::MixedCase);
+    }
+    let s = s.to_ascii_lowercase();
+    let sep = s.rfind(SEPARATOR).ok_or(KeyError::MissingSeparator)?;
+    let (hrp, data) = (&s[..sep], &s[sep + 1..]);
+    let tier = PREFIXES
+        .iter()
+        .find(|(prefix, _)| *prefix == hrp)
+        .map(|(_, tier)| *tier)
+        .ok_or_else(|| KeyError::UnknownPrefix(hrp.into()))?;
+
+    let mut values = Vec::with_capacity(data.len());
+    for c in data.chars() {
+        let v = CHARSET
+            .iter()
+            .position(|&x| x as char == c)
+            .ok_or(KeyError::InvalidCharacter(c))?;
+        values.push(v as u8);
+    }
+    if values.len() != DATA_LEN {
+        let bytes = values.len().saturating_sub(CHECKSUM_LEN) * 5 / 8;
+        return Err(KeyError::InvalidLength(bytes));
+    }
+    let mut check = hrp_expand(hrp);
+    check.extend_from_slice(&values);
+    if polymod(&check) != BECH32M_CONST {
+        return Err(KeyError::InvalidChecksum);
+    }
+
+    values.truncate(values.len() - CHECKSUM_LEN);
+    let bytes = convert_bits(&values, 5, 8, false).ok_or(KeyError::InvalidPadding)?;
+    let bytes: [u8; KEY_LEN] = bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| KeyError::InvalidLength(bytes.len()))?;
+    Ok(KeyLiteral { tier, key: PublicKey(bytes) })
+}
+
+fn encode(hrp: &str, bytes: &[u8]) -> String {
+    let mut values = convert_bits(bytes, 8, 5, true).expect("padding is allowed when encoding");
+    let mut check = hrp_expand(hrp);
+    check.extend_from_slice(&values);
+    check.extend_from_slice(&[0; CHECKSUM_LEN]);
+    let pm = polymod(&check) ^ BECH32M_CONST;
+    for i in 0..CHECKSUM_LEN {
+        values.push(((pm >> (5 * (5 - i))) & 31) as u8);
+    }
+
+    let mut out = String::with_capacity(hrp.len() + 1 + values.len());
+    out.push_str(hrp);
+    out.push(SEPARATOR);
+    for v in values {
+        out.push(CHARSET[v as usize] as char);
+    }
+    out
+}
+
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut out: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    out.push(0);
+    out.extend(hrp.bytes().map(|b| b & 31));
+    out
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    let mut chk: u32 = 1;
+    for &v in values {
+        let top = chk >> 25;
+        chk = (chk & 0x1ff_ffff) << 5 ^ v as u32;
+        for (i, g) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
+
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let max = (1u32 << to) - 1;
+    let mut out = Vec::new();
+    for &value in data {
+        acc = (acc << from) | value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            out.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || ((acc << (to - bits)) & max) != 0 {
+        return None;
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    const BYTES: [u8; KEY_LEN] = [
+        0x79, 0xf7, 0x08, 0xc2, 0x5a, 0x23, 0xed, 0x36, 0x76, 0x10, 0xfa, 0xcc, 0x14, 0x03, 0x5a, 0xdc,
+        0x7b, 0xa4, 0xb1, 0xbf, 0xa9, 0x25, 0x2e, 0xf5, 0x5c, 0x6c, 0x24, 0xf1, 0xb9, 0xb0, 0x3a, 0xbd,
+    ];
+
+    #[test]
+    fn roundtrip() {
+        let key = PublicKey::from_bytes(BYTES);
+        let s = key.to_string();
+        assert!(s.starts_with("spub1"));
+        assert_eq!(s.len(), 63);
+        assert_eq!(s.parse::<PublicKey>(), Ok(key));
+        assert_eq!(s.to_ascii_uppercase().parse::<PublicKey>(), Ok(key));
+    }
+
+    #[test]
+    fn tiers() {
+        let key = PublicKey::from_bytes(BYTES);
+        for tier in [KeyTier::General, KeyTier::Brigadier, KeyTier::Major, KeyTier::Corporal] {
+            let lit = KeyLiteral { tier: Some(tier), key };
+            assert_eq!(lit.to_string().parse::<KeyLiteral>(), Ok(lit));
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let s = PublicKey::from_bytes(BYTES).to_string();
+        let mut flipped = s.clone().into_bytes();
+        let last = flipped.len() - 1;
+        flipped[last] = if flipped[last] == b'q' { b'p' } else { b'q' };
+        let flipped = String::from_utf8(flipped).unwrap();
+        assert_eq!(decode(&flipped), Err(KeyError::InvalidChecksum));
+        // 29 characters of key, 18 bytes
+        assert_eq!(decode(&s[..40]), Err(KeyError::InvalidLength(18)));
+        assert_eq!(decode(&s[..s.len() - 1]), Err(KeyError::InvalidLength(31)));
+        assert_eq!(decode("spubx1qqqq"), Err(KeyError::UnknownPrefix("spubx".to_string())));
+        assert_eq!(decode("spub1qqqqb"), Err(KeyError::InvalidCharacter('b')));
+        assert_eq!(
+            decode("sio79f708c25a23ed367610facc14035adc7ba4b1bfa9252ef55c6c24f1b9b03abd"),
+            Err(KeyError::LegacyHex)
+        );
+        assert!(looks_like_key(&s));
+        assert!(!looks_like_key("spub1_abc"));
+        assert!(!looks_like_key("spubby"));
+        assert!(!looks_like_key("spub1"));
+        assert!(!looks_like_key("spubg1b"));
+        assert!(looks_like_key("spubg1x"));
+        assert!(looks_like_key(&s[..s.len() - 1]));
+        assert_eq!(
+            decode("79f708c25a23ed367610facc14035adc7ba4b1bfa9252ef55c6c24f1b9b03abd"),
+            Err(KeyError::LegacyHex)
+        );
+        assert!(!looks_like_key("79f708c25a23ed367610facc14035adc7ba4b1bfa9252ef55c6c24f1b9b03ab"));
+        assert!(looks_like_key(&PublicKey::from_bytes(BYTES).encode(Some(KeyTier::General))));
+    }
+}
@@ -1,49 +1,57 @@
 #![no_std]
 extern crate alloc;
+
+use alloc::rc::Rc;
+use core::cell::RefCell;
+
 pub mod allocator;
+pub mod dataflow;
 pub mod environ;
-pub mod value;
+pub mod ffi;
+pub mod identity;
+pub mod key;
+pub mod list;
+pub mod module;
+pub mod process;
 pub mod router;
+pub mod tier;
+pub mod value;
 
 pub use self::{
-    allocator::{GeneralAllocator, BrigadierAllocator, MajorAllocator, CorporalAllocator},
-    value::{GeneralValue, BrigadierValue, MajorValue, CorporalValue},
-    environ::{
-        general::{GeneralLiteral, general_literal_mapper, general_literal_to_value, create_general_env},
-        brigadier::{BrigadierLiteral, brigadier_literal_mapper, brigadier_literal_to_value, create_brigadier_env},
-        major::{MajorLiteral, major_literal_mapper, major_literal_to_value, create_major_env},
-        corporal::{CorporalLiteral, corporal_literal_mapper, corporal_literal_to_value, create_corporal_env},
-    },
+    dataflow::{BindError, Dataflow, Resolved, VarId},
+    key::{PublicKey, KeyLiteral, KeyTier, KeyError},
+    list::List,
+    identity::{Identity, IdentityError, Signature},
+    module::{SignedModule, ModuleError},
+    process::{ExitSignal, MonitorRef, Pid, ProcessId, ProcessContext, Request, Signal, Spawn, SpawnError, Strategy, Suspension, Suspend},
+    allocator::Allocator,
+    tier::Tier,
+    value::Value,
+    environ::{Literal, literal_mapper, literal_to_value, sequence_constructor, create_env},
 };
 
-pub type GeneralNIF = werbolg_exec::NIF<GeneralAllocator, GeneralLiteral, GeneralState, GeneralValue>;
-pub type GeneralEnvironment = werbolg_compile::Environment<GeneralNIF, GeneralValue>;
-pub type GeneralExecutionMachine =
-    werbolg_exec::ExecutionMachine<GeneralAllocator, GeneralLiteral, GeneralState, GeneralValue>;
-
-pub type BrigadierNIF = werbolg_exec::NIF<BrigadierAllocator, BrigadierLiteral, BrigadierState, BrigadierValue>;
-pub type BrigadierEnvironment = werbolg_compile::Environment<BrigadierNIF, BrigadierValue>;
-pub type BrigadierExecutionMachine =
-    werbolg_exec::ExecutionMachine<BrigadierAllocator, BrigadierLiteral, BrigadierState, BrigadierValue>;
-
-pub type MajorNIF = werbolg_exec::NIF<MajorAllocator, MajorLiteral, MajorState, MajorValue>;
-pub type MajorEnvironment = werbolg_compile::Environment<MajorNIF, MajorValue>;
-pub type MajorExecutionMachine =
-    werbolg_exec::ExecutionMachine<MajorAllocator, MajorLiteral, MajorState, MajorValue>;
-
-pub type CorporalNIF = werbolg_exec::NIF<CorporalAllocator, CorporalLiteral, CorporalState, CorporalValue>;
-pub type CorporalEnvironment = werbolg_compile::Environment<CorporalNIF, CorporalValue>;
-pub type CorporalExecutionMachine =
-    werbolg_exec::ExecutionMachine<CorporalAllocator, CorporalLiteral, CorporalState, CorporalValue>;
-
-#[derive(Clone)]
-pub struct GeneralState {}
-
-#[derive(Clone)]
-pub struct BrigadierState {}
-
-#[derive(Clone)]
-pub struct MajorState {}
-
-#[derive(Clone)]
-pub struct CorporalState {}
+pub type NIF = werbolg_exec::NIF<Allocator, Literal, State, Value>;
+pub type Environment = werbolg_compile::Environment<NIF, Value>;
+pub type ExecutionMachine = werbolg_exec::ExecutionMachine<Allocator, Literal, State, Value>;
+pub type ExecutionEnviron = werbolg_exec::ExecutionEnviron<Allocator, Literal, State, Value>;
+
+#[derive(Clone, Default)]
+pub struct State {
+    pub suspension: Option<Suspension>,
+    pub context: ProcessContext,
+    pub spawn: Option<Spawn<Value>>,
+    /// Dataflow variables, shared by the processes of a tier that may see each
+    /// other's variables.
+    pub store: Rc<RefCell<Dataflow<Value>>>,
+    /// Processes waiting on a variable bound since the scheduler last looked.
+    pub woken: Vec<ProcessId>,
+}
+
+impl Suspend for State {
+    fn suspend(&mut self, why: Suspension) {
+        self.suspension = Some(why);
+    }
+    fn take_suspension(&mut self) -> Option<Suspension> {
+        self.suspension.take()
+    }
+}
@@ -0,0 +1,175 @@
+//! Immutable lists for list values.
+//!
+//! A list is a chain of reference counted nodes, so copying a list, taking
+//! its tail or consing onto it never copies elements. Updating an element
+//! copies the nodes in front of it and shares the rest.
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::fmt;
+
+struct Node<V> {
+    value: V,
+    next: Option<Rc<Node<V>>>,
+}
+
+pub struct List<V> {
+    head: Option<Rc<Node<V>>>,
+    len: usize,
+}
+
+impl<V> List<V> {
+    pub fn new() -> Self {
+        List { head: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The list with `value` in front of this one.
+    pub fn cons(&self, value: V) -> Self {
+        List {
+            head: Some(Rc::new(Node { value, next: self.head.clone() })),
+            len: self.len + 1,
+        }
+    }
+
+    pub fn head(&self) -> Option<&V> {
+        self.head.as_ref().map(|node| &node.value)
+    }
+
+    pub fn tail(&self) -> Option<Self> {
+        self.head.as_ref().map(|node| List { head: node.next.clone(), len: self.len - 1 })
+    }
+
+    pub fn get(&self, index: usize) -> Option<&V> {
+        self.iter().nth(index)
+    }
+
+    pub fn iter(&self) -> Iter<'_, V> {
+        Iter { next: self.head.as_deref() }
+    }
+}
+
+impl<V: Clone> List<V> {
+    /// The list with the element at `index` replaced, [`None`] when `index` is
+    /// out of bounds.
+    pub fn set(&self, index: usize, value: V) -> Option<Self> {
+        if index >= self.len {
+            return None;
+        }
+        let mut node = self.head.as_ref();
+        let mut front = Vec::with_capacity(index);
+        for _ in 0..index {
+            let n = node?;
+            front.push(n.value.clone());
+            node = n.next.as_ref();
+        }
+        let rest = List { head: node?.next.clone(), len: self.len - index - 1 };
+        Some(front.into_iter().rev().fold(rest.cons(value), |list, v| list.cons(v)))
+    }
+
+    /// This list followed by `other`, which is shared rather than copied.
+    pub fn append(&self, other: &Self) -> Self {
+        let front: Vec<&V> = self.iter().collect();
+        front.into_iter().rev().fold(other.clone(), |list, v| list.cons(v.clone()))
+    }
+}
+
+impl<V> Default for List<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Clone for List<V> {
+    fn clone(&self) -> Self {
+        List { head: self.head.clone(), len: self.len }
+    }
+}
+
+// unlinks the nodes one by one, dropping a long list recursively could
+// overflow the stack
+impl<V> Drop for List<V> {
+    fn drop(&mut self) {
+        let mut next = self.head.take();
+        while let Some(node) = next {
+            next = match Rc::try_unwrap(node) {
+                Ok(mut node) => node.next.take(),
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+impl<V: PartialEq> PartialEq for List<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<V: fmt::Debug> fmt::Debug for List<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<V> FromIterator<V> for List<V> {
+    fn from_iter<I: IntoIterator<Item = V>>(iter: I) -> Self {
+        let values: Vec<V> = iter.into_iter().collect();
+        values.into_iter().rev().fold(List::new(), |list, v| list.cons(v))
+    }
+}
+
+pub struct Iter<'a, V> {
+    next: Option<&'a Node<V>>,
+}
+
+impl<'a, V> Iterator for Iter<'a, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        self.next.map(|node| {
+            self.next = node.next.as_deref();
+            &node.value
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn updates_share_the_rest() {
+        let xs: List<u32> = (1..=4).collect();
+        assert_eq!(xs.len(), 4);
+        assert_eq!(xs.head(), Some(&1));
+        assert_eq!(xs.get(3), Some(&4));
+        assert_eq!(xs.get(4), None);
+
+        let ys = xs.set(1, 9).unwrap();
+        assert_eq!(ys.iter().copied().collect::<Vec<_>>(), vec![1, 9, 3, 4]);
+        assert_eq!(xs.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert!(xs.set(4, 9).is_none());
+
+        let zs = xs.tail().unwrap().append(&ys);
+        assert_eq!(zs.len(), 7);
+        assert_eq!(zs.iter().copied().collect::<Vec<_>>(), vec![2, 3, 4, 1, 9, 3, 4]);
+        assert_eq!(zs.cons(2).tail().unwrap(), zs);
+        assert!(List::<u32>::new().tail().is_none());
+    }
+
+    #[test]
+    fn long_lists_drop() {
+        let xs: List<u32> = (0..1_000_000).collect();
+        let ys = xs.tail().unwrap();
+        drop(xs);
+        assert_eq!(ys.len(), 999_999);
+    }
+}
@@ -0,0 +1,200 @@
+//! Signed code modules.
+//!
+//! Source that travels between garrisons is wrapped in a [`SignedModule`]: the
+//! author's public key, an ed25519 signature over the source and the source
+//! itself. The wire format is
+//!
+//! ```text
+//! magic "SIOM" | version u8 | author [32] | signature [64] | len u32 le | source [len]
+//! ```
+use crate::identity::{self, Identity, IdentityError, Signature, SIGNATURE_LEN};
+use crate::key::{PublicKey, KEY_LEN};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+const MAGIC: &[u8; 4] = b"SIOM";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 1 + KEY_LEN + SIGNATURE_LEN + 4;
+/// Prepended to the source before signing so a module signature can never be
+/// replayed as a signature over some other kind of message.
+const DOMAIN: &[u8] = b"sio module v1\0";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedModule {
+    pub author: PublicKey,
+    pub signature: Signature,
+    pub source: String,
+}
+
+impl SignedModule {
+    /// Signs `source` as authored by `identity`.
+    pub fn sign(identity: &Identity, source: String) -> Self {
+        let signature = identity.sign(&signing_payload(&source));
+        SignedModule {
+            author: identity.public_key(),
+            signature,
+            source,
+        }
+    }
+
+    /// Checks the signature over the source against the author key.
+    pub fn verify(&self) -> Result<(), ModuleError> {
+        identity::verify(&self.author, &signing_payload(&self.source), &self.signature)
+            .map_err(ModuleError::Signature)
+    }
+
+    /// Checks the module is authored by `expected` and its signature holds.
+    /// A valid signature alone only shows the module signed itself.
+    pub fn verify_by(&self, expected: &PublicKey) -> Result<(), ModuleError> {
+        if self.author != *expected {
+            return Err(ModuleError::UnexpectedAuthor { expected: *expected, signed_by: self.author });
+        }
+        self.verify()
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HEADER_LEN + self.source.len());
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.extend_from_slice(self.author.as_bytes());
+        out.extend_from_slice(&self.signature.to_bytes());
+        out.extend_from_slice(&(self.source.len() as u32).to_le_bytes());
+        out.extend_from_slice(self.source.as_bytes());
+        out
+    }
+
+    /// Decodes a module from its wire format. This only checks the framing,
+    /// call [`SignedModule::verify`] before trusting the source.
+    pub fn decode(bytes: &[u8]) -> Result<Self, ModuleError> {
+        if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(ModuleError::Unsigned);
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(ModuleError::Truncated);
+        }
+        let version = bytes[MAGIC.len()];
+        if version != VERSION {
+            return Err(ModuleError::UnsupportedVersion(version));
+        }
+        let (author, rest) = bytes[MAGIC.len() + 1..].split_at(KEY_LEN);
+        let (signature, rest) = rest.split_at(SIGNATURE_LEN);
+        let (len, source) = rest.split_at(4);
+        let len = u32::from_le_bytes(len.try_into().expect("4 bytes")) as usize;
+        if source.len() < len {
+            return Err(ModuleError::Truncated);
+        }
+        if source.len() > len {
+            return Err(ModuleError::TrailingBytes);
+        }
+        let source = core::str::from_utf8(source).map_err(|_| ModuleError::InvalidUtf8)?;
+        Ok(SignedModule {
+            author: PublicKey::from_bytes(author.try_into().expect("32 bytes")),
+            signature: Signature::from_bytes(signature.try_into().expect("64 bytes")),
+            source: source.into(),
+        })
+    }
+}
+
+fn signing_payload(source: &str) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(DOMAIN.len() + source.len());
+    payload.extend_from_slice(DOMAIN);
+    payload.extend_from_slice(source.as_bytes());
+    payload
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ModuleError {
+    /// The bytes do not start with a signed module header.
+    Unsigned,
+    Truncated,
+    TrailingBytes,
+    UnsupportedVersion(u8),
+    InvalidUtf8,
+    Signature(IdentityError),
+    /// The module is validly signed, but not by the key its path names.
+    AuthorMismatch { signed_by: PublicKey, path: PublicKey },
+    /// The module is signed by another key than the one the caller trusts.
+    UnexpectedAuthor { expected: PublicKey, signed_by: PublicKey },
+}
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModuleError::Unsigned => write!(f, "module is not signed"),
+            ModuleError::Truncated => write!(f, "module is truncated"),
+            ModuleError::TrailingBytes => write!(f, "trailing bytes after module source"),
+            ModuleError::UnsupportedVersion(v) => write!(f, "unsupported module version {}", v),
+            ModuleError::InvalidUtf8 => write!(f, "module source is not valid utf-8"),
+            ModuleError::Signature(e) => write!(f, "{}", e),
+            ModuleError::AuthorMismatch { signed_by, path } => {
+                write!(f, "module signed by {} but its path names {}", signed_by, path)
+            }
+            ModuleError::UnexpectedAuthor { expected, signed_by } => {
+                write!(f, "module signed by {} but expected from {}", signed_by, expected)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    const SOURCE: &str = "corporal app::Corporal { pub main :: () { 1; } }";
+
+    #[test]
+    fn roundtrip() {
+        let identity = Identity::from_seed([3; 32]);
+        let module = SignedModule::sign(&identity, SOURCE.to_string());
+        let decoded = SignedModule::decode(&module.encode()).unwrap();
+        assert_eq!(decoded, module);
+        assert_eq!(decoded.verify(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_tampering() {
+        let identity = Identity::from_seed([3; 32]);
+        let mut bytes = SignedModule::sign(&identity, SOURCE.to_string()).encode();
+        let last = bytes.len() - 2;
+        bytes[last] = b'2';
+        let tampered = SignedModule::decode(&bytes).unwrap();
+        assert_eq!(
+            tampered.verify(),
+            Err(ModuleError::Signature(IdentityError::BadSignature))
+        );
+
+        let mut forged = SignedModule::sign(&identity, SOURCE.to_string());
+        forged.author = Identity::from_seed([4; 32]).public_key();
+        assert!(forged.verify().is_err());
+    }
+
+    #[test]
+    fn rejects_unexpected_authors() {
+        let identity = Identity::from_seed([3; 32]);
+        let other = Identity::from_seed([4; 32]);
+        let module = SignedModule::sign(&other, SOURCE.to_string());
+        assert_eq!(module.verify(), Ok(()));
+        assert_eq!(module.verify_by(&other.public_key()), Ok(()));
+        assert_eq!(
+            module.verify_by(&identity.public_key()),
+            Err(ModuleError::UnexpectedAuthor { expected: identity.public_key(), signed_by: other.public_key() })
+        );
+    }
+
+    #[test]
+    fn framing_errors() {
+        let identity = Identity::from_seed([3; 32]);
+        let bytes = SignedModule::sign(&identity, SOURCE.to_string()).encode();
+        assert_eq!(SignedModule::decode(SOURCE.as_bytes()), Err(ModuleError::Unsigned));
+        assert_eq!(SignedModule::decode(&bytes[..bytes.len() - 1]), Err(ModuleError::Truncated));
+        assert_eq!(SignedModule::decode(&bytes[..HEADER_LEN - 1]), Err(ModuleError::Truncated));
+        let mut longer = bytes.clone();
+        longer.push(b' ');
+        assert_eq!(SignedModule::decode(&longer), Err(ModuleError::TrailingBytes));
+        let mut future = bytes;
+        future[MAGIC.len()] = 2;
+        assert_eq!(SignedModule::decode(&future), Err(ModuleError::UnsupportedVersion(2)));
+    }
+}
@@ -1 +1,210 @@
+//! Process bookkeeping shared by the tiers and the garrison scheduler.
+use crate::identity::Identity;
+use crate::key::PublicKey;
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use werbolg_core::ValueFun;
 
+/// Identifies a process within its garrison.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProcessId(pub u64);
+
+impl fmt::Display for ProcessId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}>", self.0)
+    }
+}
+
+/// Identifies a process anywhere: the garrison running it and its id there.
+/// This is what `pid` values hold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Pid {
+    pub garrison: PublicKey,
+    pub local: ProcessId,
+}
+
+impl fmt::Display for Pid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}.{}>", self.garrison, self.local.0)
+    }
+}
+
+/// Why a process cannot make progress.
+///
+/// A NIF that would block records the suspension in its tier state and fails
+/// the call. The call instruction has not completed, so once the scheduler
+/// wakes the process the same call runs again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Suspension {
+    /// Reading a dataflow variable that is not bound yet.
+    Unbound(u64),
+    /// Receiving from an empty mailbox.
+    Receive,
+    /// Waiting for the garrison to start a process, see [`Spawn`].
+    Spawn,
+}
+
+/// A `spawn` call handed over to the garrison.
+#[derive(Clone, Debug)]
+pub enum Spawn<V> {
+    /// Start `fun(args)` in `garrison`.
+    Requested { fun: ValueFun, args: Vec<V>, garrison: PublicKey },
+    /// The garrison started the process, or why it did not.
+    Done(Result<ProcessId, SpawnError>),
+}
+
+/// Why a garrison did not start the process a `spawn` call asked for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpawnError {
+    /// Processes only start in the garrison running the caller.
+    OtherGarrison(PublicKey),
+    /// The function to start is not one of the caller's module.
+    NotAFunction,
+    /// The function cannot start with the arguments given.
+    Start(String),
+}
+
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpawnError::OtherGarrison(garrison) => write!(f, "cannot spawn into garrison {}", garrison),
+            SpawnError::NotAFunction => write!(f, "can only spawn a function of the module"),
+            SpawnError::Start(e) => write!(f, "cannot start process: {}", e),
+        }
+    }
+}
+
+/// Implemented by the tier states so the scheduler can tell a blocked call
+/// apart from a failed one.
+pub trait Suspend {
+    fn suspend(&mut self, why: Suspension);
+    fn take_suspension(&mut self) -> Option<Suspension>;
+}
+
+/// Identifies one `monitor` call of a process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MonitorRef(pub u64);
+
+/// Why a process exited, as seen by the processes linked to or monitoring it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitSignal {
+    Normal,
+    Crashed,
+    /// Stopped by its supervisor.
+    Stopped,
+    /// The process did not exist or had already exited.
+    NoProc,
+    /// The process does not allow links from the requester.
+    Unauthorised,
+}
+
+impl ExitSignal {
+    /// The reason as `receive_signal` reports it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExitSignal::Normal => "normal",
+            ExitSignal::Crashed => "crashed",
+            ExitSignal::Stopped => "stopped",
+            ExitSignal::NoProc => "noproc",
+            ExitSignal::Unauthorised => "unauthorised",
+        }
+    }
+}
+
+/// Delivered to the mailbox of a process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Signal {
+    /// A linked process exited while this process traps exits.
+    Exit { from: ProcessId, reason: ExitSignal },
+    /// A monitored process exited.
+    Down { monitor: MonitorRef, from: ProcessId, reason: ExitSignal },
+}
+
+impl Signal {
+    pub fn from(&self) -> ProcessId {
+        match self {
+            Signal::Exit { from, .. } | Signal::Down { from, .. } => *from,
+        }
+    }
+}
+
+/// How a supervision group reacts when one of its children fails.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Every child depends on the ones started before it: when one fails, it
+    /// and every child after it are restarted.
+    Series,
+    /// Children are independent: only the failed one is restarted.
+    Parallel,
+    /// The group is healthy while `k` children survive. Failed children are
+    /// restarted once fewer than `k` are left.
+    KOfN(usize),
+    /// The first child is active, the others are hot spares. When the active
+    /// child fails the next live spare is promoted and the failed child is
+    /// restarted as a spare.
+    Standby,
+}
+
+/// Asks the garrison to change the links, monitors and supervision groups
+/// of a process.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Request {
+    Link(ProcessId),
+    Unlink(ProcessId),
+    Monitor(ProcessId, MonitorRef),
+    /// Drops every monitor this process holds on the target.
+    Demonitor(ProcessId),
+    /// Makes this process the supervisor of a new group of children.
+    Supervise(Strategy, Vec<ProcessId>),
+    /// Installs a brigadier into the garrison of the key. Generals run
+    /// outside garrisons, their host serves it.
+    InstallBrigadier(PublicKey),
+}
+
+/// The part of a process the garrison manages: who it is, how it treats
+/// exit signals and what it asked for since the garrison last looked.
+#[derive(Clone, Debug, Default)]
+pub struct ProcessContext {
+    pub pid: ProcessId,
+    /// Public key of the garrison running the process.
+    pub garrison: Option<PublicKey>,
+    /// The identity of the process, derived by its garrison from the
+    /// garrison identity and the pid so that no two processes share a key.
+    pub identity: Option<Identity>,
+    /// Turn exit signals from links into mailbox messages instead of exiting.
+    pub trap_exit: bool,
+    /// Keys allowed to link to this process, anyone when `None`.
+    pub link_auth: Option<Vec<PublicKey>>,
+    pub requests: Vec<Request>,
+    pub mailbox: VecDeque<Signal>,
+    next_monitor: u64,
+}
+
+impl ProcessContext {
+    pub fn new(pid: ProcessId, identity: Identity) -> Self {
+        ProcessContext { pid, identity: Some(identity), ..Default::default() }
+    }
+
+    /// Public key of the process identity.
+    pub fn who(&self) -> Option<PublicKey> {
+        self.identity.as_ref().map(Identity::public_key)
+    }
+
+    pub fn monitor(&mut self, target: ProcessId) -> MonitorRef {
+        let monitor = MonitorRef(self.next_monitor);
+        self.next_monitor += 1;
+        self.requests.push(Request::Monitor(target, monitor));
+        monitor
+    }
+
+    /// Whether a process with identity `who` may link to this one.
+    pub fn allows_link(&self, who: Option<PublicKey>) -> bool {
+        match (&self.link_auth, who) {
+            (None, _) => true,
+            (Some(list), Some(who)) => list.contains(&who),
+            (Some(_), None) => false,
+        }
+    }
+}
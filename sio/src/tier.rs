@@ -0,0 +1,106 @@
+//! The tiers a garrison can run at.
+//!
+//! Every tier shares one value type, one literal type and the NIFs that
+//! compute. The NIFs acting on other processes are capabilities, following
+//! the ranks:
+//!
+//! - generals install brigadiers into garrisons,
+//! - brigadiers spawn and supervise majors,
+//! - majors spawn and supervise corporals,
+//! - corporals only compute and message.
+//!
+//! Every officer also links, monitors and traps exits. Threads are open to
+//! every tier, the frontend lowers them to the `spawn_thread` NIF rather than
+//! to `spawn`. [`CAPABILITIES`] lists the capabilities, [`create_env`](crate::create_env)
+//! leaves out the ones a tier lacks and the frontend rejects calls to them
+//! from modules of that tier.
+use crate::key::KeyTier;
+
+const OFFICERS: &[KeyTier] = &[KeyTier::General, KeyTier::Brigadier, KeyTier::Major];
+/// The tiers running processes under them.
+const COMMANDERS: &[KeyTier] = &[KeyTier::Brigadier, KeyTier::Major];
+
+/// NIFs only some tiers may call, with the tiers that may. Every other NIF is
+/// available to every tier.
+pub const CAPABILITIES: &[(&str, &[KeyTier])] = &[
+    ("trap_exit", OFFICERS),
+    ("link_with", OFFICERS),
+    ("delink", OFFICERS),
+    ("monitor", OFFICERS),
+    ("demonitor", OFFICERS),
+    ("link_auth", OFFICERS),
+    ("link_auth_list", OFFICERS),
+    ("spawn", COMMANDERS),
+    ("supervise", COMMANDERS),
+    ("supervise_k_of_n", COMMANDERS),
+    ("install_brigadier", &[KeyTier::General]),
+];
+
+/// Whether modules of `tier` may call the NIF `name`.
+pub fn allows(tier: KeyTier, name: &str) -> bool {
+    CAPABILITIES
+        .iter()
+        .find(|(nif, _)| *nif == name)
+        .map_or(true, |(_, tiers)| tiers.contains(&tier))
+}
+
+pub trait Tier {
+    const KEY_TIER: KeyTier;
+    const NAME: &'static str;
+}
+
+pub struct General;
+pub struct Brigadier;
+pub struct Major;
+pub struct Corporal;
+
+impl Tier for General {
+    const KEY_TIER: KeyTier = KeyTier::General;
+    const NAME: &'static str = "general";
+}
+
+impl Tier for Brigadier {
+    const KEY_TIER: KeyTier = KeyTier::Brigadier;
+    const NAME: &'static str = "brigadier";
+}
+
+impl Tier for Major {
+    const KEY_TIER: KeyTier = KeyTier::Major;
+    const NAME: &'static str = "major";
+}
+
+impl Tier for Corporal {
+    const KEY_TIER: KeyTier = KeyTier::Corporal;
+    const NAME: &'static str = "corporal";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corporals_only_compute() {
+        assert!(allows(KeyTier::Corporal, "+"));
+        assert!(allows(KeyTier::Corporal, "receive_signal"));
+        assert!(!allows(KeyTier::Corporal, "monitor"));
+        assert!(allows(KeyTier::Major, "monitor"));
+        assert!(allows(KeyTier::General, "trap_exit"));
+        assert!(!allows(KeyTier::Corporal, "supervise"));
+        assert!(allows(KeyTier::Major, "supervise_k_of_n"));
+        assert!(!allows(KeyTier::Corporal, "spawn"));
+        assert!(allows(KeyTier::Corporal, "spawn_thread"));
+    }
+
+    #[test]
+    fn capabilities_follow_the_ranks() {
+        assert!(allows(KeyTier::General, "install_brigadier"));
+        assert!(!allows(KeyTier::General, "spawn"));
+        assert!(!allows(KeyTier::General, "supervise"));
+        for tier in [KeyTier::Brigadier, KeyTier::Major] {
+            assert!(!allows(tier, "install_brigadier"));
+            assert!(allows(tier, "spawn"));
+            assert!(allows(tier, "supervise"));
+        }
+        assert!(!allows(KeyTier::Corporal, "install_brigadier"));
+    }
+}
@@ -0,0 +1,250 @@
+use werbolg_core::{ConstrId, ValueFun};
+use werbolg_exec::{ExecutionError, Valuable, ValueKind};
+use single_assignment_store::Unify;
+use crate::dataflow::VarId;
+use crate::key::PublicKey;
+use crate::list::List;
+use crate::process::Pid;
+use alloc::rc::Rc;
+use core::fmt;
+
+/// The integer of a literal without a suffix.
+pub type ValueInt = i64;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Unit,
+    /// A dataflow variable, see [`crate::dataflow`].
+    Var(VarId),
+    Bool(bool),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F64(f64),
+    /// Shared between copies, which slicing and concatenation never modify.
+    Str(Rc<str>),
+    List(List<Value>),
+    Key(PublicKey),
+    Pid(Pid),
+    Fun(ValueFun),
+}
+
+impl Value {
+    fn desc(&self) -> ValueKind {
+        match self {
+            Value::Unit => UNIT_KIND,
+            Value::Var(_) => VAR_KIND,
+            Value::Bool(_) => BOOL_KIND,
+            Value::I8(_) => I8_KIND,
+            Value::I16(_) => I16_KIND,
+            Value::I32(_) => I32_KIND,
+            Value::I64(_) => I64_KIND,
+            Value::U8(_) => U8_KIND,
+            Value::U16(_) => U16_KIND,
+            Value::U32(_) => U32_KIND,
+            Value::U64(_) => U64_KIND,
+            Value::F64(_) => F64_KIND,
+            Value::Str(_) => STR_KIND,
+            Value::List(_) => LIST_KIND,
+            Value::Key(_) => KEY_KIND,
+            Value::Pid(_) => PID_KIND,
+            Value::Fun(_) => FUN_KIND,
+        }
+    }
+}
+
+pub const UNIT_KIND: ValueKind = "    unit";
+pub const VAR_KIND: ValueKind = "     var";
+pub const BOOL_KIND: ValueKind = "    bool";
+pub const I8_KIND: ValueKind = "      i8";
+pub const I16_KIND: ValueKind = "     i16";
+pub const I32_KIND: ValueKind = "     i32";
+pub const I64_KIND: ValueKind = "     i64";
+pub const U8_KIND: ValueKind = "      u8";
+pub const U16_KIND: ValueKind = "     u16";
+pub const U32_KIND: ValueKind = "     u32";
+pub const U64_KIND: ValueKind = "     u64";
+pub const F64_KIND: ValueKind = "     f64";
+pub const STR_KIND: ValueKind = "     str";
+pub const LIST_KIND: ValueKind = "    list";
+pub const KEY_KIND: ValueKind = "     key";
+pub const PID_KIND: ValueKind = "     pid";
+pub const FUN_KIND: ValueKind = "     fun";
+
+impl Valuable for Value {
+    fn descriptor(&self) -> werbolg_exec::ValueKind {
+        self.desc()
+    }
+
+    fn conditional(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    fn fun(&self) -> Option<ValueFun> {
+        match self {
+            Self::Fun(valuefun) => Some(*valuefun),
+            _ => None,
+        }
+    }
+
+    fn structure(&self) -> Option<(ConstrId, &[Self])> {
+        None
+    }
+
+    fn index(&self, index: usize) -> Option<&Self> {
+        match self {
+            Value::List(list) => list.get(index),
+            _ => None,
+        }
+    }
+
+    fn make_fun(fun: ValueFun) -> Self {
+        Value::Fun(fun)
+    }
+
+    fn make_dummy() -> Self {
+        Value::Unit
+    }
+}
+
+impl Unify<VarId> for Value {
+    fn key(&self) -> Option<&VarId> {
+        match self {
+            Value::Var(id) => Some(id),
+            _ => None,
+        }
+    }
+}
+
+/// The text `to_string` and `format` produce: strings without quotes, floats
+/// always with a fractional part.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Unit => write!(f, "()"),
+            Value::Var(var) => write!(f, "_{}", var),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::I8(n) => write!(f, "{}", n),
+            Value::I16(n) => write!(f, "{}", n),
+            Value::I32(n) => write!(f, "{}", n),
+            Value::I64(n) => write!(f, "{}", n),
+            Value::U8(n) => write!(f, "{}", n),
+            Value::U16(n) => write!(f, "{}", n),
+            Value::U32(n) => write!(f, "{}", n),
+            Value::U64(n) => write!(f, "{}", n),
+            Value::F64(x) => write!(f, "{:?}", x),
+            Value::Str(s) => write!(f, "{}", s),
+            Value::List(list) => {
+                write!(f, "[")?;
+                for (i, v) in list.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                write!(f, "]")
+            }
+            Value::Key(k) => write!(f, "{}", k),
+            Value::Pid(pid) => write!(f, "{}", pid),
+            Value::Fun(_) => write!(f, "<fun>"),
+        }
+    }
+}
+
+macro_rules! from_number {
+    ($($t:ty => $variant:ident),*) => {
+        $(
+            impl From<$t> for Value {
+                fn from(n: $t) -> Self {
+                    Value::$variant(n)
+                }
+            }
+        )*
+    };
+}
+
+from_number!(i8 => I8, i16 => I16, i32 => I32, i64 => I64, u8 => U8, u16 => U16, u32 => U32, u64 => U64, f64 => F64);
+
+impl Value {
+    pub fn is_int(&self) -> bool {
+        matches!(
+            self,
+            Value::I8(_)
+                | Value::I16(_)
+                | Value::I32(_)
+                | Value::I64(_)
+                | Value::U8(_)
+                | Value::U16(_)
+                | Value::U32(_)
+                | Value::U64(_)
+        )
+    }
+
+    pub fn float(&self) -> Result<f64, ExecutionError> {
+        match self {
+            Value::F64(x) => Ok(*x),
+            _ => Err(ExecutionError::ValueKindUnexpected {
+                value_expected: F64_KIND,
+                value_got: self.descriptor(),
+            }),
+        }
+    }
+
+    pub fn str(&self) -> Result<&Rc<str>, ExecutionError> {
+        match self {
+            Value::Str(s) => Ok(s),
+            _ => Err(ExecutionError::ValueKindUnexpected {
+                value_expected: STR_KIND,
+                value_got: self.descriptor(),
+            }),
+        }
+    }
+
+    pub fn list(&self) -> Result<&List<Value>, ExecutionError> {
+        match self {
+            Value::List(list) => Ok(list),
+            _ => Err(ExecutionError::ValueKindUnexpected {
+                value_expected: LIST_KIND,
+                value_got: self.descriptor(),
+            }),
+        }
+    }
+
+    pub fn u64(&self) -> Result<u64, ExecutionError> {
+        match self {
+            Value::U64(o) => Ok(*o),
+            _ => Err(ExecutionError::ValueKindUnexpected {
+                value_expected: U64_KIND,
+                value_got: self.descriptor(),
+            }),
+        }
+    }
+
+    pub fn key(&self) -> Result<PublicKey, ExecutionError> {
+        match self {
+            Value::Key(k) => Ok(*k),
+            _ => Err(ExecutionError::ValueKindUnexpected {
+                value_expected: KEY_KIND,
+                value_got: self.descriptor(),
+            }),
+        }
+    }
+
+    pub fn pid(&self) -> Result<Pid, ExecutionError> {
+        match self {
+            Value::Pid(pid) => Ok(*pid),
+            _ => Err(ExecutionError::ValueKindUnexpected {
+                value_expected: PID_KIND,
+                value_got: self.descriptor(),
+            }),
+        }
+    }
+}
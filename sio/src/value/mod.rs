@@ -1,11 +0,0 @@
-pub mod general;
-pub mod brigadier;
-pub mod major;
-pub mod corporal;
-
-pub use {
-    general::GeneralValue,
-    brigadier::BrigadierValue,
-    major::MajorValue,
-    corporal::CorporalValue,
-};